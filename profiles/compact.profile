@@ -0,0 +1,12 @@
+# A smaller machine with 1 KB of RAM and 16 KB of banked ROM, used by the introductory modules.
+name = compact
+
+ram.base = 0x0000
+ram.size = 0x0400       # 1 KB
+
+rom.base = 0xC000
+rom.size = 0x4000       # 16 KB window split into two 8 KB banks
+
+stack = 0x03FF
+trap_table = 0x0100     # The default table at 0x1C00 is beyond this RAM
+reset_vector = 0xFFFE
@@ -0,0 +1,15 @@
+# The standard Chiiko machine. Every value here is also the built-in default.
+name = standard
+
+ram.base = 0x0000
+ram.size = 0x2000       # 8 KB
+
+rom.base = 0x8000
+rom.size = 0x8000       # 32 KB window: switchable bank at 0x8000, fixed bank at 0xC000
+
+stack = 0x1FFF
+trap_table = 0x1C00     # TRAP n jumps through the two byte vector at trap_table + 2n
+host_calls = 0          # 1 lets the emulator answer traps 0xF0-0xF2
+reset_vector = 0xFFFE   # Must be in the fixed bank
+
+device.bank_select = 0x7FFF
@@ -7,15 +7,49 @@ pub enum AssemblyError {
     MissingFile,
     TooManyOperands(String),
     NoOperandString,
+    MissingOperand(String),
+    UndefinedSymbol(String),
+    InvalidRegister(String),
+    InvalidOperand(String),
+    ValueOutOfRange(String),
+    InvalidBank(String),
+    OverlappingCode(u16),
+    OverlappingResetVector(u16),
+    ImageTooLarge,
+    Unsupported(String),
+    UnknownMnemonic(String),
+    InvalidDirective(String),
+    ModeConflict(String),
+    InvalidMacro(String),
+    ParseError(String),
 }
 
 impl fmt::Display for AssemblyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AssemblyError::CannotReadFile(filename) => 
+            AssemblyError::CannotReadFile(filename) =>
                 write!(f, "Cannot read file: {:?}", filename),
             AssemblyError::MissingFile => write!(f, "Missing input file name"),
             AssemblyError::NoOperandString => write!(f, "Cannot extract string from operand"),
+            AssemblyError::TooManyOperands(mnemonic) => write!(f, "Too many operands for {}", mnemonic),
+            AssemblyError::MissingOperand(mnemonic) => write!(f, "Missing operand for {}", mnemonic),
+            AssemblyError::UndefinedSymbol(name) => write!(f, "Undefined symbol: {}", name),
+            AssemblyError::InvalidRegister(id) => write!(f, "Invalid Register Code: {}", id),
+            AssemblyError::InvalidOperand(operand) => write!(f, "Invalid operand: {}", operand),
+            AssemblyError::ValueOutOfRange(operand) => write!(f, "Value out of range: {}", operand),
+            AssemblyError::InvalidBank(bank) => write!(f, "Invalid ROM bank: {}", bank),
+            AssemblyError::OverlappingCode(address) =>
+                write!(f, "Code overlaps previously assembled bytes at {:#06X}", address),
+            AssemblyError::OverlappingResetVector(address) =>
+                write!(f, "Code overlaps the reset vector at {:#06X}", address),
+            AssemblyError::ImageTooLarge => write!(f, "Code runs past the end of the address space"),
+            AssemblyError::Unsupported(feature) => write!(f, "Unsupported: {}", feature),
+            AssemblyError::UnknownMnemonic(mnemonic) => write!(f, "Unknown mnemonic: {}", mnemonic),
+            AssemblyError::InvalidDirective(header) => write!(f, "Invalid directive: {}", header),
+            AssemblyError::ModeConflict(mnemonic) =>
+                write!(f, "Operands do not fit the mode of {}", mnemonic),
+            AssemblyError::InvalidMacro(message) => write!(f, "Macro error: {}", message),
+            AssemblyError::ParseError(message) => write!(f, "{}", message),
             _ => write!(f, "Invalid Assembly Error")
         }
     }
@@ -0,0 +1,483 @@
+use std::collections::{HashMap, HashSet};
+use crate::assembler::assembly_error::AssemblyError;
+use crate::assembler::encoder::{program::Program, program::Segment, symbol_table::SymbolTable,
+    symbol_table::Symbol, syntax_checker::SyntaxChecker,
+};
+use crate::assembler::parser::{Parser, ast_node::ASTNode, ast_node::MacroNode,
+    assembler_operand::AssemblerOperand,
+};
+use crate::chiiko::MachineProfile;
+use crate::chiiko::components::{cpu_operand::CpuOperand, instruction::Instruction};
+use crate::codec;
+use crate::mode::{Mode, mode_group::ModeGroup};
+use crate::operation::Operation;
+
+const MINIMUM_BANKS: usize = 2;
+const HIGHEST_SWITCHABLE_BANK: u16 = 0xFE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bank {
+    Switchable(u8),
+    Fixed,
+}
+
+// A single instruction after its mode has been settled, but before operands are resolved
+struct Plan<'a> {
+    operation: Operation,
+    groups: (ModeGroup, ModeGroup),
+    explicit_mode: bool,
+    relative: bool, // The left operand is a branch displacement
+    operands: [Option<&'a AssemblerOperand>; 2],
+}
+
+impl Plan<'_> {
+    fn size(&self) -> u16 {
+        let mode_size = if self.explicit_mode { 1 } else { 0 };
+        1 + mode_size + self.groups.0.operand_size() + self.groups.1.operand_size()
+    }
+}
+
+pub struct Encoder<'a> {
+    table: &'a mut SymbolTable,
+    code_origin: u16, // Start of the switchable bank window
+    fixed_origin: u16, // Start of the fixed bank window
+    reset_vector: u16, // Two bytes in the fixed bank that code must leave free
+    bank_size: usize,
+    bank: Bank,
+    locations: HashMap<Bank, u16>,
+    rom: Vec<u8>,
+    written: Vec<bool>,
+    addresses: Vec<u16>,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn encode(ast: &[ASTNode], table: &'a mut SymbolTable) -> Result<Program, AssemblyError> {
+        Self::encode_for(ast, table, &MachineProfile::default())
+    }
+
+    // Lays the program out for the ROM window of a particular machine
+    pub fn encode_for(
+    ast: &[ASTNode],
+    table: &'a mut SymbolTable,
+    profile: &MachineProfile
+    ) -> Result<Program, AssemblyError> {
+        let mut encoder = Self {
+            table,
+            code_origin: profile.rom_base,
+            fixed_origin: profile.fixed_bank_base(),
+            reset_vector: profile.reset_vector_address,
+            bank_size: profile.bank_size(),
+            bank: Bank::Switchable(0),
+            locations: HashMap::new(),
+            rom: Vec::new(),
+            written: Vec::new(),
+            addresses: Vec::new(),
+        };
+
+        let bank_count = encoder.layout(ast)?;
+        encoder.allocate_variables(ast);
+
+        encoder.rom = vec![0; bank_count * encoder.bank_size];
+        encoder.written = vec![false; bank_count * encoder.bank_size];
+        encoder.bank = Bank::Switchable(0);
+        encoder.locations.clear();
+
+        let data = encoder.emit(ast)?;
+
+        Ok(Program { rom: encoder.rom, data, addresses: encoder.addresses })
+    }
+
+    // First pass: assigns addresses to labels and counters, returns the number of banks needed
+    fn layout(&mut self, ast: &[ASTNode]) -> Result<usize, AssemblyError> {
+        let mut highest_bank = 0;
+
+        for node in ast {
+            match node {
+                ASTNode::Label(label) => {
+                    let location = self.location();
+                    self.table.table.insert(label.to_string(), Symbol::Address(location));
+                },
+                ASTNode::Instruction { mnemonic, mode, operands } => {
+                    let plan = Self::plan(mnemonic, mode, operands)?;
+                    let next = self.advance(plan.size())?;
+
+                    for operand in operands {
+                        if let AssemblerOperand::StartCount(id) = operand {
+                            self.table.table.insert(
+                                format!("&START_COUNT<{}>", id),
+                                Symbol::Counter { start: next, end: next }
+                            );
+                        }
+                    }
+                },
+                ASTNode::Macro(MacroNode::EndCount { id }) => {
+                    let location = self.location();
+                    let key = format!("&START_COUNT<{}>", id);
+
+                    match self.table.table.get_mut(&key) {
+                        Some(Symbol::Counter { end, .. }) => *end = location,
+                        _ => return Err(AssemblyError::UnmatchedBracket),
+                    }
+                },
+                ASTNode::Macro(MacroNode::BankSelect(bank)) => {
+                    self.bank = Self::lookup_bank(bank)?;
+
+                    if let Bank::Switchable(number) = self.bank {
+                        highest_bank = highest_bank.max(number as usize);
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        Ok((highest_bank + 2).max(MINIMUM_BANKS))
+    }
+
+    // Second pass: writes instruction bytes into the ROM image and collects RAM data
+    fn emit(&mut self, ast: &[ASTNode]) -> Result<Vec<Segment>, AssemblyError> {
+        let mut data = Vec::new();
+
+        for node in ast {
+            match node {
+                ASTNode::Instruction { mnemonic, mode, operands } => {
+                    let plan = Self::plan(mnemonic, mode, operands)?;
+                    let bytes = self.assemble(&plan)?;
+                    self.addresses.push(self.location());
+                    self.place(&bytes)?;
+                },
+                ASTNode::Macro(MacroNode::BankSelect(bank)) => self.bank = Self::lookup_bank(bank)?,
+                ASTNode::Macro(MacroNode::ArrayData { address, elements }) => {
+                    let mut bytes = Vec::new();
+                    for element in elements {
+                        match element {
+                            AssemblerOperand::NamedElement { value, .. } => bytes.push(*value),
+                            AssemblerOperand::Identifier(_) => bytes.push(0),
+                            AssemblerOperand::Number(number) => bytes.push(Self::byte(*number, element)?),
+                            AssemblerOperand::DirectAddress(_) | AssemblerOperand::JumpAddress(_) => {
+                                bytes.extend(self.resolve(element)?.to_be_bytes())
+                            },
+                            _ => return Err(AssemblyError::InvalidOperand(format!("{:?}", element))),
+                        }
+                    }
+
+                    data.push(Segment { address: Self::data_address(address)?, bytes });
+                },
+                ASTNode::Macro(MacroNode::VariableData { address, pointer: Some(pointer), .. }) => {
+                    let bytes = self.resolve(pointer)?.to_be_bytes().to_vec();
+                    data.push(Segment { address: Self::data_address(address)?, bytes });
+                },
+                ASTNode::Macro(MacroNode::StringData { address, value }) => {
+                    let mut bytes = value.string()?.into_bytes();
+                    bytes.push(0); // Null terminated for PRNT
+
+                    data.push(Segment { address: Self::data_address(address)?, bytes });
+                },
+                ASTNode::Macro(MacroNode::LinkData(filename)) => {
+                    return Err(AssemblyError::Unsupported(format!("LINK {}", filename)))
+                },
+                _ => ()
+            }
+        }
+
+        Ok(data)
+    }
+
+    // Undeclared `$NAME` operands are given one byte each after the declared data
+    fn allocate_variables(&mut self, ast: &[ASTNode]) {
+        let mut declared: HashSet<String> = HashSet::new();
+        let mut next_free: usize = 0;
+
+        for node in ast {
+            match node {
+                ASTNode::Macro(MacroNode::VariableData { address, label, pointer }) => {
+                    if let (Ok(name), Ok(address)) = (label.string(), Self::data_address(address)) {
+                        declared.insert(name);
+                        let width = pointer.as_ref().map_or(1, |pointer| pointer.data_width());
+                        next_free = next_free.max(address as usize + width);
+                    }
+                },
+                ASTNode::Macro(MacroNode::ArrayData { address, elements }) => {
+                    for element in elements {
+                        if let Ok(name) = element.string() {
+                            declared.insert(name);
+                        }
+                    }
+
+                    if let Ok(address) = Self::data_address(address) {
+                        let width: usize = elements.iter().map(|element| element.data_width()).sum();
+                        next_free = next_free.max(address as usize + width);
+                    }
+                },
+                ASTNode::Macro(MacroNode::StringData { address, value }) => {
+                    if let (Ok(string), Ok(address)) = (value.string(), Self::data_address(address)) {
+                        next_free = next_free.max(address as usize + string.len() + 1);
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        let mut implicit: Vec<String> = self.table.table
+            .iter()
+            .filter(|(name, symbol)| {
+                matches!(symbol, Symbol::Variable { .. }) && !declared.contains(*name)
+            })
+            .map(|(name, _)| name.to_string())
+            .collect();
+        implicit.sort();
+
+        for name in implicit {
+            self.table.table.insert(name, Symbol::Variable { address: next_free as u16, value: 0 });
+            next_free += 1;
+        }
+    }
+
+    fn plan<'b>(
+    mnemonic: &str,
+    mode: &Option<(ModeGroup, ModeGroup)>,
+    operands: &'b [AssemblerOperand]
+    ) -> Result<Plan<'b>, AssemblyError> {
+        let operation = Operation::lookup_mnemonic(mnemonic)
+            .ok_or_else(|| AssemblyError::UnknownMnemonic(mnemonic.to_string()))?;
+        let default_mode = Mode::groups_from_byte(operation.default_mode);
+        let set_mode = mode.clone().unwrap_or_else(Mode::default_tuple);
+        let mut inferred_mode = SyntaxChecker::infer_mode(&operands.to_vec());
+
+        // A `:LABEL` given to a relative branch is encoded as a displacement byte
+        let relative = operation.is_relative_branch();
+        if relative && inferred_mode.0 == ModeGroup::JumpAddress {
+            inferred_mode.0 = ModeGroup::Value;
+        }
+
+        let groups = (
+            Self::settle_group(set_mode.0, default_mode.0.clone(), inferred_mode.0),
+            Self::settle_group(set_mode.1, default_mode.1.clone(), inferred_mode.1),
+        );
+
+        // Every group past this point has a mode nibble to encode
+        let encodable = |group: &ModeGroup| *group != ModeGroup::Error && Mode::lookup_group(group).is_some();
+        if !encodable(&groups.0) || !encodable(&groups.1) {
+            return Err(AssemblyError::InvalidOperand(format!("{} {:?}", mnemonic, operands)))
+        }
+
+        let mut remaining = operands.iter();
+        let mut take = |group: &ModeGroup| -> Result<Option<&'b AssemblerOperand>, AssemblyError> {
+            if group.operand_size() == 0 {
+                Ok(None)
+            } else {
+                remaining.next()
+                    .map(Some)
+                    .ok_or_else(|| AssemblyError::MissingOperand(mnemonic.to_string()))
+            }
+        };
+        let left = take(&groups.0)?;
+        let right = take(&groups.1)?;
+
+        if remaining.next().is_some() {
+            return Err(AssemblyError::TooManyOperands(mnemonic.to_string()))
+        }
+
+        let explicit_mode = groups != default_mode;
+
+        let relative = relative && groups.0 == ModeGroup::Value;
+
+        Ok(Plan { operation, groups, explicit_mode, relative, operands: [left, right] })
+    }
+
+    fn settle_group(set: ModeGroup, default: ModeGroup, inferred: ModeGroup) -> ModeGroup {
+        if set != ModeGroup::Default {
+            set
+        } else if Mode::is_compatible(default.clone(), inferred.clone()) {
+            default
+        } else {
+            inferred
+        }
+    }
+
+    fn assemble(&self, plan: &Plan) -> Result<Vec<u8>, AssemblyError> {
+        let mode = Mode::from_group(&plan.groups.0).into_nibble() << 4 |
+            Mode::from_group(&plan.groups.1).into_nibble();
+
+        let left = match (plan.relative, plan.operands[0]) {
+            (true, Some(operand)) => {
+                let next = self.location().wrapping_add(plan.size());
+                CpuOperand::Value(self.displacement(operand, next)?)
+            },
+            _ => self.cpu_operand(&plan.groups.0, plan.operands[0])?,
+        };
+        let right = self.cpu_operand(&plan.groups.1, plan.operands[1])?;
+
+        Ok(codec::encode(&Instruction::new(plan.operation, mode, left, right)))
+    }
+
+    fn cpu_operand(&self, group: &ModeGroup, operand: Option<&AssemblerOperand>) -> Result<CpuOperand, AssemblyError> {
+        let operand = match operand {
+            Some(operand) => operand,
+            None => return Ok(codec::implicit_operand(Mode::from_group(group).into_nibble())),
+        };
+
+        match group {
+            ModeGroup::Register | ModeGroup::IndirectRegister => {
+                let id = operand.string()?;
+                let code = AssemblerOperand::register_code(&id)
+                    .ok_or(AssemblyError::InvalidRegister(id))?;
+                Ok(if *group == ModeGroup::Register {
+                    CpuOperand::Register(code)
+                } else {
+                    CpuOperand::IndirectRegister(code)
+                })
+            },
+            ModeGroup::Value => Ok(CpuOperand::Value(Self::byte(self.resolve(operand)?, operand)?)),
+            ModeGroup::ZeroPage => Ok(CpuOperand::ZeroPageAddress(Self::byte(self.resolve(operand)?, operand)?)),
+            ModeGroup::IndirectZeroPage => {
+                Ok(CpuOperand::IndirectZeroPageAddress(Self::byte(self.resolve(operand)?, operand)?))
+            },
+            ModeGroup::DirectAddress => Ok(CpuOperand::MemoryAddress(self.resolve(operand)?)),
+            ModeGroup::IndirectAddress => Ok(CpuOperand::IndirectMemoryAddress(self.resolve(operand)?)),
+            ModeGroup::JumpAddress => Ok(CpuOperand::JumpAddress(self.resolve(operand)?)),
+            ModeGroup::StackRelative => match operand {
+                AssemblerOperand::StackOffset(offset) => i8::try_from(*offset)
+                    .map(|offset| CpuOperand::StackRelative(offset as u8))
+                    .map_err(|_| AssemblyError::ValueOutOfRange(format!("%{}", offset))),
+                _ => Err(AssemblyError::InvalidOperand(format!("{:?}", operand))),
+            },
+            ModeGroup::IndexedAddress | ModeGroup::IndexedZeroPage => {
+                let index = match operand {
+                    AssemblerOperand::Indexed { index, .. } => index,
+                    _ => return Err(AssemblyError::InvalidOperand(format!("{:?}", operand))),
+                };
+                let code = AssemblerOperand::register_code(index)
+                    .ok_or_else(|| AssemblyError::InvalidRegister(index.to_string()))?;
+
+                Ok(if *group == ModeGroup::IndexedZeroPage {
+                    CpuOperand::IndexedZeroPage(Self::byte(self.resolve(operand)?, operand)?, code)
+                } else {
+                    CpuOperand::IndexedAddress(self.resolve(operand)?, code)
+                })
+            },
+            _ => Ok(codec::implicit_operand(Mode::from_group(group).into_nibble())),
+        }
+    }
+
+    fn displacement(&self, operand: &AssemblerOperand, next: u16) -> Result<u8, AssemblyError> {
+        let displacement = match operand {
+            AssemblerOperand::JumpAddress(_) => self.resolve(operand)? as i32 - next as i32,
+            AssemblerOperand::StartCount(_) => self.resolve(operand)? as i32,
+            // A plain number is already a displacement byte
+            _ => return Self::byte(self.resolve(operand)?, operand),
+        };
+
+        i8::try_from(displacement)
+            .map(|displacement| displacement as u8)
+            .map_err(|_| AssemblyError::ValueOutOfRange(format!("Branch to {:?} is {} bytes away", operand, displacement)))
+    }
+
+    fn resolve(&self, operand: &AssemblerOperand) -> Result<u16, AssemblyError> {
+        match operand {
+            AssemblerOperand::Number(number) => Ok(*number),
+            AssemblerOperand::StartCount(id) => match self.table.table.get(&format!("&START_COUNT<{}>", id)) {
+                Some(Symbol::Counter { start, end }) => Ok(end.wrapping_sub(*start)),
+                _ => Err(AssemblyError::UnmatchedBracket),
+            },
+            AssemblerOperand::Identifier(name) | AssemblerOperand::DirectAddress(name) |
+            AssemblerOperand::IndirectAddress(name) | AssemblerOperand::JumpAddress(name) |
+            AssemblerOperand::Indexed { base: name, .. } => {
+                if let Ok(number) = Parser::normalize_number(name) {
+                    return u16::try_from(number)
+                        .map_err(|_| AssemblyError::ValueOutOfRange(name.to_string()))
+                }
+
+                match self.table.table.get(name) {
+                    Some(Symbol::Variable { address, .. }) | Some(Symbol::Address(address)) => Ok(*address),
+                    _ => Err(AssemblyError::UndefinedSymbol(name.to_string())),
+                }
+            },
+            _ => Err(AssemblyError::InvalidOperand(format!("{:?}", operand))),
+        }
+    }
+
+    fn byte(value: u16, operand: &AssemblerOperand) -> Result<u8, AssemblyError> {
+        u8::try_from(value).map_err(|_| AssemblyError::ValueOutOfRange(format!("{:?}", operand)))
+    }
+
+    fn data_address(address: &AssemblerOperand) -> Result<u16, AssemblyError> {
+        let id = address.string()?;
+        Parser::normalize_number(&id)
+            .ok()
+            .and_then(|number| u16::try_from(number).ok())
+            .ok_or(AssemblyError::InvalidOperand(id))
+    }
+
+    fn lookup_bank(bank: &AssemblerOperand) -> Result<Bank, AssemblyError> {
+        match bank {
+            AssemblerOperand::Number(number) if *number <= HIGHEST_SWITCHABLE_BANK => {
+                Ok(Bank::Switchable(*number as u8))
+            },
+            AssemblerOperand::Identifier(id) if id == "FIXED" => Ok(Bank::Fixed),
+            _ => Err(AssemblyError::InvalidBank(format!("{:?}", bank))),
+        }
+    }
+
+    fn location(&self) -> u16 {
+        let origin = match self.bank {
+            Bank::Switchable(_) => self.code_origin,
+            Bank::Fixed => self.fixed_origin,
+        };
+
+        *self.locations.get(&self.bank).unwrap_or(&origin)
+    }
+
+    // Moves the location counter past `size` bytes and returns the new location.
+    // Bank 0 may run on into the fixed window so that small programs stay one flat image.
+    fn advance(&mut self, size: u16) -> Result<u16, AssemblyError> {
+        let limit = match self.bank {
+            Bank::Switchable(0) | Bank::Fixed => self.fixed_origin as u32 + self.bank_size as u32,
+            Bank::Switchable(_) => self.fixed_origin as u32,
+        };
+        let start = self.location() as u32;
+        let next = start + size as u32;
+
+        if next > limit {
+            return Err(AssemblyError::InvalidBank(format!("{:?} overflows", self.bank)))
+        }
+
+        // Switchable banks other than 0 end before the fixed window, so only these can reach it
+        let vector = self.reset_vector as u32;
+        let in_fixed_window = matches!(self.bank, Bank::Switchable(0) | Bank::Fixed);
+        if in_fixed_window && start < vector + 2 && vector < next {
+            return Err(AssemblyError::OverlappingResetVector(self.reset_vector))
+        }
+
+        let next = u16::try_from(next).map_err(|_| AssemblyError::ImageTooLarge)?;
+        self.locations.insert(self.bank, next);
+        Ok(next)
+    }
+
+    fn place(&mut self, bytes: &[u8]) -> Result<(), AssemblyError> {
+        let start = self.location();
+        let bank_count = self.rom.len() / self.bank_size;
+
+        for (offset, byte) in bytes.iter().enumerate() {
+            let address = start + offset as u16;
+            let index = match self.bank {
+                _ if address >= self.fixed_origin => {
+                    (bank_count - 1) * self.bank_size + (address - self.fixed_origin) as usize
+                },
+                Bank::Switchable(number) => {
+                    number as usize * self.bank_size + (address - self.code_origin) as usize
+                },
+                Bank::Fixed => return Err(AssemblyError::InvalidBank(format!("Fixed code at {:#06X}", address))),
+            };
+
+            if self.written[index] {
+                return Err(AssemblyError::OverlappingCode(address))
+            }
+
+            self.rom[index] = *byte;
+            self.written[index] = true;
+        }
+
+        self.advance(bytes.len() as u16)?;
+        Ok(())
+    }
+}
@@ -2,6 +2,9 @@ mod core;
 pub mod symbol_table;
 pub mod symbol;
 pub mod syntax_checker;
+pub mod program;
 
 #[cfg(test)]
 mod test;
+
+pub use core::Encoder;
@@ -0,0 +1,14 @@
+// The output of the encoder: a banked ROM image plus the RAM contents the loader must
+// initialise before the program starts.
+#[derive(Debug, Default, PartialEq)]
+pub struct Program {
+    pub rom: Vec<u8>,
+    pub data: Vec<Segment>,
+    pub addresses: Vec<u16>, // Where each instruction starts, in source order
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Segment {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
@@ -22,11 +22,13 @@ impl SymbolTable {
 
         for node in ast_tree {
             match node {
-                ASTNode::Macro(MacroNode::VariableData {address, label}) => {
-                    let number = Parser::normalize_number(&address.string().unwrap())
-                        .unwrap_or_else(|_| panic!("Bad address: {:?}", address));
+                // Malformed data macros are skipped here and reported by the encoder
+                ASTNode::Macro(MacroNode::VariableData {address, label, ..}) => {
+                    let (Some(number), Ok(label)) = (Self::address(address), label.string()) else {
+                        continue
+                    };
                     table.insert(
-                        label.string().unwrap(), 
+                        label, 
                         Symbol::Variable {
                             address: number as u16,
                             value: 0,
@@ -34,10 +36,12 @@ impl SymbolTable {
                     );
                 },
                 ASTNode::Macro(MacroNode::ArrayData {address, elements}) => {
-                    let address = Parser::normalize_number(&address.string().unwrap())
-                        .unwrap_or_else(|_| panic!("Bad address: {:?}", address));
+                    let Some(address) = Self::address(address) else {
+                        continue
+                    };
 
-                    for (offset, element) in elements.iter().enumerate() {
+                    let mut offset = 0;
+                    for element in elements {
                         if let AssemblerOperand::NamedElement { name, value } = element {
                             table.insert(
                                 name.to_string(), 
@@ -55,6 +59,8 @@ impl SymbolTable {
                                 }
                             );
                         }
+
+                        offset += element.data_width();
                     }
                 },
                 ASTNode::Instruction {operands, ..} => {
@@ -65,7 +71,8 @@ impl SymbolTable {
                                 Symbol::Counter { start: 0, end: 0 }
                             );
                         } if let AssemblerOperand::DirectAddress(label) | 
-                            AssemblerOperand::IndirectAddress(label) = operand {
+                            AssemblerOperand::IndirectAddress(label) |
+                            AssemblerOperand::Indexed { base: label, .. } = operand {
                             if Parser::normalize_number(&label).is_ok() || label.len() < 3 {
                                 continue;
                             }
@@ -89,4 +96,8 @@ impl SymbolTable {
 
         Self { table: table }
     }
+
+    fn address(operand: &AssemblerOperand) -> Option<usize> {
+        operand.string().ok().and_then(|id| Parser::normalize_number(&id).ok())
+    }
 }
@@ -11,18 +11,33 @@ pub mod SyntaxChecker {
     pub fn check(source: Vec<ASTNode>) -> Result<(), AssemblyError> {
         for node in source {
             match node {
-                ASTNode::Macro(macro_node) => check_macro(macro_node),
+                ASTNode::Macro(macro_node) => check_macro(macro_node)?,
                 ASTNode::Directive(header) => {
                     if !Operation::is_directive(&header) {
-                        panic!("Invalid Directive: {}", header)
+                        return Err(AssemblyError::InvalidDirective(header))
                     }
                 },
                 ASTNode::Instruction {ref mnemonic, ref mode, ref operands} => {
                     if operands.len() > 2 {
-                        panic!("Syntax Error: Too many operands {:?} {:?}", mnemonic, operands)
+                        return Err(AssemblyError::TooManyOperands(mnemonic.to_string()))
                     }
 
-                    let operation = Operation::from_mnemonic(&mnemonic);
+                    for operand in operands {
+                        match operand {
+                            AssemblerOperand::Error(message) => {
+                                return Err(AssemblyError::InvalidOperand(message.to_string()))
+                            },
+                            AssemblerOperand::Register(id) => {
+                                if !AssemblerOperand::is_valid_register(id) {
+                                    return Err(AssemblyError::InvalidRegister(id.to_string()))
+                                }
+                            },
+                            _ => ()
+                        }
+                    }
+
+                    let operation = Operation::lookup_mnemonic(&mnemonic)
+                        .ok_or_else(|| AssemblyError::UnknownMnemonic(mnemonic.to_string()))?;
                     let default_mode = Mode::groups_from_byte(operation.default_mode);
                     let set_mode = if mode.is_some() {
                         mode.clone().unwrap()
@@ -31,33 +46,13 @@ pub mod SyntaxChecker {
                     };
                     let inferred_mode = infer_mode(&operands);
 
-                    // Diagnostic info
-                    // println!(
-                    // "{:?} \n\tdef: {:?} \n\tset: {:?} \n\tinf: {:?}", 
-                    // operation, default_mode, set_mode, inferred_mode
-                    // );
-
                     if !Mode::are_compatible(default_mode.clone(), inferred_mode.clone()) {
                         if !Mode::are_compatible(set_mode.clone(), inferred_mode.clone()) {
-                            panic!(
-                            "Mode Conflict: {:?} \n\tdef: {:?} \n\tset: {:?} \n\tinf: {:?}", 
-                            node.clone(), default_mode, set_mode, inferred_mode
-                            )
-                        }
-                    }
-
-                    for operand in operands {
-                        match operand {
-                            AssemblerOperand::Register(id) => {
-                                if !AssemblerOperand::is_valid_register(id) {
-                                    panic!("Invalid Register Code: {}", id)
-                                }
-                            }
-                            _ => ()
+                            return Err(AssemblyError::ModeConflict(mnemonic.to_string()))
                         }
                     }
                 },
-                ASTNode::Error(message) => panic!("{}", message),
+                ASTNode::Error(message) => return Err(AssemblyError::ParseError(message)),
                 _ => ()
             }
         }
@@ -65,7 +60,7 @@ pub mod SyntaxChecker {
         Ok(())
     }
 
-    fn infer_mode(operands: &Vec<AssemblerOperand>) -> (ModeGroup, ModeGroup) {
+    pub fn infer_mode(operands: &Vec<AssemblerOperand>) -> (ModeGroup, ModeGroup) {
         if operands.is_empty() {
             Mode::default_tuple()
         } else if operands.len() == 1 {
@@ -107,6 +102,16 @@ pub mod SyntaxChecker {
                 }
             },
             AssemblerOperand::JumpAddress(_) => ModeGroup::JumpAddress,
+            AssemblerOperand::StackOffset(_) => ModeGroup::StackRelative,
+            AssemblerOperand::Indexed { base, index } => {
+                if !matches!(index.as_str(), "I" | "J") {
+                    ModeGroup::Error
+                } else if Parser::normalize_number(base).is_ok_and(|number| number <= 0xFF) {
+                    ModeGroup::IndexedZeroPage
+                } else {
+                    ModeGroup::IndexedAddress
+                }
+            },
                 AssemblerOperand::String(_) | AssemblerOperand::Error(_) |
                 AssemblerOperand::Placeholder(_) | AssemblerOperand::EndCount |
                 AssemblerOperand::NamedElement {..} => ModeGroup::Error,
@@ -115,22 +120,39 @@ pub mod SyntaxChecker {
         }
     }
 
-    fn check_macro(node: MacroNode) {
+    fn check_macro(node: MacroNode) -> Result<(), AssemblyError> {
         match node {
-            MacroNode::LinkData(operand) => (),
             MacroNode::StringData { address, value } => {
                 if !address.is_destination() {
-                    panic!("Macro Error: Invalid STRING destination: {:?}", address);
+                    return Err(AssemblyError::InvalidMacro(format!("Invalid STRING destination: {:?}", address)))
                 }
 
-                if let AssemblerOperand::String(string) = value {
-                    ()
-                } else {
-                    panic!("Macro Error: Invalid STRING source: {:?}", value);
+                if !matches!(value, AssemblerOperand::String(_)) {
+                    return Err(AssemblyError::InvalidMacro(format!("Invalid STRING source: {:?}", value)))
                 }
             }
-            MacroNode::MacroError(message) => panic!("{}", message),
+            MacroNode::BankSelect(bank) => {
+                let valid = match &bank {
+                    AssemblerOperand::Number(number) => *number < 0xFF,
+                    AssemblerOperand::Identifier(id) => id == "FIXED",
+                    _ => false,
+                };
+
+                if !valid {
+                    return Err(AssemblyError::InvalidMacro(format!("Invalid BANK number: {:?}", bank)))
+                }
+            },
+            MacroNode::VariableData { address, .. } | MacroNode::ArrayData { address, .. } => {
+                let numeric = address.string().is_ok_and(|id| Parser::normalize_number(&id).is_ok());
+
+                if !numeric {
+                    return Err(AssemblyError::InvalidMacro(format!("Data address must be a number: {:?}", address)))
+                }
+            },
+            MacroNode::MacroError(message) => return Err(AssemblyError::InvalidMacro(message)),
             _ => ()
         }
+
+        Ok(())
     }
 }
@@ -0,0 +1,215 @@
+use crate::assembler::encoder::{Encoder, program::Program, program::Segment,
+    symbol_table::SymbolTable, symbol_table::Symbol,
+};
+use crate::assembler::assembly_error::AssemblyError;
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::assembler::source_map::SourceMap;
+use crate::chiiko::MachineProfile;
+
+fn assemble(source: &str) -> Result<(Program, SymbolTable), AssemblyError> {
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode(&parser.instructions, &mut table)?;
+
+    Ok((program, table))
+}
+
+#[test]
+fn encodes_default_mode_instructions_without_mode_byte() {
+    let (program, _) = assemble("START:\n  LOAD 5 B\n  ADD B\n  CALL :SUB\n  HALT\nSUB:\n  RTRN\n").unwrap();
+
+    assert_eq!(
+        program.rom[0..10],
+        [0x51, 0x05, 0x01, 0x00, 0x01, 0x30, 0x80, 0x09, 0x70, 0x31]
+    );
+}
+
+#[test]
+fn encodes_explicit_mode_byte_when_operands_differ_from_default() {
+    let (program, _) = assemble("INC B\n").unwrap();
+
+    assert_eq!(program.rom[0..3], [0x85, 0x2A, 0x01]);
+}
+
+#[test]
+fn counter_measures_enclosed_block() {
+    let (program, _) = assemble("ZERO {\n  INC B\n}\nHALT\n").unwrap();
+
+    assert_eq!(program.rom[0..6], [0x22, 0x03, 0x85, 0x2A, 0x01, 0x70]);
+}
+
+#[test]
+fn comments_and_blank_lines_are_not_code() {
+    let (commented, _) = assemble("; Setup\n\nSTART:  ; entry\n  INC B ; once\n\n\n  HALT\n").unwrap();
+    let (plain, _) = assemble("START:\n  INC B\n  HALT\n").unwrap();
+
+    assert_eq!(commented, plain);
+}
+
+#[test]
+fn arrays_continue_across_lines() {
+    let (program, _) = assemble("ARRAY $0x20 [1,\n  2,\n  3]\nHALT\n").unwrap();
+
+    assert_eq!(program.data[0].bytes, [1, 2, 3]);
+}
+
+#[test]
+fn small_program_is_a_flat_two_bank_image() {
+    let (program, _) = assemble("HALT\n").unwrap();
+
+    assert_eq!(program.rom.len(), 0x8000);
+}
+
+#[test]
+fn bank_macro_places_code_in_bank() {
+    let (program, table) = assemble("BANK 1\nFAR:\n  RTRN\nBANK FIXED\nMAIN:\n  CALL :FAR\n").unwrap();
+
+    assert_eq!(program.rom.len(), 3 * 0x4000);
+    assert_eq!(program.rom[0x4000], 0x31);
+    assert_eq!(program.rom[0x8000..0x8003], [0x30, 0x80, 0x00]);
+    assert!(matches!(table.table.get("MAIN"), Some(Symbol::Address(0xC000))));
+}
+
+#[test]
+fn overflowing_switchable_bank_is_an_error() {
+    let source = format!("BANK 1\n{}", "CALL :FAR\n".repeat(0x1600));
+
+    assert!(matches!(assemble(&source), Err(AssemblyError::InvalidBank(_))));
+}
+
+#[test]
+fn overlapping_code_is_an_error() {
+    let source = format!("{}BANK FIXED\nHALT\n", "WAIT\n".repeat(0x4001));
+
+    assert!(matches!(assemble(&source), Err(AssemblyError::OverlappingCode(0xC000))));
+}
+
+#[test]
+fn code_cannot_reach_the_reset_vector() {
+    let fits = format!("BANK FIXED\n{}", "WAIT\n".repeat(0x3FFE));
+    let source = format!("BANK FIXED\n{}", "WAIT\n".repeat(0x3FFF));
+
+    assert!(assemble(&fits).is_ok());
+    assert!(matches!(assemble(&source), Err(AssemblyError::OverlappingResetVector(0xFFFE))));
+}
+
+#[test]
+fn data_macros_become_ram_segments() {
+    let (program, table) = assemble(
+        "ARRAY $0x10 [LIVES=3, 7]\nSTRING $0x20 \"HI\"\nSAVE A $SCORE\n"
+    ).unwrap();
+
+    assert_eq!(program.data, vec![
+        Segment { address: 0x10, bytes: vec![3, 7] },
+        Segment { address: 0x20, bytes: vec![b'H', b'I', 0] },
+    ]);
+    assert!(matches!(table.table.get("SCORE"), Some(Symbol::Variable { address: 0x23, .. })));
+    assert_eq!(program.rom[0..5], [0xD2, 0x26, 0x00, 0x00, 0x23]);
+}
+
+#[test]
+fn data_macros_emit_pointer_words() {
+    let (program, table) = assemble(
+        "VAR $0x10 SCORE\nVAR $0x12 CURSOR $SCORE\nARRAY $0x20 [:START, 5, FLAG]\nSTART:\n  HALT\n"
+    ).unwrap();
+
+    assert_eq!(program.data, vec![
+        Segment { address: 0x12, bytes: vec![0x00, 0x10] },
+        Segment { address: 0x20, bytes: vec![0x80, 0x00, 5, 0] },
+    ]);
+    assert!(matches!(table.table.get("FLAG"), Some(Symbol::Variable { address: 0x23, .. })));
+}
+
+#[test]
+fn encodes_indexed_operands() {
+    let (program, _) = assemble("ADD $0x0120+I\nADD $0x10+J\nARRAY $0x30 [TABLE]\nSUB $TABLE+I\n").unwrap();
+
+    assert_eq!(
+        program.rom[0..13],
+        [0x80, 0xC9, 0x01, 0x20, 0x05, 0x80, 0xD9, 0x10, 0x06, 0x81, 0xC9, 0x00, 0x30]
+    );
+    assert_eq!(program.rom[13], 0x05);
+}
+
+#[test]
+fn indexing_by_other_registers_is_an_error() {
+    assert!(matches!(assemble("ADD $0x0120+B\n"), Err(AssemblyError::InvalidOperand(_))));
+}
+
+#[test]
+fn encodes_bit_instructions() {
+    let (program, _) = assemble("BTST 3\nBSET 0 $0x0140\n").unwrap();
+
+    assert_eq!(program.rom[0..7], [0x18, 0x03, 0x99, 0x16, 0x00, 0x01, 0x40]);
+}
+
+#[test]
+fn encodes_stack_relative_operands() {
+    let (program, _) = assemble("ENTR 2\nADD %5\nSAVE A %-1\nLEAV\n").unwrap();
+
+    assert_eq!(program.rom[0..10], [0x46, 0x02, 0x80, 0xE9, 0x05, 0xD2, 0x2E, 0x00, 0xFF, 0x47]);
+}
+
+#[test]
+fn branch_labels_become_signed_displacements() {
+    let (program, _) = assemble("TOP:\n  ZERO :NEXT\n  WAIT\nNEXT:\n  POS :TOP\n").unwrap();
+
+    assert_eq!(program.rom[0..5], [0x22, 0x01, 0x71, 0x21, 0xFB]);
+}
+
+#[test]
+fn branch_out_of_range_is_an_error() {
+    let source = format!("ZERO :FAR\n{}FAR:\n  HALT\n", "WAIT\n".repeat(200));
+
+    assert!(matches!(assemble(&source), Err(AssemblyError::ValueOutOfRange(_))));
+}
+
+#[test]
+fn undefined_label_is_an_error() {
+    assert!(matches!(assemble("JUMP :NOWHERE\n"), Err(AssemblyError::UndefinedSymbol(_))));
+}
+
+#[test]
+fn encodes_for_profile_rom_window() {
+    let profile = MachineProfile::parse(include_str!("../../../profiles/compact.profile")).unwrap();
+    let mut lexer = Lexer::new("BANK FIXED\nMAIN:\n  JUMP :MAIN\n");
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+
+    let program = Encoder::encode_for(&parser.instructions, &mut table, &profile).unwrap();
+
+    assert_eq!(program.rom.len(), 2 * 0x2000);
+    assert_eq!(program.rom[0x2000..0x2003], [0x32, 0xE0, 0x00]);
+}
+
+#[test]
+fn maps_instructions_to_source_lines() {
+    let source = "; Counts to two\nSTART:\n  INC B\n\n  INC B ; again\n  HALT\n";
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode(&parser.instructions, &mut table).unwrap();
+
+    assert_eq!(program.addresses, [0x8000, 0x8003, 0x8006]);
+
+    let lines: Vec<usize> = parser.node_starts.iter().map(|start| lexer.positions()[*start].0).collect();
+    let map = SourceMap::new(&parser.instructions, &lines, &program);
+
+    assert_eq!(map.line_of(0x8003), Some(5));
+    assert_eq!(map.line_of(0x8004), None);
+    assert_eq!(map.address_of(3), Some((3, 0x8000)));
+    assert_eq!(map.address_of(4), Some((5, 0x8003)));
+    assert_eq!(map.address_of(7), None);
+}
+
+#[test]
+fn unchecked_programs_are_errors_not_panics() {
+    assert!(matches!(assemble("FROB A\n"), Err(AssemblyError::UnknownMnemonic(_))));
+    assert!(assemble("ARRAY\n").is_err());
+}
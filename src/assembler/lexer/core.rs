@@ -13,6 +13,7 @@ pub struct Lexer<'a> {
     source: &'a str,
     cursor: Cursor<'a>,
     mode: Vec<LexerMode>,
+    positions: Vec<(usize, usize)>,
 }
 
 impl<'a> Lexer<'a> {
@@ -21,16 +22,18 @@ impl<'a> Lexer<'a> {
             source: source,
             cursor: Cursor::new(source),
             mode: Vec::with_capacity(4),
+            positions: Vec::new(),
         }
     }
 
-    pub fn lex(&mut self) -> Vec<Token> {
+    pub fn lex(&mut self) -> Vec<Token<'a>> {
         let mut tokens = Vec::new();
         let buffer = String::new();
 
         self.mode.push(LexerMode::Normal);
 
         while let Some(character) = self.cursor.peek() {
+            let start = self.cursor.line_and_column();
             let token = match self.mode.last() {
                 Some(LexerMode::Normal) => {
                     if character.is_whitespace() {
@@ -123,9 +126,15 @@ impl<'a> Lexer<'a> {
                             },
                             '$' => {
                                 self.cursor.advance();
-                                let slice = self.cursor.consume_while(|c| c.is_alphanumeric());
+                                // `$BASE+I` is an indexed address
+                                let slice = self.cursor.consume_while(|c| c.is_alphanumeric() || c == '+');
                                 Token::DirectAddress(slice)
                             },
+                            '%' => {
+                                self.cursor.advance();
+                                let slice = self.cursor.consume_while(|c| c.is_alphanumeric() || c == '-');
+                                Token::StackOffset(slice)
+                            },
                             '@' => {
                                 self.cursor.advance();
                                 let slice = self.cursor.consume_while(|c| c.is_alphanumeric());
@@ -184,10 +193,7 @@ impl<'a> Lexer<'a> {
                     }
                 },
                 Some(LexerMode::ArrayLiteral) => {
-                    if character == '\n' {
-                        self.cursor.advance();
-                    } 
-
+                    // Arrays may span lines, so newlines inside them are just whitespace
                     if character.is_whitespace() {
                         self.cursor.advance();
                         continue;
@@ -231,12 +237,19 @@ impl<'a> Lexer<'a> {
             };
 
             tokens.push(token);
+            self.positions.push(start);
         }
 
         tokens.push(Token::EndOfFile);
+        self.positions.push(self.cursor.line_and_column());
         tokens
     }
 
+    // The line and column (both from 1) where each token from `lex` starts
+    pub fn positions(&self) -> &[(usize, usize)] {
+        &self.positions
+    }
+
     fn slice(&self, start: usize, end: usize) -> &'a str {
         self.source
         .get(start..end)
@@ -12,6 +12,7 @@ pub enum Token<'a> {
     JumpLabel(&'a str),
     DirectAddress(&'a str),
     IndirectAddress(&'a str),
+    StackOffset(&'a str),
     Comment(&'a str),
     Comma,
     Newline,
@@ -26,3 +27,20 @@ pub enum Token<'a> {
     ModeKey(&'a str),
     Error { message: String, line_and_column: (usize, usize), snippet: &'a str } ,
 }
+
+impl Token<'_> {
+    // How many characters the token takes in the source, including its sigil
+    pub fn width(&self) -> usize {
+        match self {
+            Token::Identifier(text) | Token::DecimalNumber(text) | Token::String(text) |
+            Token::Element(text) | Token::ModeKey(text) => text.chars().count(),
+            Token::Directive(text) | Token::LabelHeader(text) | Token::JumpLabel(text) |
+            Token::DirectAddress(text) | Token::IndirectAddress(text) | Token::StackOffset(text) |
+            Token::Comment(text) => text.chars().count() + 1,
+            Token::BinaryNumber(text) | Token::OctalNumber(text) | Token::HexNumber(text) => text.chars().count() + 2,
+            Token::Error { snippet, .. } => snippet.chars().count(),
+            Token::Newline | Token::EndOfFile => 0,
+            _ => 1,
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::assembler::lexer::{Lexer, token::Token};
+use crate::assembler::linter::warning::{Lint, Warning};
+use crate::assembler::parser::{Parser, assembler_operand::AssemblerOperand, ast_node::ASTNode,
+    ast_node::MacroNode,
+};
+use crate::chiiko::MachineProfile;
+use crate::mode::Mode;
+use crate::mode::mode_group::ModeGroup;
+use crate::operation::Operation;
+use crate::operation::group::{Group, MemoryVariant, SubroutineVariant, SystemVariant};
+
+// Programs start here, so it counts as used without being jumped to
+const ENTRY_LABEL: &str = "START";
+
+// A RAM area claimed by a data macro
+struct Region {
+    start: usize,
+    end: usize,
+    line: usize,
+    name: String,
+}
+
+// Looks for code that assembles but probably does not do what was meant. This runs after
+// `SyntaxChecker` has passed, but tolerates anything the parser produces.
+pub fn lint(source: &str, profile: &MachineProfile) -> Vec<Warning> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.lex();
+    let mut parser = Parser::new(tokens.clone());
+    parser.parse();
+
+    let nodes: Vec<(&ASTNode, usize)> = parser.instructions
+        .iter()
+        .zip(&parser.node_starts)
+        .map(|(node, start)| (node, lexer.positions()[*start].0))
+        .collect();
+
+    let mut warnings = Vec::new();
+    check_symbols(&nodes, &mut warnings);
+    check_reachability(&nodes, &mut warnings);
+    check_modes(&nodes, &mut warnings);
+    check_rom_writes(&nodes, profile, &mut warnings);
+    check_data_overlap(&nodes, &mut warnings);
+
+    let allowed = allowed_lints(&nodes, &tokens, &parser.node_starts);
+    warnings.retain(|warning| {
+        !allowed.get(&warning.line).is_some_and(|lints| lints.contains(&warning.lint))
+    });
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+fn warn(warnings: &mut Vec<Warning>, lint: Lint, line: usize, message: String) {
+    warnings.push(Warning { lint, line, message });
+}
+
+// Duplicate labels, and labels or variables nothing refers to
+fn check_symbols(nodes: &[(&ASTNode, usize)], warnings: &mut Vec<Warning>) {
+    let mut labels: Vec<(&str, usize)> = Vec::new();
+    let mut variables: Vec<(String, usize)> = Vec::new();
+    let mut used: HashSet<String> = HashSet::new();
+
+    for (node, line) in nodes {
+        match node {
+            ASTNode::Label(name) => match labels.iter().find(|(label, _)| label == name) {
+                Some((_, first)) => warn(
+                    warnings, Lint::DuplicateLabel, *line,
+                    format!("Label {} is already defined on line {}", name, first)
+                ),
+                None => labels.push((name, *line)),
+            },
+            ASTNode::Instruction { operands, .. } => used.extend(operands.iter().filter_map(reference)),
+            ASTNode::Macro(MacroNode::VariableData { label, pointer, .. }) => {
+                if let Ok(name) = label.string() {
+                    variables.push((name, *line));
+                }
+                used.extend(pointer.iter().filter_map(reference));
+            },
+            ASTNode::Macro(MacroNode::ArrayData { elements, .. }) => {
+                used.extend(elements.iter().filter_map(reference));
+            },
+            _ => (),
+        }
+    }
+
+    for (name, line) in labels {
+        if name != ENTRY_LABEL && !used.contains(name) {
+            warn(warnings, Lint::UnusedLabel, line, format!("Label {} is never used", name));
+        }
+    }
+
+    for (name, line) in variables {
+        if !used.contains(&name) {
+            warn(warnings, Lint::UnusedVariable, line, format!("Variable {} is never used", name));
+        }
+    }
+}
+
+// The name an operand refers to. ARRAY identifiers and named elements define names instead.
+fn reference(operand: &AssemblerOperand) -> Option<String> {
+    match operand {
+        AssemblerOperand::JumpAddress(name) | AssemblerOperand::DirectAddress(name) |
+        AssemblerOperand::IndirectAddress(name) | AssemblerOperand::Indexed { base: name, .. } => {
+            Some(name.to_string())
+        },
+        _ => None,
+    }
+}
+
+// Code straight after HALT or JUMP can only be reached through a label
+fn check_reachability(nodes: &[(&ASTNode, usize)], warnings: &mut Vec<Warning>) {
+    let mut stopped_by: Option<&str> = None;
+
+    for (node, line) in nodes {
+        match node {
+            ASTNode::Instruction { mnemonic, .. } => {
+                if let Some(stop) = stopped_by.take() {
+                    warn(
+                        warnings, Lint::Unreachable, *line,
+                        format!("Code after {} is unreachable without a label", stop)
+                    );
+                }
+
+                let stops = Operation::lookup_mnemonic(mnemonic).is_some_and(|operation| matches!(
+                    operation.group,
+                    Group::System(SystemVariant::Halt) | Group::Subroutine(SubroutineVariant::Jump)
+                ));
+                if stops {
+                    stopped_by = Some(mnemonic);
+                }
+            },
+            ASTNode::Label(_) | ASTNode::Directive(_) | ASTNode::Macro(MacroNode::EndCount { .. }) |
+            ASTNode::Macro(MacroNode::BankSelect(_)) => stopped_by = None,
+            _ => (),
+        }
+    }
+}
+
+// Mode tuples that only repeat what the operation would use anyway
+fn check_modes(nodes: &[(&ASTNode, usize)], warnings: &mut Vec<Warning>) {
+    for (node, line) in nodes {
+        let ASTNode::Instruction { mnemonic, mode: Some(mode), .. } = node else {
+            continue
+        };
+        let Some(operation) = Operation::lookup_mnemonic(mnemonic) else {
+            continue
+        };
+
+        if Mode::groups_from_byte(operation.default_mode) == *mode {
+            warn(
+                warnings, Lint::RedundantMode, *line,
+                format!("({}, {}) is already the default mode of {}", key(&mode.0), key(&mode.1), mnemonic)
+            );
+        }
+    }
+}
+
+fn key(group: &ModeGroup) -> &'static str {
+    Mode::from_group(group).keys[0]
+}
+
+// SAVE and MOVE into the ROM window are ignored by the machine
+fn check_rom_writes(nodes: &[(&ASTNode, usize)], profile: &MachineProfile, warnings: &mut Vec<Warning>) {
+    let mut addresses: HashMap<String, usize> = HashMap::new();
+
+    for (node, _) in nodes {
+        match node {
+            ASTNode::Macro(MacroNode::VariableData { address, label, .. }) => {
+                if let (Some(address), Ok(name)) = (data_address(address), label.string()) {
+                    addresses.insert(name, address);
+                }
+            },
+            ASTNode::Macro(MacroNode::ArrayData { address, elements }) => {
+                let Some(mut address) = data_address(address) else {
+                    continue
+                };
+
+                for element in elements {
+                    if let AssemblerOperand::NamedElement { name, .. } | AssemblerOperand::Identifier(name) = element {
+                        addresses.insert(name.to_string(), address);
+                    }
+                    address += element.data_width();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    let rom = profile.rom_base as usize..profile.rom_base as usize + profile.rom_size;
+
+    for (node, line) in nodes {
+        let ASTNode::Instruction { mnemonic, operands, .. } = node else {
+            continue
+        };
+        let writes = Operation::lookup_mnemonic(mnemonic).is_some_and(|operation| matches!(
+            operation.group,
+            Group::Memory(MemoryVariant::Save) | Group::Memory(MemoryVariant::Move)
+        ));
+        let Some(AssemblerOperand::DirectAddress(destination)) = operands.last().filter(|_| writes) else {
+            continue
+        };
+
+        let address = Parser::normalize_number(destination).ok().or_else(|| addresses.get(destination).copied());
+        if let Some(address) = address.filter(|address| rom.contains(address)) {
+            warn(
+                warnings, Lint::RomWrite, *line,
+                format!("{} writes to {:#06X}, which is ROM", mnemonic, address)
+            );
+        }
+    }
+}
+
+// VAR, ARRAY and STRING data that share bytes
+fn check_data_overlap(nodes: &[(&ASTNode, usize)], warnings: &mut Vec<Warning>) {
+    let mut regions: Vec<Region> = Vec::new();
+
+    for (node, line) in nodes {
+        let (address, width, kind, label) = match node {
+            ASTNode::Macro(MacroNode::VariableData { address, label, pointer }) => {
+                let width = pointer.as_ref().map_or(1, |pointer| pointer.data_width());
+                (address, width, "VAR", label.string().ok())
+            },
+            ASTNode::Macro(MacroNode::ArrayData { address, elements }) => {
+                (address, elements.iter().map(|element| element.data_width()).sum(), "ARRAY", None)
+            },
+            ASTNode::Macro(MacroNode::StringData { address, value }) => {
+                (address, value.string().map_or(0, |string| string.len() + 1), "STRING", None)
+            },
+            _ => continue,
+        };
+
+        if let Some(start) = data_address(address).filter(|_| width > 0) {
+            let name = match label {
+                Some(label) => format!("{} {}", kind, label),
+                None => format!("{} at {:#06X}", kind, start),
+            };
+            regions.push(Region { start, end: start + width, line: *line, name });
+        }
+    }
+
+    for (index, region) in regions.iter().enumerate() {
+        let overlapped = regions[..index]
+            .iter()
+            .find(|other| other.start < region.end && region.start < other.end);
+
+        if let Some(other) = overlapped {
+            warn(
+                warnings, Lint::DataOverlap, region.line,
+                format!("{} overlaps {} from line {}", region.name, other.name, other.line)
+            );
+        }
+    }
+}
+
+fn data_address(address: &AssemblerOperand) -> Option<usize> {
+    address.string().ok().and_then(|id| Parser::normalize_number(&id).ok())
+}
+
+// `; allow(code, ...)` silences lints on its own line, or on the next line of code when the
+// comment stands on a line by itself
+fn allowed_lints(nodes: &[(&ASTNode, usize)], tokens: &[Token], starts: &[usize]) -> HashMap<usize, Vec<Lint>> {
+    let mut allowed: HashMap<usize, Vec<Lint>> = HashMap::new();
+    let mut pending: Vec<Lint> = Vec::new();
+
+    for (index, (node, line)) in nodes.iter().enumerate() {
+        match node {
+            ASTNode::Comment(text) => {
+                let lints = allowed_in(text);
+                let start = starts[index];
+
+                if start > 0 && tokens[start - 1] != Token::Newline {
+                    allowed.entry(*line).or_default().extend(lints);
+                } else {
+                    pending.extend(lints);
+                }
+            },
+            ASTNode::BlankLine => pending.clear(),
+            _ => allowed.entry(*line).or_default().append(&mut pending),
+        }
+    }
+
+    allowed
+}
+
+fn allowed_in(comment: &str) -> Vec<Lint> {
+    let comment = comment.to_lowercase();
+    let Some((_, rest)) = comment.split_once("allow(") else {
+        return Vec::new()
+    };
+
+    rest.split(')')
+        .next()
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(Lint::from_code)
+        .collect()
+}
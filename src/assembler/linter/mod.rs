@@ -0,0 +1,7 @@
+mod core;
+pub mod warning;
+
+#[cfg(test)]
+mod test;
+
+pub use core::lint;
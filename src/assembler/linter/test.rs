@@ -0,0 +1,123 @@
+use crate::assembler::linter::{lint, warning::Lint};
+use crate::chiiko::MachineProfile;
+
+fn lints(source: &str) -> Vec<(usize, Lint)> {
+    lint(source, &MachineProfile::default())
+        .iter()
+        .map(|warning| (warning.line, warning.lint))
+        .collect()
+}
+
+#[test]
+fn clean_program_has_no_warnings() {
+    let source = "\
+VAR $0x10 SCORE
+START:
+    LOAD 5 A
+LOOP:
+    DEC A
+    SAVE A $SCORE
+    JUMP :LOOP
+";
+
+    assert_eq!(lints(source), vec![]);
+}
+
+#[test]
+fn reports_unused_and_duplicate_symbols() {
+    let source = "\
+VAR $0x10 SCORE
+START:
+    HALT
+SPARE:
+    HALT
+SPARE:
+    HALT
+";
+
+    assert_eq!(lints(source), vec![
+        (1, Lint::UnusedVariable),
+        (4, Lint::UnusedLabel),
+        (6, Lint::DuplicateLabel),
+    ]);
+}
+
+#[test]
+fn reports_code_after_halt_and_jump_without_a_label() {
+    let source = "\
+START:
+    HALT
+    INC B
+    INC B
+AGAIN:
+    JUMP :AGAIN
+    ; Nothing jumps here
+    DEC B
+";
+
+    assert_eq!(lints(source), vec![(3, Lint::Unreachable), (8, Lint::Unreachable)]);
+}
+
+#[test]
+fn reports_writes_to_rom() {
+    let source = "\
+VAR $0x8010 FLAG
+START:
+    SAVE A $0x8000
+    SAVE A $FLAG
+    SAVE A $0x0010
+";
+
+    let warnings = lint(source, &MachineProfile::default());
+    let lines: Vec<usize> = warnings
+        .iter()
+        .filter(|warning| warning.lint == Lint::RomWrite)
+        .map(|warning| warning.line)
+        .collect();
+    assert_eq!(lines, vec![3, 4]);
+    assert_eq!(warnings[0].message, "SAVE writes to 0x8000, which is ROM");
+}
+
+#[test]
+fn reports_overlapping_data() {
+    let source = "\
+ARRAY $0x10 [1, 2, 3]
+VAR $0x12 LAST
+STRING $0x20 \"Hi\"
+VAR $0x22 AFTER $AFTER
+";
+
+    let warnings = lint(source, &MachineProfile::default());
+    let overlaps: Vec<&str> = warnings
+        .iter()
+        .filter(|warning| warning.lint == Lint::DataOverlap)
+        .map(|warning| warning.message.as_str())
+        .collect();
+    assert_eq!(overlaps, vec![
+        "VAR LAST overlaps ARRAY at 0x0010 from line 1",
+        "VAR AFTER overlaps STRING at 0x0020 from line 3",
+    ]);
+}
+
+#[test]
+fn reports_mode_tuples_that_match_the_default() {
+    assert_eq!(lints("START:\n    ADD (R, A) B\n    ADD (R, R) B C\n"), vec![(2, Lint::RedundantMode)]);
+}
+
+#[test]
+fn allow_comments_silence_one_line() {
+    let source = "\
+START:
+    HALT
+    INC B ; allow(unreachable)
+; allow(unused-label, duplicate-label)
+SPARE:
+SPARE:
+
+; allow(unused-variable)
+
+VAR $0x10 SCORE
+";
+
+    assert_eq!(lints(source), vec![(6, Lint::DuplicateLabel), (10, Lint::UnusedVariable)]);
+}
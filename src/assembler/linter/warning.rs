@@ -0,0 +1,55 @@
+use std::fmt;
+
+// Things that assemble but are probably mistakes. Each has a code that an
+// `; allow(code)` comment can name to silence it for one line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lint {
+    UnusedLabel,
+    UnusedVariable,
+    Unreachable,
+    RomWrite,
+    DataOverlap,
+    DuplicateLabel,
+    RedundantMode,
+}
+
+const LINTS: &[Lint] = &[
+    Lint::UnusedLabel,
+    Lint::UnusedVariable,
+    Lint::Unreachable,
+    Lint::RomWrite,
+    Lint::DataOverlap,
+    Lint::DuplicateLabel,
+    Lint::RedundantMode,
+];
+
+impl Lint {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::UnusedLabel => "unused-label",
+            Lint::UnusedVariable => "unused-variable",
+            Lint::Unreachable => "unreachable",
+            Lint::RomWrite => "rom-write",
+            Lint::DataOverlap => "data-overlap",
+            Lint::DuplicateLabel => "duplicate-label",
+            Lint::RedundantMode => "redundant-mode",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        LINTS.iter().copied().find(|lint| lint.code().eq_ignore_ascii_case(code.trim()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub line: usize, // From 1, like the lexer's positions
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.lint.code())
+    }
+}
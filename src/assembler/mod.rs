@@ -3,7 +3,9 @@ pub mod lexer;
 pub mod assembly_error;
 pub mod parser;
 pub mod encoder;
-mod source;
+pub mod linter;
+pub mod source;
+pub mod source_map;
 
 #[cfg(test)]
 mod test;
@@ -14,6 +14,8 @@ pub enum AssemblerOperand {
     StartCount(usize),
     EndCount,
     NamedElement {name: String, value: u8},
+    Indexed {base: String, index: String},
+    StackOffset(i16), // `%n`, a signed offset from the frame pointer
 }
 
 impl AssemblerOperand {
@@ -23,7 +25,7 @@ impl AssemblerOperand {
             Self::String(string) | Self::JumpAddress(string) | Self::Identifier(string) => {
                 Ok(string.to_string())
             },
-            Self::NamedElement { name, .. } => {
+            Self::NamedElement { name, .. } | Self::Indexed { base: name, .. } => {
                 Ok(name.to_string())
             }, 
             _ => Err(AssemblyError::NoOperandString)
@@ -33,12 +35,53 @@ impl AssemblerOperand {
     pub fn is_destination(&self) -> bool {
         matches!(self, 
         AssemblerOperand::Register(_) | AssemblerOperand::DirectAddress(_) | 
-        AssemblerOperand::IndirectAddress(_))
+        AssemblerOperand::IndirectAddress(_) | AssemblerOperand::Indexed { .. })
+    }
+
+    // Bytes taken by an ARRAY element or VAR initialiser: addresses are stored as pointer words
+    pub fn data_width(&self) -> usize {
+        match self {
+            AssemblerOperand::DirectAddress(_) | AssemblerOperand::JumpAddress(_) => 2,
+            _ => 1,
+        }
     }
 
     pub fn is_valid_register(id: &str) -> bool {
         REGISTER_CODES.contains(&id)
     }
+
+    // Matches the codes used by `Cpu::read_register` and `Cpu::read_register_pair`
+    pub fn register_code(id: &str) -> Option<u8> {
+        match id {
+            "A" => Some(0),
+            "B" => Some(1),
+            "C" => Some(2),
+            "H" => Some(3),
+            "L" => Some(4),
+            "I" => Some(5),
+            "J" => Some(6),
+            "BC" => Some(9),
+            "HL" => Some(10),
+            "IJ" => Some(11),
+            _ => None,
+        }
+    }
+
+    pub fn register_name(code: u8) -> Option<&'static str> {
+        match code {
+            0 => Some("A"),
+            1 => Some("B"),
+            2 => Some("C"),
+            3 => Some("H"),
+            4 => Some("L"),
+            5 => Some("I"),
+            6 => Some("J"),
+            9 => Some("BC"),
+            10 => Some("HL"),
+            11 => Some("IJ"),
+            _ => None,
+        }
+    }
 }
 
 static REGISTER_CODES: &[&str] = &[
@@ -11,6 +11,8 @@ pub enum ASTNode {
     Macro(MacroNode),
     Directive(String),
     Label(String),
+    Comment(String), // The text after `;`, kept so source can be rewritten without losing it
+    BlankLine,
     Error(String)
 }
 
@@ -29,8 +31,10 @@ pub enum MacroNode {
     },
     VariableData {
         address: AssemblerOperand,
-        label: AssemblerOperand
+        label: AssemblerOperand,
+        pointer: Option<AssemblerOperand>, // `VAR $addr NAME $TARGET` stores a pointer word
     },
     LinkData(String),
+    BankSelect(AssemblerOperand),
     MacroError(String),
 }
@@ -10,6 +10,7 @@ use crate::operation::Operation;
 pub struct Parser<'a> {
     tokens: Vec<Token<'a>>,
     pub instructions: Vec<ASTNode>,
+    pub node_starts: Vec<usize>, // Index of the first token of each node in `instructions`
     position: usize,
     counter_id: usize,
 }
@@ -19,6 +20,7 @@ impl<'a> Parser<'a> {
         Self {
             tokens: tokens,
             instructions: Vec::new(),
+            node_starts: Vec::new(),
             position: 0,
             counter_id: 0,
         }
@@ -26,6 +28,8 @@ impl<'a> Parser<'a> {
 
     pub fn parse(&mut self) {
         while self.position < self.tokens.len() {
+            let start = self.position;
+
             match self.current_token() {
                 Token::Directive(id) => {
                     let mode = Self::normalize_string(id);
@@ -46,7 +50,17 @@ impl<'a> Parser<'a> {
                     self.counter_id += 1;
                     self.advance();
                 },
-                Token::Newline => self.advance(),
+                Token::Comment(text) => {
+                    self.instructions.push(ASTNode::Comment(text.trim_end().to_string()));
+                    self.advance();
+                },
+                Token::Newline => {
+                    // A line break straight after another one ends an empty line
+                    if self.position == 0 || self.tokens[self.position - 1] == Token::Newline {
+                        self.instructions.push(ASTNode::BlankLine);
+                    }
+                    self.advance();
+                },
                 Token::EndOfFile => break,
                 _ => {
                     self.instructions.push(
@@ -55,6 +69,8 @@ impl<'a> Parser<'a> {
                     self.advance();
                 }
             }
+
+            self.node_starts.resize(self.instructions.len(), start);
         }
     }
 
@@ -79,8 +95,10 @@ impl<'a> Parser<'a> {
         let mut mode: Option<(ModeGroup, ModeGroup)> = self.parse_mode();
         let mut operands: Vec<AssemblerOperand> = Vec::new();
 
-        while !matches!(self.current_token(), Token::Newline | Token::CloseBrace){
-            if matches!(self.current_token(), Token::Comma | Token::Quote | Token::Comment(_)) { 
+        while !matches!(self.current_token(), 
+            Token::Newline | Token::CloseBrace | Token::Comment(_) | Token::EndOfFile
+        ) {
+            if matches!(self.current_token(), Token::Comma | Token::Quote) { 
                 self.advance();
                 continue;
             }
@@ -89,7 +107,7 @@ impl<'a> Parser<'a> {
             self.advance();
         }
 
-        self.advance();
+        self.finish_line();
         self.instructions.push(
             ASTNode::Instruction {
                 mnemonic: mnemonic,
@@ -136,7 +154,7 @@ impl<'a> Parser<'a> {
 
                 self.advance();
 
-                while self.current_token() != Token::CloseBracket {
+                while !matches!(self.current_token(), Token::CloseBracket | Token::EndOfFile) {
                     if self.current_token() == Token::Comma {
                         self.advance();
                         continue;
@@ -157,10 +175,20 @@ impl<'a> Parser<'a> {
                 let label = self.lookup_operand();
                 self.advance();
 
+                let pointer = match self.current_token() {
+                    Token::DirectAddress(_) | Token::JumpLabel(_) => {
+                        let pointer = self.lookup_operand();
+                        self.advance();
+                        Some(pointer)
+                    },
+                    _ => None,
+                };
+
                 self.instructions.push(
                     ASTNode::Macro(MacroNode::VariableData {
                         address: address,
                         label: label,
+                        pointer,
                     })
                 );
             },
@@ -175,15 +203,27 @@ impl<'a> Parser<'a> {
                 }
 
                 self.advance();
-            }
+            },
+            "BANK" => {
+                self.instructions.push(ASTNode::Macro(MacroNode::BankSelect(address)));
+            },
             _ => ()
         } 
 
-        self.advance();
+        self.finish_line();
+    }
+
+    // Steps over the token that ends a statement, leaving a trailing comment to be parsed
+    // as a node of its own
+    fn finish_line(&mut self) {
+        if !matches!(self.current_token(), Token::Comment(_) | Token::EndOfFile) {
+            self.advance();
+        }
     }
 
-    fn current_token(&self) -> Token {
-        self.tokens[self.position].clone()
+    // Reading past the end gives EndOfFile, so unfinished lines cannot run off the token list
+    fn current_token(&self) -> Token<'a> {
+        self.tokens.get(self.position).cloned().unwrap_or(Token::EndOfFile)
     }
 
     fn advance(&mut self) {
@@ -209,30 +249,25 @@ impl<'a> Parser<'a> {
     fn lookup_mode_key(&self) -> ModeGroup {
         if let Token::ModeKey(code) = self.current_token() {
             let key = Self::normalize_string(code);
-            Mode::from_key(&key).group
+            Mode::lookup_key(&key).map_or(ModeGroup::Error, |mode| mode.group)
         } else {
-            panic!("Key missing from Mode: {:?}", self.current_token())
+            ModeGroup::Error
+        }
+    }
+
+    fn number(digits: &str, radix: u32) -> AssemblerOperand {
+        match u16::from_str_radix(digits, radix) {
+            Ok(number) => AssemblerOperand::Number(number),
+            Err(_) => AssemblerOperand::Error(format!("Number out of range: {}", digits)),
         }
     }
 
     fn lookup_operand(&self) -> AssemblerOperand {
         match self.current_token() {
-            Token::BinaryNumber(value) => {
-                let number = u16::from_str_radix(value, 2).unwrap();
-                AssemblerOperand::Number(number)
-            },
-            Token::OctalNumber(value) => {
-                let number = u16::from_str_radix(value, 8).unwrap();
-                AssemblerOperand::Number(number)
-            },
-            Token::DecimalNumber(value) => {
-                let number = u16::from_str_radix(value, 10).unwrap();
-                AssemblerOperand::Number(number)
-            },
-            Token::HexNumber(value) => {
-                let number = u16::from_str_radix(value, 16).unwrap();
-                AssemblerOperand::Number(number)
-            },
+            Token::BinaryNumber(value) => Self::number(value, 2),
+            Token::OctalNumber(value) => Self::number(value, 8),
+            Token::DecimalNumber(value) => Self::number(value, 10),
+            Token::HexNumber(value) => Self::number(value, 16),
             Token::Identifier(value) => {
                 let id = Self::normalize_string(value);
                 if id.len() < 3 {
@@ -243,27 +278,48 @@ impl<'a> Parser<'a> {
             },
             Token::DirectAddress(value) => {
                 let id = Self::normalize_string(value);
-                AssemblerOperand::DirectAddress(id)
+                if let Some((base, index)) = id.split_once('+') {
+                    AssemblerOperand::Indexed { base: base.to_string(), index: index.to_string() }
+                } else {
+                    AssemblerOperand::DirectAddress(id)
+                }
             },
             Token::IndirectAddress(value) => {
                 let id = Self::normalize_string(value);
                 AssemblerOperand::IndirectAddress(id)
             },
+            Token::StackOffset(value) => {
+                let (sign, digits) = match value.strip_prefix('-') {
+                    Some(digits) => (-1, digits),
+                    None => (1, value),
+                };
+
+                match Self::normalize_number(digits) {
+                    Ok(number) if number <= 0x80 => AssemblerOperand::StackOffset(sign * number as i16),
+                    _ => AssemblerOperand::Error(format!("Invalid stack offset: %{}", value)),
+                }
+            },
             Token::JumpLabel(value) => {
                 let address = Self::normalize_string(value);
                 AssemblerOperand::JumpAddress(address)
             },
             Token::Element(element) => {
+                // `$NAME` and `:LABEL` elements are stored as two byte pointers
+                if let Some(name) = element.strip_prefix('$') {
+                    return AssemblerOperand::DirectAddress(Self::normalize_string(name))
+                } else if let Some(name) = element.strip_prefix(':') {
+                    return AssemblerOperand::JumpAddress(Self::normalize_string(name))
+                }
+
                 if let Some(index) = element.find('=') {
                     let name = Self::normalize_string(&element[0..index]);
                     let value = &element[index + 1..].trim();
-                    let number = Self::normalize_number(&value)
-                                    .unwrap_or_else(|_| panic!(
-                                    "Initialized Named Element must assign a Value; found: \"{}\"", 
-                                    value
-                                ));
-
-                    AssemblerOperand::NamedElement { name: name, value: number as u8 }
+                    match Self::normalize_number(&value) {
+                        Ok(number) => AssemblerOperand::NamedElement { name: name, value: number as u8 },
+                        Err(_) => AssemblerOperand::Error(format!(
+                            "Initialized Named Element must assign a Value; found: \"{}\"", value
+                        )),
+                    }
                 } else if let Ok(number) = Self::normalize_number(element) {
                     AssemblerOperand::Number(number as u16)
                 } else {
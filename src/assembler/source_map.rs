@@ -0,0 +1,36 @@
+use crate::assembler::encoder::program::Program;
+use crate::assembler::parser::ast_node::ASTNode;
+
+// Ties assembled instructions back to the source lines they were written on, so debuggers
+// can work in terms of the .ku file. Lines count from 1, like the lexer's positions.
+// Code in different switchable banks can share an address; lookups by address find the
+// first instruction written there.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    entries: Vec<(usize, u16)>, // The line and address of each instruction, in source order
+}
+
+impl SourceMap {
+    // `lines` holds the line each node of `ast` starts on
+    pub fn new(ast: &[ASTNode], lines: &[usize], program: &Program) -> Self {
+        let entries = ast
+            .iter()
+            .zip(lines)
+            .filter(|(node, _)| matches!(node, ASTNode::Instruction { .. }))
+            .map(|(_, line)| *line)
+            .zip(program.addresses.iter().copied())
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn line_of(&self, address: u16) -> Option<usize> {
+        self.entries.iter().find(|(_, start)| *start == address).map(|(line, _)| *line)
+    }
+
+    // The first instruction on `line`, or on the nearest line after it with code, along with
+    // the line it was found on
+    pub fn address_of(&self, line: usize) -> Option<(usize, u16)> {
+        self.entries.iter().find(|(start, _)| *start >= line).copied()
+    }
+}
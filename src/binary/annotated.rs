@@ -0,0 +1,54 @@
+use crate::binary::image;
+
+const ROW_WIDTH: usize = 16;
+
+// Hex bytes separated by whitespace. `@address` moves the write position, and
+// everything after a `;` is a comment. Addresses are machine addresses, counted from
+// `load_address` at the start of the image.
+pub fn parse(text: &str, load_address: u16) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut address: usize = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or("");
+        let error = |message: String| format!("Line {}: {}", index + 1, message);
+
+        for token in code.split_whitespace() {
+            if let Some(marker) = token.strip_prefix('@') {
+                address = parse_hex(marker)
+                    .ok_or_else(|| error(format!("invalid address marker `{}`", token)))?
+                    .checked_sub(load_address as usize)
+                    .ok_or_else(|| error(format!("`{}` is below the ROM at {:#06X}", token, load_address)))?;
+                if address >= image::MAXIMUM_IMAGE_SIZE {
+                    return Err(error(format!("Address {:#X} is beyond the largest ROM image", address)))
+                }
+                continue;
+            }
+
+            let byte = parse_hex(token)
+                .and_then(|value| u8::try_from(value).ok())
+                .ok_or_else(|| error(format!("invalid byte `{}`", token)))?;
+
+            image::place(&mut bytes, address, &[byte]).map_err(error)?;
+            address += 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+pub fn write(bytes: &[u8], load_address: u16) -> String {
+    let mut text = format!("; Chiiko image, {} bytes from {:#06X}\n", bytes.len(), load_address);
+
+    for (offset, row) in image::rows(bytes, ROW_WIDTH) {
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02X}", byte)).collect();
+        text.push_str(&format!("@{:04X}  {}\n", load_address as usize + offset, hex.join(" ")));
+    }
+
+    text
+}
+
+fn parse_hex(token: &str) -> Option<usize> {
+    let digits = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+    usize::from_str_radix(digits, 16).ok()
+}
@@ -0,0 +1,294 @@
+use std::fs;
+
+use crate::assembler::encoder::{program::Program, symbol_table::Symbol, symbol_table::SymbolTable};
+use crate::chiiko::MachineProfile;
+
+pub const MAGIC: &[u8; 4] = b"CHKO";
+const VERSION: u8 = 1;
+const ROM_SECTION: u8 = 1;
+const DATA_SECTION: u8 = 2;
+const SYMBOL_SECTION: u8 = 3;
+const LABEL_SYMBOL: u8 = 0;
+const VARIABLE_SYMBOL: u8 = 1;
+const CRC_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Label,
+    Variable,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerSymbol {
+    pub name: String,
+    pub address: u16,
+    pub kind: SymbolKind,
+}
+
+// A Chiiko ROM file: a header describing where and how to run the program, followed by
+// sections for the ROM image, RAM initialisation data and debug symbols, then a CRC-32
+// of everything before it. All numbers are big endian, like the machine.
+//
+//   "CHKO" | version | entry point (2) | load address (2) | title | profile | section count
+//   section: kind (1) | length (4) | payload
+//   strings: length (1) | UTF-8 bytes
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomContainer {
+    pub title: String,
+    pub entry_point: u16,
+    pub load_address: u16,
+    pub profile: String,
+    pub rom: Vec<u8>,
+    pub data: Vec<(u16, Vec<u8>)>,
+    pub symbols: Vec<ContainerSymbol>,
+}
+
+impl RomContainer {
+    pub fn from_program(
+    program: &Program,
+    table: &SymbolTable,
+    title: &str,
+    profile: &str,
+    load_address: u16
+    ) -> Self {
+        let mut symbols: Vec<ContainerSymbol> = table.table
+            .iter()
+            .filter_map(|(name, symbol)| match symbol {
+                Symbol::Address(address) => Some((name, *address, SymbolKind::Label)),
+                Symbol::Variable { address, .. } => Some((name, *address, SymbolKind::Variable)),
+                _ => None,
+            })
+            .map(|(name, address, kind)| ContainerSymbol { name: name.to_string(), address, kind })
+            .collect();
+        symbols.sort_by(|first, second| first.name.cmp(&second.name));
+
+        // Programs start at a `START` label when they have one, otherwise at the top of ROM
+        let entry_point = symbols
+            .iter()
+            .find(|symbol| symbol.name == "START" && symbol.kind == SymbolKind::Label)
+            .map(|symbol| symbol.address)
+            .unwrap_or(load_address);
+
+        Self {
+            title: title.to_string(),
+            entry_point,
+            load_address,
+            profile: profile.to_string(),
+            rom: program.rom.clone(),
+            data: program.data.iter().map(|segment| (segment.address, segment.bytes.clone())).collect(),
+            symbols,
+        }
+    }
+
+    // Bare images have no header, so they start at their reset vector, or at the top of ROM
+    // when it is blank
+    pub fn from_image(image: Vec<u8>, title: &str, profile: &MachineProfile) -> Self {
+        let offset = reset_vector_offset(image.len(), profile);
+        let vector = image.get(offset..offset + 2).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+
+        Self {
+            title: title.to_string(),
+            entry_point: if vector == 0 { profile.rom_base } else { vector },
+            load_address: profile.rom_base,
+            profile: profile.name.clone(),
+            rom: image,
+            data: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    // The ROM as a bare image, with the entry point written into its reset vector
+    pub fn image(&self, profile: &MachineProfile) -> Vec<u8> {
+        let offset = reset_vector_offset(self.rom.len(), profile);
+        let mut image = self.rom.clone();
+        if image.len() < offset + 2 {
+            image.resize(offset + 2, 0);
+        }
+        image[offset..offset + 2].copy_from_slice(&self.entry_point.to_be_bytes());
+
+        image
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let bytes = self.encode()?;
+        fs::write(filename, bytes)
+            .map_err(|error| format!("Failed to write file: {} {}", filename, error))
+    }
+
+    pub fn is_container(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    // Unused ROM is zero filled, so trailing zeros are left out
+    pub fn used_rom(&self) -> &[u8] {
+        let length = self.rom.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+        &self.rom[..length]
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(self.entry_point.to_be_bytes());
+        bytes.extend(self.load_address.to_be_bytes());
+        write_string(&mut bytes, &self.title)?;
+        write_string(&mut bytes, &self.profile)?;
+
+        let mut data_section = Vec::new();
+        for (address, segment) in &self.data {
+            let length = u16::try_from(segment.len()).map_err(|_| "Data segment is too large")?;
+            data_section.extend(address.to_be_bytes());
+            data_section.extend(length.to_be_bytes());
+            data_section.extend(segment);
+        }
+
+        let mut symbol_section = Vec::new();
+        for symbol in &self.symbols {
+            symbol_section.push(match symbol.kind {
+                SymbolKind::Label => LABEL_SYMBOL,
+                SymbolKind::Variable => VARIABLE_SYMBOL,
+            });
+            symbol_section.extend(symbol.address.to_be_bytes());
+            write_string(&mut symbol_section, &symbol.name)?;
+        }
+
+        let sections = [(ROM_SECTION, &self.rom), (DATA_SECTION, &data_section), (SYMBOL_SECTION, &symbol_section)];
+        bytes.push(sections.len() as u8);
+        for (kind, payload) in sections {
+            bytes.push(kind);
+            bytes.extend((payload.len() as u32).to_be_bytes());
+            bytes.extend(payload);
+        }
+
+        bytes.extend(crc32(&bytes).to_be_bytes());
+        Ok(bytes)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if !Self::is_container(data) {
+            return Err("Not a Chiiko ROM file: bad magic number".to_string())
+        }
+
+        if data.len() < MAGIC.len() + CRC_SIZE {
+            return Err("ROM file is truncated".to_string())
+        }
+
+        let (body, crc) = data.split_at(data.len() - CRC_SIZE);
+        if crc32(body).to_be_bytes() != crc {
+            return Err("ROM file is corrupt: CRC mismatch".to_string())
+        }
+
+        let mut reader = Reader { data: body, position: MAGIC.len() };
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(format!("Unsupported ROM file version {}", version))
+        }
+
+        let mut container = Self {
+            entry_point: reader.word()?,
+            load_address: reader.word()?,
+            title: reader.string()?,
+            profile: reader.string()?,
+            rom: Vec::new(),
+            data: Vec::new(),
+            symbols: Vec::new(),
+        };
+
+        for _ in 0..reader.byte()? {
+            let kind = reader.byte()?;
+            let length = u32::from_be_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+            let mut section = Reader { data: reader.take(length)?, position: 0 };
+
+            match kind {
+                ROM_SECTION => container.rom = section.data.to_vec(),
+                DATA_SECTION => while !section.is_empty() {
+                    let address = section.word()?;
+                    let length = section.word()? as usize;
+                    container.data.push((address, section.take(length)?.to_vec()));
+                },
+                SYMBOL_SECTION => while !section.is_empty() {
+                    let kind = match section.byte()? {
+                        LABEL_SYMBOL => SymbolKind::Label,
+                        VARIABLE_SYMBOL => SymbolKind::Variable,
+                        other => return Err(format!("Unknown symbol kind {}", other)),
+                    };
+                    let address = section.word()?;
+                    let name = section.string()?;
+                    container.symbols.push(ContainerSymbol { name, address, kind });
+                },
+                _ => (), // Unknown sections are skipped so newer files stay loadable
+            }
+        }
+
+        if !reader.is_empty() {
+            return Err("ROM file has trailing bytes after its sections".to_string())
+        }
+
+        Ok(container)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self.position + length;
+        let slice = self.data
+            .get(self.position..end)
+            .ok_or_else(|| "ROM file is truncated".to_string())?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.byte()? as usize;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| "ROM file contains an invalid string".to_string())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+}
+
+// The reset vector sits in the fixed bank, which is the last bank of the image
+fn reset_vector_offset(image_length: usize, profile: &MachineProfile) -> usize {
+    let bank_size = profile.bank_size();
+    let bank_count = image_length.div_ceil(bank_size).max(2);
+
+    (bank_count - 1) * bank_size + (profile.reset_vector_address - profile.fixed_bank_base()) as usize
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) -> Result<(), String> {
+    let length = u8::try_from(string.len())
+        .map_err(|_| format!("String is too long for a ROM file: {}", string))?;
+    bytes.push(length);
+    bytes.extend(string.as_bytes());
+    Ok(())
+}
+
+// CRC-32 (IEEE 802.3), the same checksum used by zip and PNG
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
@@ -1,7 +1,8 @@
 use std::fs;
-use std::env;
 use std::fmt;
 
+use crate::binary::{annotated, format::Format, intel_hex};
+
 #[derive(Default, Debug, PartialEq)]
 pub struct Binary {
     pub raw: String,
@@ -9,23 +10,31 @@ pub struct Binary {
 }
 
 impl Binary {
-    #[cfg(not(test))]
-    pub fn from_args() -> Result<Self, String> {
-        let filename = env::args().nth(1).ok_or("Missing Input File name")?;
-        Self::from_file(&filename)
-    }
+    // Text formats name machine addresses; `load_address` is where the image's first byte sits
+    pub fn from_data(data: &[u8], format: Format, load_address: u16) -> Result<Self, String> {
+        if format == Format::Raw {
+            return Ok(Self { raw: String::new(), bytes: data.to_vec() })
+        }
 
-    pub fn from_file(filename: &str) -> Result<Self, String> {
-        let raw = fs::read_to_string(filename)
-            .map_err(|error| format!("Failed to read file: {} {}", filename, error))?;
-        let bytes: Vec<u8> = Self::parse_bytes(&raw);
+        let raw = String::from_utf8(data.to_vec())
+            .map_err(|_| format!("{:?} image is not valid text", format))?;
+        let bytes = match format {
+            Format::Base2 => Self::parse_bytes(&raw)?,
+            Format::IntelHex => intel_hex::parse(&raw, load_address)?,
+            Format::Annotated => annotated::parse(&raw, load_address)?,
+            Format::Raw => unreachable!(),
+        };
 
         Ok(Self { raw, bytes })
     }
 
+    pub fn from_image(bytes: Vec<u8>) -> Self {
+        Self { raw: String::new(), bytes }
+    }
+
     #[cfg(test)]
     pub fn from_str(raw_input: &str) -> Result<Self, String> {
-        let bytes = Self::parse_bytes(&raw_input);
+        let bytes = Self::parse_bytes(&raw_input)?;
 
         Ok(Self {
             raw: raw_input.to_string(),
@@ -33,21 +42,32 @@ impl Binary {
         })
     }
 
-    fn parse_bytes(file: &str) -> Vec<u8> {
+    fn parse_bytes(file: &str) -> Result<Vec<u8>, String> {
         file.split_whitespace()
         .map(|byte| {
             u8::from_str_radix(byte, 2)
             .map_err(|error| format!("Invalid byte '{}': {}", byte, error))
-        }
-            .unwrap())
+        })
         .collect()
     }
 
-    pub fn print_bytes(&self) {
-        for byte in &self.bytes {
-            println!("{}", byte.to_string())
+    pub fn encode(&self, format: Format, load_address: u16) -> Vec<u8> {
+        match format {
+            Format::Raw => self.bytes.clone(),
+            Format::Base2 => self.bytes
+                .iter()
+                .map(|byte| format!("{:08b}\n", byte))
+                .collect::<String>()
+                .into_bytes(),
+            Format::IntelHex => intel_hex::write(&self.bytes, load_address).into_bytes(),
+            Format::Annotated => annotated::write(&self.bytes, load_address).into_bytes(),
         }
     }
+
+    pub fn save(&self, filename: &str, format: Format, load_address: u16) -> Result<(), String> {
+        fs::write(filename, self.encode(format, load_address))
+            .map_err(|error| format!("Failed to write file: {} {}", filename, error))
+    }
 }
 
 impl fmt::Display for Binary {
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Base2,     // Whitespace separated strings of 0s and 1s
+    Raw,       // The image bytes exactly as they are loaded
+    IntelHex,
+    Annotated, // Hex bytes with `@address` markers and `;` comments
+}
+
+impl Format {
+    pub fn from_extension(filename: &str) -> Option<Self> {
+        let extension = Path::new(filename).extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "bin" => Some(Format::Raw),
+            "hex" | "ihex" => Some(Format::IntelHex),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "base2" => Some(Format::Base2),
+            "raw" | "bin" => Some(Format::Raw),
+            "ihex" | "hex" | "intel-hex" => Some(Format::IntelHex),
+            "annotated" | "text" => Some(Format::Annotated),
+            _ => None,
+        }
+    }
+
+    // Text formats are recognised by their contents; anything else is a raw image
+    pub fn detect(data: &[u8]) -> Self {
+        let Ok(text) = std::str::from_utf8(data) else {
+            return Format::Raw
+        };
+
+        let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        if lines.is_empty() {
+            return Format::Raw
+        }
+
+        if lines.iter().all(|line| line.starts_with(':')) {
+            return Format::IntelHex
+        }
+
+        let is_base2_token = |token: &str| token.len() == 8 && token.chars().all(|c| c == '0' || c == '1');
+        if text.split_whitespace().all(is_base2_token) {
+            return Format::Base2
+        }
+
+        let is_annotated_token = |token: &str| {
+            token.starts_with('@') || token.starts_with(';') ||
+            (token.len() <= 4 && token.trim_start_matches("0x").chars().all(|c| c.is_ascii_hexdigit()))
+        };
+        let is_annotated = lines.iter().all(|line| {
+            line.starts_with(';') || line.split_whitespace().take_while(|token| !token.starts_with(';'))
+                .all(is_annotated_token)
+        });
+
+        if is_annotated { Format::Annotated } else { Format::Raw }
+    }
+}
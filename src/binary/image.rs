@@ -0,0 +1,28 @@
+pub const MAXIMUM_IMAGE_SIZE: usize = 0x40_0000; // 256 banks of 16 KB
+
+// Writes `bytes` into a flat image at `address`, zero filling any gap
+pub fn place(image: &mut Vec<u8>, address: usize, bytes: &[u8]) -> Result<(), String> {
+    let end = match address.checked_add(bytes.len()) {
+        Some(end) if end <= MAXIMUM_IMAGE_SIZE => end,
+        _ => return Err(format!("Address {:#X} is beyond the largest ROM image", address)),
+    };
+
+    if image.len() < end {
+        image.resize(end, 0);
+    }
+
+    image[address..end].copy_from_slice(bytes);
+    Ok(())
+}
+
+// Splits an image into fixed size rows, leaving out rows that are entirely zero.
+// The last row is always kept so the image keeps its length (and so its bank count).
+pub fn rows(image: &[u8], width: usize) -> impl Iterator<Item = (usize, &[u8])> {
+    let last = image.len().saturating_sub(1) / width;
+
+    image
+        .chunks(width)
+        .enumerate()
+        .filter(move |(index, row)| *index == last || row.iter().any(|byte| *byte != 0))
+        .map(move |(index, row)| (index * width, row))
+}
@@ -0,0 +1,101 @@
+use crate::binary::image;
+
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+const RECORD_WIDTH: usize = 16;
+
+// Record addresses are machine addresses, so `load_address` is subtracted to find each
+// record's place in the image
+pub fn parse(text: &str, load_address: u16) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut base: usize = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+
+        let error = |message: &str| format!("Intel HEX line {}: {}", index + 1, message);
+        let record = line
+            .strip_prefix(':')
+            .ok_or_else(|| error("record must start with ':'"))?;
+
+        if !record.is_ascii() {
+            return Err(error("record contains non-hex characters"))
+        }
+        if record.len() % 2 != 0 || record.len() < 10 {
+            return Err(error("record has the wrong length"))
+        }
+
+        let fields = (0..record.len())
+            .step_by(2)
+            .map(|start| u8::from_str_radix(&record[start..start + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| error("record contains non-hex characters"))?;
+
+        let length = fields[0] as usize;
+        if fields.len() != length + 5 {
+            return Err(error("byte count does not match record length"))
+        }
+
+        if fields.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(error("checksum mismatch"))
+        }
+
+        let address = u16::from_be_bytes([fields[1], fields[2]]) as usize;
+        let data = &fields[4..4 + length];
+
+        match fields[3] {
+            DATA => {
+                let offset = (base + address)
+                    .checked_sub(load_address as usize)
+                    .ok_or_else(|| error(&format!("address is below the ROM at {:#06X}", load_address)))?;
+                image::place(&mut bytes, offset, data).map_err(|message| error(&message))?
+            },
+            END_OF_FILE => return Ok(bytes),
+            EXTENDED_SEGMENT_ADDRESS if length == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 4
+            },
+            EXTENDED_LINEAR_ADDRESS if length == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 16
+            },
+            START_SEGMENT_ADDRESS | START_LINEAR_ADDRESS => (), // Entry points come from the reset vector
+            _ => return Err(error("unsupported record type")),
+        }
+    }
+
+    Err("Intel HEX file is missing its end of file record".to_string())
+}
+
+pub fn write(bytes: &[u8], load_address: u16) -> String {
+    let mut text = String::new();
+    let mut base: usize = 0;
+
+    for (offset, row) in image::rows(bytes, RECORD_WIDTH) {
+        let address = load_address as usize + offset;
+        if address >> 16 != base {
+            base = address >> 16;
+            text.push_str(&record(EXTENDED_LINEAR_ADDRESS, 0, &(base as u16).to_be_bytes()));
+        }
+
+        text.push_str(&record(DATA, (address & 0xFFFF) as u16, row));
+    }
+
+    text.push_str(&record(END_OF_FILE, 0, &[]));
+    text
+}
+
+fn record(kind: u8, address: u16, data: &[u8]) -> String {
+    let [high, low] = address.to_be_bytes();
+    let mut fields = vec![data.len() as u8, high, low, kind];
+    fields.extend_from_slice(data);
+
+    let checksum = fields.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+    fields.push(checksum);
+
+    let hex: String = fields.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!(":{}\n", hex)
+}
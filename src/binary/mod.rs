@@ -1,6 +1,12 @@
 mod core;
+mod image;
+mod intel_hex;
+mod annotated;
+pub mod format;
+pub mod container;
 
 #[cfg(test)]
 mod test;
 
-pub use core::Binary;
\ No newline at end of file
+pub use core::Binary;
+pub use format::Format;
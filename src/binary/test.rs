@@ -0,0 +1,207 @@
+use crate::binary::{Binary, Format};
+use crate::binary::container::{crc32, ContainerSymbol, RomContainer, SymbolKind};
+use crate::assembler::encoder::{Encoder, symbol_table::SymbolTable};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::chiiko::MachineProfile;
+
+#[test]
+fn parses_base2_bytes() {
+    let binary = Binary::from_str("01110001 10000000\n00000001").unwrap();
+
+    assert_eq!(binary.bytes, vec![0x71, 0x80, 0x01]);
+}
+
+#[test]
+fn invalid_base2_byte_is_an_error() {
+    assert!(Binary::from_str("01110001 2").is_err());
+}
+
+#[test]
+fn detects_formats_from_contents() {
+    assert_eq!(Format::detect(b"01110001 00000000"), Format::Base2);
+    assert_eq!(Format::detect(b":00000001FF\n"), Format::IntelHex);
+    assert_eq!(Format::detect(b"; header\n@8000 51 05 01\n"), Format::Annotated);
+    assert_eq!(Format::detect(&[0x51, 0x05, 0xFF, 0x00]), Format::Raw);
+}
+
+#[test]
+fn detects_formats_from_extension() {
+    assert_eq!(Format::from_extension("game.bin"), Some(Format::Raw));
+    assert_eq!(Format::from_extension("game.HEX"), Some(Format::IntelHex));
+    assert_eq!(Format::from_extension("game.txt"), None);
+    assert_eq!(Format::from_extension("game.rom"), None); // Containers, detected by their header
+}
+
+#[test]
+fn intel_hex_places_data_at_record_addresses() {
+    let text = ":020004005105A4\n:01001000717E\n:00000001FF\n";
+    let binary = Binary::from_data(text.as_bytes(), Format::IntelHex, 0).unwrap();
+
+    assert_eq!(binary.bytes[4..6], [0x51, 0x05]);
+    assert_eq!(binary.bytes[0x10], 0x71);
+    assert_eq!(binary.bytes.len(), 0x11);
+}
+
+#[test]
+fn intel_hex_rejects_bad_checksum() {
+    let error = Binary::from_data(b":01001000717F\n:00000001FF\n", Format::IntelHex, 0).unwrap_err();
+
+    assert_eq!(error, "Intel HEX line 1: checksum mismatch");
+}
+
+#[test]
+fn intel_hex_rejects_non_ascii_records() {
+    let error = Binary::from_data(":€0000000\n".as_bytes(), Format::IntelHex, 0).unwrap_err();
+
+    assert_eq!(error, "Intel HEX line 1: record contains non-hex characters");
+}
+
+#[test]
+fn intel_hex_extended_linear_address_reaches_high_banks() {
+    let text = ":020000040001F9\n:01000000AA55\n:00000001FF\n";
+    let binary = Binary::from_data(text.as_bytes(), Format::IntelHex, 0).unwrap();
+
+    assert_eq!(binary.bytes.len(), 0x10001);
+    assert_eq!(binary.bytes[0x10000], 0xAA);
+}
+
+#[test]
+fn intel_hex_requires_end_of_file_record() {
+    assert!(Binary::from_data(b":01000000AA55\n", Format::IntelHex, 0).is_err());
+}
+
+#[test]
+fn annotated_text_supports_comments_and_markers() {
+    let text = "; program\n51 05 01 ; LOAD 5 B\n@0x10\n0x70\n@20 FF";
+    let binary = Binary::from_data(text.as_bytes(), Format::Annotated, 0).unwrap();
+
+    assert_eq!(binary.bytes[0..3], [0x51, 0x05, 0x01]);
+    assert_eq!(binary.bytes[0x10], 0x70);
+    assert_eq!(binary.bytes[0x20], 0xFF);
+}
+
+#[test]
+fn annotated_text_reports_bad_tokens() {
+    let error = Binary::from_data(b"51\n1FF", Format::Annotated, 0).unwrap_err();
+
+    assert_eq!(error, "Line 2: invalid byte `1FF`");
+}
+
+#[test]
+fn annotated_text_rejects_addresses_beyond_the_image() {
+    let error = Binary::from_data(b"@FFFFFFFFFFFFFFFF 00", Format::Annotated, 0).unwrap_err();
+    assert_eq!(error, "Line 1: Address 0xFFFFFFFFFFFFFFFF is beyond the largest ROM image");
+
+    let error = Binary::from_data(b"@3FFFFF 00 01", Format::Annotated, 0).unwrap_err();
+    assert_eq!(error, "Line 1: Address 0x400000 is beyond the largest ROM image");
+}
+
+#[test]
+fn every_format_round_trips() {
+    let mut bytes = vec![0; 0x14000];
+    bytes[0] = 0x51;
+    bytes[0x11] = 0x70;
+    bytes[0x12345] = 0xAB;
+    let binary = Binary::from_image(bytes.clone());
+
+    for format in [Format::Raw, Format::Base2, Format::IntelHex, Format::Annotated] {
+        let reloaded = Binary::from_data(&binary.encode(format, 0), format, 0).unwrap();
+        assert_eq!(reloaded.bytes, bytes, "{:?}", format);
+    }
+}
+
+fn sample_container() -> RomContainer {
+    RomContainer {
+        title: "Adventure".to_string(),
+        entry_point: 0x8010,
+        load_address: 0x8000,
+        profile: "standard".to_string(),
+        rom: vec![0x71, 0x70],
+        data: vec![(0x0040, b"HI\0".to_vec())],
+        symbols: vec![ContainerSymbol { name: "START".to_string(), address: 0x8010, kind: SymbolKind::Label }],
+    }
+}
+
+#[test]
+fn container_round_trips() {
+    let container = sample_container();
+    let bytes = container.encode().unwrap();
+
+    assert_eq!(&bytes[0..4], b"CHKO");
+    assert_eq!(RomContainer::parse(&bytes).unwrap(), container);
+}
+
+#[test]
+fn container_detects_corruption() {
+    let mut bytes = sample_container().encode().unwrap();
+    bytes[12] ^= 0xFF;
+
+    assert_eq!(RomContainer::parse(&bytes).unwrap_err(), "ROM file is corrupt: CRC mismatch");
+}
+
+#[test]
+fn container_rejects_bad_magic_and_version() {
+    assert!(RomContainer::parse(b"CHIP\x01").is_err());
+
+    let mut bytes = sample_container().encode().unwrap();
+    bytes[4] = 9;
+    let body = bytes.len() - 4;
+    let crc = crc32(&bytes[..body]).to_be_bytes();
+    bytes[body..].copy_from_slice(&crc);
+
+    assert_eq!(RomContainer::parse(&bytes).unwrap_err(), "Unsupported ROM file version 9");
+}
+
+#[test]
+fn crc32_matches_reference_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn container_from_program_keeps_symbols_and_start_label() {
+    let mut lexer = Lexer::new("VAR $0x20 SCORE\nINIT:\n  WAIT\nSTART:\n  HALT\n");
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode(&parser.instructions, &mut table).unwrap();
+
+    let container = RomContainer::from_program(&program, &table, "demo", "standard", 0x8000);
+
+    assert_eq!(container.entry_point, 0x8001);
+    assert_eq!(container.symbols.iter().map(|symbol| symbol.name.as_str()).collect::<Vec<_>>(),
+        ["INIT", "SCORE", "START"]);
+    assert_eq!(container.symbols.iter().find(|symbol| symbol.name == "START").unwrap().address, 0x8001);
+    assert_eq!(container.used_rom(), &[0x71, 0x70]);
+}
+
+#[test]
+fn images_carry_the_entry_point_in_their_reset_vector() {
+    let profile = MachineProfile::default();
+    let image = sample_container().image(&profile);
+
+    assert_eq!(image.len(), 0x8000);
+    assert_eq!(&image[0x7FFE..], &[0x80, 0x10]);
+    assert_eq!(RomContainer::from_image(image, "Adventure", &profile).entry_point, 0x8010);
+    assert_eq!(RomContainer::from_image(vec![0x71], "blank", &profile).entry_point, 0x8000);
+}
+
+#[test]
+fn text_formats_name_machine_addresses() {
+    let mut bytes = vec![0; 0x8000];
+    bytes[0] = 0x51;
+    bytes[0x7FFE] = 0x80;
+    let binary = Binary::from_image(bytes.clone());
+
+    let hex = String::from_utf8(binary.encode(Format::IntelHex, 0x8000)).unwrap();
+    let annotated = String::from_utf8(binary.encode(Format::Annotated, 0x8000)).unwrap();
+    assert!(hex.starts_with(":1080000051"));
+    assert!(annotated.contains("@8000  51"));
+
+    for format in [Format::IntelHex, Format::Annotated] {
+        let reloaded = Binary::from_data(&binary.encode(format, 0x8000), format, 0x8000).unwrap();
+        assert_eq!(reloaded.bytes, bytes, "{:?}", format);
+    }
+
+    assert!(Binary::from_data(b"@10 FF", Format::Annotated, 0x8000).is_err());
+}
@@ -1,8 +1,7 @@
-use std::io;
-use rand::Rng;
 use crate::chiiko::components::{
     cpu::Cpu, chip::Chip, instruction::Instruction, cpu_operand::CpuOperand, cpu_operand::CpuOperand::JumpAddress,
-    cpu_operand::CpuOperand::Register,
+    cpu_operand::CpuOperand::Register, cpu::BC_PAIR, cpu::HL_PAIR, cpu::IJ_PAIR,
+    cpu::HALT_ADDRESS,
 };
 use crate::operation::group::{
     Group, ArithmeticVariant, LogicVariant, BranchVariant, SubroutineVariant, 
@@ -12,8 +11,10 @@ use crate::operation::group::{
 const ZERO_STATUS: u8 = 0b00000001;
 const NEGATIVE_STATUS: u8 = 0b00000010;
 const POSITIVE_STATUS: u8 = 0b00000000;
+const OVERFLOW_STATUS: u8 = 0b00001000;
 const DEFAULT_CHARACTER_LIMIT: u8 = 0xFF;
 const NULL_CHARACTER: u8 = 0;
+const BLOCK_CYCLES_PER_BYTE: u64 = 2; // One read and one write per byte
 
 pub trait Alu {
     fn execute(&mut self) -> Result<(), &'static str>;
@@ -22,6 +23,12 @@ pub trait Alu {
     variant: &ArithmeticVariant, 
     instruction: &Instruction
     ) -> Result<(), &'static str>;
+    fn evaluate_unsigned(
+    &mut self, 
+    variant: &ArithmeticVariant, 
+    left: u8, 
+    right: u8
+    ) -> Result<(u8, bool), &'static str>;
     fn evaluate_16bit_arithmetic(
     &mut self, 
     variant: &ArithmeticVariant, 
@@ -83,8 +90,9 @@ impl Alu for Cpu {
             Group::Branch(variant) => self.evaluate_branch(&variant, &instruction),
             Group::Subroutine(variant) => self.evaluate_subroutine(&variant, &instruction),
             Group::Stack(variant) => self.evaluate_stack(&variant, &instruction),
+            Group::Memory(variant) => self.evaluate_memory(&variant, &instruction),
+            Group::InputOutput(variant) => self.evaluate_io(&variant, &instruction),
             Group::System(variant) => self.evaluate_system(&variant, &instruction),
-            _ => Err("Invalid Instruction")
         }
     }
 
@@ -93,30 +101,58 @@ impl Alu for Cpu {
     variant: &ArithmeticVariant, 
     instruction: &Instruction
     ) -> Result<(), &'static str> {
+        let carry_in = self.carry() as u8;
         self.clear_flags();
         
         let left = self.find(instruction.left_operand)?;
         let right = self.find(instruction.right_operand)?;
 
-        let (result, overflow) = match variant {
-            ArithmeticVariant::Add | ArithmeticVariant::Increment => left.overflowing_add(right),
-            ArithmeticVariant::Subtract | ArithmeticVariant::Decrement => left.overflowing_sub(right),
-            ArithmeticVariant::Multiply => left.overflowing_mul(right),
-            ArithmeticVariant::Divide => if right == 0 {
+        // Carry reports unsigned overflow, overflow reports two's complement overflow
+        let (result, carry, overflow) = match variant {
+            ArithmeticVariant::Add | ArithmeticVariant::Increment => {
+                let (result, carry) = left.overflowing_add(right);
+                (result, carry, (left ^ result) & (right ^ result) & 0x80 != 0)
+            },
+            ArithmeticVariant::Subtract | ArithmeticVariant::Decrement => {
+                let (result, carry) = left.overflowing_sub(right);
+                (result, carry, (left ^ right) & (left ^ result) & 0x80 != 0)
+            },
+            ArithmeticVariant::Multiply => {
+                let (result, carry) = left.overflowing_mul(right);
+                (result, carry, (left as i8).overflowing_mul(right as i8).1)
+            },
+            // ADC/SBC chain through the carry flag so wider numbers can be handled a byte at a time
+            ArithmeticVariant::AddCarry => {
+                let (partial, first_carry) = left.overflowing_add(right);
+                let (result, second_carry) = partial.overflowing_add(carry_in);
+                let signed = left as i8 as i16 + right as i8 as i16 + carry_in as i16;
+                (result, first_carry || second_carry, signed != result as i8 as i16)
+            },
+            ArithmeticVariant::SubtractBorrow => {
+                let (partial, first_borrow) = left.overflowing_sub(right);
+                let (result, second_borrow) = partial.overflowing_sub(carry_in);
+                let signed = left as i8 as i16 - right as i8 as i16 - carry_in as i16;
+                (result, first_borrow || second_borrow, signed != result as i8 as i16)
+            },
+            ArithmeticVariant::SignedMultiply => {
+                let (result, overflow) = (left as i8).overflowing_mul(right as i8);
+                (result as u8, false, overflow)
+            },
+            ArithmeticVariant::SignedDivide => if right == 0 {
                 return Err("Division by zero")
-            } else { 
-                left.overflowing_div(right)
+            } else {
+                // -128 / -1 is the only quotient that does not fit
+                let (result, overflow) = (left as i8).overflowing_div(right as i8);
+                (result as u8, false, overflow)
             },
-            ArithmeticVariant::Remainder => if right == 0 {
-                return Err("Modulo by zero")
-            } else { 
-                left.overflowing_rem(right)
+            _ => {
+                let (result, carry) = self.evaluate_unsigned(variant, left, right)?;
+                (result, carry, false)
             },
-            ArithmeticVariant::Random => rand::rng().random::<u8>().overflowing_rem(right),
-            _ => return Err("Invalid Single Word Arithmetic")
         };
 
-        if overflow { self.set_carry() }
+        if carry { self.set_carry() }
+        if overflow { self.set_overflow() }
         self.set_zero_or_negative(result);
 
         if matches!(
@@ -129,6 +165,28 @@ impl Alu for Cpu {
         }
     }
 
+    fn evaluate_unsigned(
+    &mut self, 
+    variant: &ArithmeticVariant, 
+    left: u8, 
+    right: u8
+    ) -> Result<(u8, bool), &'static str> {
+        Ok(match variant {
+            ArithmeticVariant::Divide => if right == 0 {
+                return Err("Division by zero")
+            } else { 
+                left.overflowing_div(right)
+            },
+            ArithmeticVariant::Remainder => if right == 0 {
+                return Err("Modulo by zero")
+            } else { 
+                left.overflowing_rem(right)
+            },
+            ArithmeticVariant::Random => (self.random.below(right), false),
+            _ => return Err("Invalid Single Word Arithmetic")
+        })
+    }
+
     fn evaluate_16bit_arithmetic(
     &mut self, 
     variant: &ArithmeticVariant, 
@@ -148,7 +206,7 @@ impl Alu for Cpu {
         let left = self.read_register_pair(register_code)?;
         let right = self.find(instruction.right_operand)? as u16;
 
-        let (result, overflow) = match variant {
+        let (result, carry) = match variant {
             ArithmeticVariant::Sum => left.overflowing_add(right),
             ArithmeticVariant::Difference => left.overflowing_sub(right),
             ArithmeticVariant::Product => left.overflowing_mul(right),
@@ -161,8 +219,15 @@ impl Alu for Cpu {
             },
             _ => return Err("Invalid 16-bit arithmetic CpuOperand")
         };
+        let overflow = match variant {
+            ArithmeticVariant::Sum => (left ^ result) & (right ^ result) & 0x8000 != 0,
+            ArithmeticVariant::Difference => (left ^ right) & (left ^ result) & 0x8000 != 0,
+            ArithmeticVariant::Product => (left as i16).overflowing_mul(right as i16).1,
+            _ => false,
+        };
         
-        if overflow { self.set_carry() }
+        if carry { self.set_carry() }
+        if overflow { self.set_overflow() }
         if result == 0 { self.set_zero() }
         if result & 0b10000000_00000000 > 0 { self.set_negative() }
 
@@ -193,6 +258,21 @@ impl Alu for Cpu {
             },
             LogicVariant::LeftRotate => left.rotate_left(right as u32),
             LogicVariant::RightRotate => left.rotate_right(right as u32),
+            // The left operand is the bit number, the right operand the byte it applies to
+            LogicVariant::BitTest | LogicVariant::BitSet | LogicVariant::BitClear => {
+                if left > 7 {
+                    return Err("Bit number must be 0-7")
+                }
+
+                match variant {
+                    LogicVariant::BitTest => {
+                        if right & (1 << left) == 0 { self.set_zero() }
+                        return Ok(())
+                    },
+                    LogicVariant::BitSet => right | (1 << left),
+                    _ => right & !(1 << left),
+                }
+            },
         };
 
         self.set_zero_or_negative(result);
@@ -220,10 +300,24 @@ impl Alu for Cpu {
                 self.clear_flags();
 
                 let right = self.find(instruction.right_operand)?;
-                let result = left.wrapping_sub(right);
+                let (result, carry) = left.overflowing_sub(right);
 
+                if carry { self.set_carry() }
+                if (left ^ right) & (left ^ result) & 0x80 != 0 { self.set_overflow() }
                 self.set_zero_or_negative(result)
             },
+            BranchVariant::SignedCompare => {
+                // Leaves NEGATIVE set when left < right as signed bytes, so POS/ZERO/NEG
+                // branch on the signed ordering
+                self.clear_flags();
+
+                let right = self.find(instruction.right_operand)?;
+                match (left as i8).cmp(&(right as i8)) {
+                    std::cmp::Ordering::Less => self.set_negative(),
+                    std::cmp::Ordering::Equal => self.set_zero(),
+                    std::cmp::Ordering::Greater => (),
+                }
+            },
             BranchVariant::Positive => {
                 if self.status & 0b00000011 == POSITIVE_STATUS {
                     self.relative_jump(left)
@@ -239,6 +333,21 @@ impl Alu for Cpu {
                     self.relative_jump(left)
                 }
             },
+            BranchVariant::Overflow => {
+                if self.status & OVERFLOW_STATUS != 0 {
+                    self.relative_jump(left)
+                }
+            },
+            BranchVariant::Carry => {
+                if self.carry() {
+                    self.relative_jump(left)
+                }
+            },
+            BranchVariant::NoCarry => {
+                if !self.carry() {
+                    self.relative_jump(left)
+                }
+            },
         }
 
         Ok(())
@@ -287,6 +396,14 @@ impl Alu for Cpu {
             SubroutineVariant::JumpLessEqual if right <= self.accumulator => self.set_pc(address),
             SubroutineVariant::JumpLess if right < self.accumulator => self.set_pc(address),
             SubroutineVariant::JumpNotEqual if right != self.accumulator => self.set_pc(address),
+            SubroutineVariant::JumpGreaterSigned 
+            if (right as i8) > (self.accumulator as i8) => self.set_pc(address),
+            SubroutineVariant::JumpGreaterEqualSigned 
+            if (right as i8) >= (self.accumulator as i8) => self.set_pc(address),
+            SubroutineVariant::JumpLessEqualSigned 
+            if (right as i8) <= (self.accumulator as i8) => self.set_pc(address),
+            SubroutineVariant::JumpLessSigned 
+            if (right as i8) < (self.accumulator as i8) => self.set_pc(address),
             _ => ()
         }
 
@@ -325,6 +442,24 @@ impl Alu for Cpu {
                 self.b_register = self.pop()?;
                 self.accumulator = self.pop()?;
             },
+            StackVariant::GetStackPointer | StackVariant::SetStackPointer => {
+                let register_code = match instruction.left_operand {
+                    Register(register_code) if instruction.left_operand.is_register_pair() => register_code,
+                    _ => return Err("Stack pointer can only be moved through a register pair"),
+                };
+
+                if let StackVariant::GetStackPointer = variant {
+                    self.write_register_pair(register_code, self.stack_pointer())?;
+                } else {
+                    let address = self.read_register_pair(register_code)?;
+                    self.set_stack_pointer(address);
+                }
+            },
+            StackVariant::EnterFrame => {
+                let size = self.find(instruction.left_operand)?;
+                self.enter_frame(size)?;
+            },
+            StackVariant::LeaveFrame => self.leave_frame()?,
         }
 
         Ok(())
@@ -356,7 +491,34 @@ impl Alu for Cpu {
                 }
                 self.send(instruction.right_operand, left)?;
                 self.send(instruction.left_operand, right)?;
-            }
+            },
+            // Block operations take their addresses from register pairs: HL is the source,
+            // IJ the destination and BC the number of bytes. The pairs are left unchanged.
+            MemoryVariant::BlockCopy => {
+                let source = self.read_register_pair(HL_PAIR)?;
+                let destination = self.read_register_pair(IJ_PAIR)?;
+                let count = self.read_register_pair(BC_PAIR)?;
+
+                // Copy backwards when the destination overlaps the end of the source
+                let backwards = destination.wrapping_sub(source) < count;
+                for step in 0..count {
+                    let offset = if backwards { count - 1 - step } else { step };
+                    let byte = self.read(source.wrapping_add(offset));
+                    self.write(destination.wrapping_add(offset), byte)?;
+                }
+
+                self.cycle_count += count as u64 * BLOCK_CYCLES_PER_BYTE;
+            },
+            MemoryVariant::BlockFill => {
+                let destination = self.read_register_pair(IJ_PAIR)?;
+                let count = self.read_register_pair(BC_PAIR)?;
+
+                for offset in 0..count {
+                    self.write(destination.wrapping_add(offset), left)?;
+                }
+
+                self.cycle_count += count as u64 * BLOCK_CYCLES_PER_BYTE;
+            },
         }
 
         Ok(())
@@ -378,8 +540,7 @@ impl Alu for Cpu {
 
         match variant {
             InputOutputVariant::StringInput => {
-                let mut input = String::new();
-                io::stdin().read_line(&mut input).map_err(|_| "Failed to read input")?;
+                let input = self.console.read_line()?;
 
                 for (offset, byte) in input.bytes().take(limit as usize).enumerate() {
                     self.write(address + offset as u16, byte)?;
@@ -388,8 +549,7 @@ impl Alu for Cpu {
                 Ok(())
             },
             InputOutputVariant::NumericInput => {
-                let mut input = String::new();
-                io::stdin().read_line(&mut input).map_err(|_| "Failed to read number")?;
+                let input = self.console.read_line()?;
                 let number: u8 = input.trim()
                 .parse()
                 .map_err(|_| "Invalid number input")?;
@@ -406,16 +566,14 @@ impl Alu for Cpu {
                 }
 
                 if let Ok(output) = String::from_utf8(line) {
-                    print!("{}", output);
+                    self.console.print(&output)
                 } else {
-                    return Err("Invalid UTF-8 in string output");
+                    Err("Invalid UTF-8 in string output")
                 }
-
-                Ok(())
             },
             InputOutputVariant::PrintNumber => {
-                println!("{}", self.read(address));
-                Ok(())
+                let number = self.read(address);
+                self.console.print(&format!("{}\n", number))
             },
         }
     }
@@ -426,8 +584,15 @@ impl Alu for Cpu {
     instruction: &Instruction
     ) -> Result<(), &'static str> {
         match variant {
-            SystemVariant::Halt => Ok(self.set_pc(0xFFFF)),
+            SystemVariant::Halt => Ok(self.set_pc(HALT_ADDRESS)),
             SystemVariant::Wait => Ok(()),
+            SystemVariant::SetCarry => Ok(self.set_carry()),
+            SystemVariant::ClearCarry => Ok(self.clear_carry()),
+            SystemVariant::Trap => {
+                let number = self.find(instruction.left_operand)?;
+                self.trap(number)
+            },
+            SystemVariant::TrapReturn => self.return_from_trap(),
         }
     }
 }
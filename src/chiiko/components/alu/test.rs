@@ -0,0 +1,398 @@
+use crate::chiiko::components::{
+    alu::Alu, bus::Bus, chip::Chip, cpu::Cpu, cpu_operand::CpuOperand, instruction::Instruction,
+    random::Random, console::Console,
+};
+use crate::chiiko::MachineProfile;
+use crate::operation::Operation;
+
+fn execute(cpu: &mut Cpu, mnemonic: &str, mode: u8, left: CpuOperand, right: CpuOperand) -> Result<(), &'static str> {
+    cpu.instruction = Instruction::new(Operation::from_mnemonic(mnemonic), mode, left, right);
+    cpu.execute()
+}
+
+#[test]
+fn rand_uses_machine_random_source() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.random = Random::scripted(&[42, 7]);
+
+    execute(&mut cpu, "RAND", 0x9B, CpuOperand::Register(0), CpuOperand::Value(255)).unwrap();
+    assert_eq!(cpu.accumulator, 42);
+
+    execute(&mut cpu, "RAND", 0x9B, CpuOperand::Register(0), CpuOperand::Value(255)).unwrap();
+    assert_eq!(cpu.accumulator, 7);
+}
+
+#[test]
+fn rand_with_same_seed_repeats() {
+    let mut first = Cpu::new(Bus::default());
+    let mut second = Cpu::new(Bus::default());
+    first.random = Random::seeded(1234);
+    second.random = Random::seeded(1234);
+
+    for _ in 0..16 {
+        execute(&mut first, "RAND", 0x9B, CpuOperand::Register(0), CpuOperand::Value(100)).unwrap();
+        execute(&mut second, "RAND", 0x9B, CpuOperand::Register(0), CpuOperand::Value(100)).unwrap();
+        assert_eq!(first.accumulator, second.accumulator);
+        assert!(first.accumulator < 100);
+    }
+}
+
+#[test]
+fn rand_with_zero_bound_uses_full_range() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.random = Random::scripted(&[200]);
+
+    execute(&mut cpu, "RAND", 0x21, CpuOperand::Register(0), CpuOperand::Value(0)).unwrap();
+
+    assert_eq!(cpu.accumulator, 200);
+}
+
+#[test]
+fn add_sets_overflow_separately_from_carry() {
+    let mut cpu = Cpu::new(Bus::default());
+
+    // 0x7F + 0x01 overflows as signed but not as unsigned
+    cpu.b_register = 0x01;
+    cpu.accumulator = 0x7F;
+    execute(&mut cpu, "ADD", 0x22, CpuOperand::Register(1), CpuOperand::Register(0)).unwrap();
+    assert_eq!(cpu.accumulator, 0x80);
+    assert_eq!(cpu.status & 0b0000_1100, 0b0000_1000);
+
+    // 0xFF + 0x01 carries but is -1 + 1 as signed
+    cpu.accumulator = 0xFF;
+    execute(&mut cpu, "ADD", 0x22, CpuOperand::Register(1), CpuOperand::Register(0)).unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.status & 0b0000_1100, 0b0000_0100);
+}
+
+#[test]
+fn subtract_sets_overflow_on_signed_wrap() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.accumulator = 0x80;
+    cpu.b_register = 0x01;
+
+    // SUB writes left - right into the right operand: -128 - 1
+    execute(&mut cpu, "SUB", 0x22, CpuOperand::Register(0), CpuOperand::Register(1)).unwrap();
+
+    assert_eq!(cpu.b_register, 0x7F);
+    assert_eq!(cpu.status & 0b0000_1000, 0b0000_1000);
+}
+
+#[test]
+fn signed_multiply_and_divide() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.b_register = (-6i8) as u8;
+
+    execute(&mut cpu, "SMUL", 0x12, CpuOperand::Value(3), CpuOperand::Register(1)).unwrap();
+    assert_eq!(cpu.b_register as i8, -18);
+    assert_eq!(cpu.status & 0b0000_1010, 0b0000_0010);
+
+    execute(&mut cpu, "SDIV", 0x12, CpuOperand::Value((-2i8) as u8), CpuOperand::Register(1)).unwrap();
+    assert_eq!(cpu.b_register, 0);
+
+    cpu.b_register = (-1i8) as u8;
+    execute(&mut cpu, "SDIV", 0x12, CpuOperand::Value(0x80), CpuOperand::Register(1)).unwrap();
+    assert_eq!(cpu.b_register, 0x80);
+    assert_eq!(cpu.status & 0b0000_1000, 0b0000_1000);
+
+    assert!(execute(&mut cpu, "SDIV", 0x12, CpuOperand::Value(1), CpuOperand::Value(0)).is_err());
+}
+
+#[test]
+fn signed_compare_orders_negative_numbers_first() {
+    let mut cpu = Cpu::new(Bus::default());
+
+    execute(&mut cpu, "SCMP", 0x11, CpuOperand::Value((-1i8) as u8), CpuOperand::Value(1)).unwrap();
+    assert_eq!(cpu.status & 0b0000_0011, 0b0000_0010);
+
+    execute(&mut cpu, "COMP", 0x11, CpuOperand::Value((-1i8) as u8), CpuOperand::Value(1)).unwrap();
+    assert_eq!(cpu.status & 0b0000_0011, 0b0000_0010);
+
+    execute(&mut cpu, "SCMP", 0x11, CpuOperand::Value(1), CpuOperand::Value((-1i8) as u8)).unwrap();
+    assert_eq!(cpu.status & 0b0000_0011, 0b0000_0000);
+}
+
+#[test]
+fn signed_jumps_compare_against_accumulator() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.accumulator = (-5i8) as u8;
+
+    execute(&mut cpu, "SJGT", 0x81, CpuOperand::JumpAddress(0x9000), CpuOperand::Value(3)).unwrap();
+    assert_eq!(cpu.program_counter, 0x9000);
+
+    // Unsigned, 3 is less than 0xFB so JGT does not jump
+    execute(&mut cpu, "JGT", 0x81, CpuOperand::JumpAddress(0xA000), CpuOperand::Value(3)).unwrap();
+    assert_eq!(cpu.program_counter, 0x9000);
+
+    execute(&mut cpu, "SJLT", 0x81, CpuOperand::JumpAddress(0xA000), CpuOperand::Value((-6i8) as u8)).unwrap();
+    assert_eq!(cpu.program_counter, 0xA000);
+}
+
+#[test]
+fn add_with_carry_chains_multi_byte_sums() {
+    let mut cpu = Cpu::new(Bus::default());
+
+    // 0x01FF + 0x0001, low byte in A, high byte in B
+    cpu.accumulator = 0xFF;
+    cpu.b_register = 0x01;
+    execute(&mut cpu, "ADD", 0x12, CpuOperand::Value(0x01), CpuOperand::Register(0)).unwrap();
+    assert!(cpu.carry());
+    execute(&mut cpu, "ADC", 0x12, CpuOperand::Value(0x00), CpuOperand::Register(1)).unwrap();
+
+    assert_eq!([cpu.b_register, cpu.accumulator], [0x02, 0x00]);
+    assert!(!cpu.carry());
+}
+
+#[test]
+fn subtract_with_borrow_chains_multi_byte_differences() {
+    let mut cpu = Cpu::new(Bus::default());
+
+    // 0x0200 - 0x0001; SUB and SBC compute left - right into the right operand
+    cpu.accumulator = 0x01;
+    cpu.b_register = 0x00;
+    execute(&mut cpu, "SUB", 0x12, CpuOperand::Value(0x00), CpuOperand::Register(0)).unwrap();
+    assert!(cpu.carry());
+    execute(&mut cpu, "SBC", 0x12, CpuOperand::Value(0x02), CpuOperand::Register(1)).unwrap();
+
+    assert_eq!([cpu.b_register, cpu.accumulator], [0x01, 0xFF]);
+    assert!(!cpu.carry());
+}
+
+#[test]
+fn carry_can_be_set_cleared_and_tested() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.program_counter = 0x8000;
+
+    execute(&mut cpu, "SETC", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert!(cpu.carry());
+    execute(&mut cpu, "CARY", 0x10, CpuOperand::Value(4), CpuOperand::None).unwrap();
+    assert_eq!(cpu.program_counter, 0x8004);
+
+    execute(&mut cpu, "CLRC", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert!(!cpu.carry());
+    execute(&mut cpu, "CARY", 0x10, CpuOperand::Value(4), CpuOperand::None).unwrap();
+    assert_eq!(cpu.program_counter, 0x8004);
+    execute(&mut cpu, "NCRY", 0x10, CpuOperand::Value(4), CpuOperand::None).unwrap();
+    assert_eq!(cpu.program_counter, 0x8008);
+}
+
+#[test]
+fn indirect_modes_follow_16bit_pointers() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.write(0x0010, 0x01).unwrap();
+    cpu.write(0x0011, 0x23).unwrap();
+    cpu.write(0x0123, 0x42).unwrap();
+
+    assert_eq!(cpu.resolve_address(&CpuOperand::IndirectZeroPageAddress(0x10)), Ok(0x0123));
+    assert_eq!(cpu.find(CpuOperand::IndirectMemoryAddress(0x0010)), Ok(0x42));
+
+    cpu.send(CpuOperand::IndirectZeroPageAddress(0x10), 0x99).unwrap();
+    assert_eq!(cpu.read(0x0123), 0x99);
+}
+
+#[test]
+fn indexed_operands_add_index_register_to_base() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.write(0x0122, 7).unwrap();
+    cpu.write(0x0001, 9).unwrap();
+    cpu.i_register = 2;
+    cpu.j_register = 3;
+
+    execute(&mut cpu, "ADD", 0xC9, CpuOperand::IndexedAddress(0x0120, 5), CpuOperand::Register(0)).unwrap();
+    assert_eq!(cpu.accumulator, 7);
+
+    // Zero page indexing wraps: 0xFE + 3 reads 0x0001
+    execute(&mut cpu, "ADD", 0xD9, CpuOperand::IndexedZeroPage(0xFE, 6), CpuOperand::Register(0)).unwrap();
+    assert_eq!(cpu.accumulator, 16);
+
+    cpu.send(CpuOperand::IndexedAddress(0x0120, 6), 0x55).unwrap();
+    assert_eq!(cpu.read(0x0123), 0x55);
+
+    assert!(cpu.find(CpuOperand::IndexedAddress(0x0120, 1)).is_err());
+}
+
+#[test]
+fn bit_instructions_test_set_and_clear_single_bits() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.write(0x0040, 0b0000_0100).unwrap();
+
+    execute(&mut cpu, "BTST", 0x16, CpuOperand::Value(2), CpuOperand::MemoryAddress(0x0040)).unwrap();
+    assert_eq!(cpu.status & 0b0000_0001, 0);
+    execute(&mut cpu, "BTST", 0x16, CpuOperand::Value(3), CpuOperand::MemoryAddress(0x0040)).unwrap();
+    assert_eq!(cpu.status & 0b0000_0001, 1);
+
+    execute(&mut cpu, "BSET", 0x16, CpuOperand::Value(7), CpuOperand::MemoryAddress(0x0040)).unwrap();
+    assert_eq!(cpu.read(0x0040), 0b1000_0100);
+
+    execute(&mut cpu, "BCLR", 0x16, CpuOperand::Value(2), CpuOperand::MemoryAddress(0x0040)).unwrap();
+    assert_eq!(cpu.read(0x0040), 0b1000_0000);
+
+    cpu.accumulator = 0;
+    execute(&mut cpu, "BSET", 0x19, CpuOperand::Value(0), CpuOperand::Register(0)).unwrap();
+    assert_eq!(cpu.accumulator, 1);
+
+    assert!(execute(&mut cpu, "BSET", 0x19, CpuOperand::Value(8), CpuOperand::Register(0)).is_err());
+}
+
+fn set_block_registers(cpu: &mut Cpu, source: u16, destination: u16, count: u16) {
+    cpu.write_register_pair(10, source).unwrap();
+    cpu.write_register_pair(11, destination).unwrap();
+    cpu.write_register_pair(9, count).unwrap();
+}
+
+#[test]
+fn block_copy_handles_overlapping_ranges() {
+    let mut cpu = Cpu::new(Bus::default());
+    for (offset, byte) in [1, 2, 3, 4].iter().enumerate() {
+        cpu.write(0x0100 + offset as u16, *byte).unwrap();
+    }
+
+    // Destination overlaps the end of the source
+    set_block_registers(&mut cpu, 0x0100, 0x0102, 4);
+    execute(&mut cpu, "COPY", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert_eq!((0x0100..0x0106).map(|address| cpu.read(address)).collect::<Vec<u8>>(), [1, 2, 1, 2, 3, 4]);
+
+    // Destination overlaps the start of the source
+    set_block_registers(&mut cpu, 0x0102, 0x0101, 4);
+    execute(&mut cpu, "COPY", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert_eq!((0x0100..0x0106).map(|address| cpu.read(address)).collect::<Vec<u8>>(), [1, 1, 2, 3, 4, 4]);
+
+    assert_eq!(cpu.cycle_count, 16);
+}
+
+#[test]
+fn block_fill_writes_value_across_range() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.accumulator = 0xAA;
+    set_block_registers(&mut cpu, 0, 0x0200, 0x0100);
+
+    execute(&mut cpu, "FILL", 0x90, CpuOperand::Register(0), CpuOperand::None).unwrap();
+
+    assert!((0x0200..0x0300).all(|address| cpu.read(address) == 0xAA));
+    assert_eq!(cpu.read(0x0300), 0);
+    assert_eq!(cpu.cycle_count, 0x200);
+}
+
+#[test]
+fn frames_give_stack_relative_access_to_locals_and_arguments() {
+    let mut cpu = Cpu::new(Bus::default());
+    let top = cpu.stack_pointer();
+
+    // Caller pushes an argument, CALL pushes the return address
+    cpu.push(0x2A).unwrap();
+    cpu.push(0x80).unwrap();
+    cpu.push(0x10).unwrap();
+
+    execute(&mut cpu, "ENTR", 0x10, CpuOperand::Value(2), CpuOperand::None).unwrap();
+    assert_eq!(cpu.stack_pointer(), top - 7);
+
+    execute(&mut cpu, "ADD", 0xE9, CpuOperand::StackRelative(5), CpuOperand::Register(0)).unwrap();
+    assert_eq!(cpu.accumulator, 0x2A);
+
+    cpu.send(CpuOperand::StackRelative((-1i8) as u8), 0x99).unwrap();
+    assert_eq!(cpu.read(cpu.frame_pointer() - 1), 0x99);
+
+    // Nested frames restore the caller's frame pointer
+    let outer = cpu.frame_pointer();
+    execute(&mut cpu, "ENTR", 0x10, CpuOperand::Value(1), CpuOperand::None).unwrap();
+    execute(&mut cpu, "LEAV", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert_eq!(cpu.frame_pointer(), outer);
+
+    execute(&mut cpu, "LEAV", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert_eq!(cpu.stack_pointer(), top - 3);
+    assert_eq!(cpu.frame_pointer(), top);
+}
+
+#[test]
+fn stack_pointer_moves_through_register_pairs() {
+    let mut cpu = Cpu::new(Bus::default());
+    let top = cpu.stack_pointer();
+
+    execute(&mut cpu, "GTSP", 0x20, CpuOperand::Register(10), CpuOperand::None).unwrap();
+    assert_eq!(cpu.read_register_pair(10), Ok(top));
+
+    cpu.write_register_pair(11, 0x1000).unwrap();
+    execute(&mut cpu, "STSP", 0x20, CpuOperand::Register(11), CpuOperand::None).unwrap();
+    assert_eq!(cpu.stack_pointer(), 0x1000);
+
+    assert!(execute(&mut cpu, "STSP", 0x20, CpuOperand::Register(0), CpuOperand::None).is_err());
+}
+
+#[test]
+fn trap_jumps_through_vector_table_and_returns() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.program_counter = 0x8010;
+    cpu.set_carry();
+    cpu.write(0x1C00 + 3 * 2, 0x90).unwrap();
+    cpu.write(0x1C00 + 3 * 2 + 1, 0x00).unwrap();
+
+    execute(&mut cpu, "TRAP", 0x10, CpuOperand::Value(3), CpuOperand::None).unwrap();
+    assert_eq!(cpu.program_counter, 0x9000);
+
+    cpu.clear_flags();
+    execute(&mut cpu, "RTRP", 0x00, CpuOperand::None, CpuOperand::None).unwrap();
+    assert_eq!(cpu.program_counter, 0x8010);
+    assert!(cpu.carry());
+}
+
+#[test]
+fn trap_without_vector_is_an_error() {
+    let mut cpu = Cpu::new(Bus::default());
+
+    assert!(execute(&mut cpu, "TRAP", 0x10, CpuOperand::Value(4), CpuOperand::None).is_err());
+}
+
+fn host_call_cpu() -> Cpu {
+    let profile = MachineProfile { host_calls: true, ..MachineProfile::default() };
+    Cpu::from_profile(Bus::from_profile(&profile, &[]).unwrap(), &profile)
+}
+
+#[test]
+fn host_calls_print_and_exit() {
+    let mut cpu = host_call_cpu();
+    cpu.console = Console::buffered(&[]);
+    cpu.write_register_pair(10, 1234).unwrap();
+    cpu.accumulator = 3;
+
+    execute(&mut cpu, "TRAP", 0x10, CpuOperand::Value(0xF0), CpuOperand::None).unwrap();
+    execute(&mut cpu, "TRAP", 0x10, CpuOperand::Value(0xF2), CpuOperand::None).unwrap();
+
+    assert_eq!(cpu.console.output(), "1234\n");
+    assert_eq!(cpu.exit_code, Some(3));
+    assert_eq!(cpu.program_counter, 0xFFFF);
+}
+
+#[test]
+fn host_call_reads_file_into_memory() {
+    let path = std::env::temp_dir().join("chiiko_host_call_read.txt");
+    std::fs::write(&path, b"HELLO").unwrap();
+    let mut cpu = host_call_cpu();
+
+    for (offset, byte) in path.to_str().unwrap().bytes().chain([0]).enumerate() {
+        cpu.write(0x0400 + offset as u16, byte).unwrap();
+    }
+    cpu.write_register_pair(10, 0x0400).unwrap();
+    cpu.write_register_pair(11, 0x0800).unwrap();
+    cpu.write_register_pair(9, 3).unwrap();
+
+    execute(&mut cpu, "TRAP", 0x10, CpuOperand::Value(0xF1), CpuOperand::None).unwrap();
+
+    assert_eq!(cpu.read_register_pair(9), Ok(3));
+    assert_eq!([cpu.read(0x0800), cpu.read(0x0801), cpu.read(0x0802), cpu.read(0x0803)], [b'H', b'E', b'L', 0]);
+    assert!(!cpu.carry());
+
+    cpu.write(0x0400, b'#').unwrap();
+    execute(&mut cpu, "TRAP", 0x10, CpuOperand::Value(0xF1), CpuOperand::None).unwrap();
+    assert!(cpu.carry());
+    assert_eq!(cpu.read_register_pair(9), Ok(0));
+}
+
+#[test]
+fn relative_branches_can_go_backwards() {
+    let mut cpu = Cpu::new(Bus::default());
+    cpu.program_counter = 0x8010;
+
+    execute(&mut cpu, "POS", 0x10, CpuOperand::Value((-8i8) as u8), CpuOperand::None).unwrap();
+
+    assert_eq!(cpu.program_counter, 0x8008);
+}
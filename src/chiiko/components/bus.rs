@@ -1,40 +1,81 @@
-use crate::chiiko::components::{chip::Chip, ram::Ram, rom::Rom};
+use crate::chiiko::components::{chip::Chip, memory_exchange::MemoryExchange, ram::Ram, rom::Rom};
+use crate::chiiko::machine_profile::MachineProfile;
 
 pub struct Bus {
     ram: Ram,
     rom: Rom,
+    bank_select_address: Option<u16>,
 }
 
 impl Bus {
     pub fn default() -> Self {
-        Self {
-            ram: Ram::default(),
-            rom: Rom::default(),
-        }
+        let profile = MachineProfile::default();
+        Self::from_profile(&profile, &[])
+            .expect("Standard machine profile is valid")
     }
 
     pub fn new(ram: Ram, rom: Rom) -> Self {
         Self {
             ram: ram,
             rom: rom,
+            bank_select_address: MachineProfile::default().bank_select_address(),
         }
     }
+
+    pub fn from_profile(profile: &MachineProfile, rom_image: &[u8]) -> Result<Self, &'static str> {
+        let ram = Ram::with_size(profile.ram_size, profile.ram_base);
+        let rom = Rom::with_window(rom_image, profile.rom_base, profile.rom_size)?;
+
+        Ok(Self {
+            ram,
+            rom,
+            bank_select_address: profile.bank_select_address(),
+        })
+    }
+
+    pub fn export_ram(&self) -> Vec<u8> {
+        self.ram.export()
+    }
+
+    pub fn import_ram(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        self.ram.import(0, data)
+    }
+
+    pub fn selected_bank(&self) -> u8 {
+        self.rom.selected_bank()
+    }
+
+    pub fn select_bank(&mut self, bank: u8) -> Result<(), &'static str> {
+        self.rom.select_bank(bank)
+    }
+
+    fn is_bank_select(&self, address: u16) -> bool {
+        self.bank_select_address == Some(address)
+    }
 }
 
 impl Chip for Bus {
     fn read(&self, address: u16) -> u8 {
-        match address {
-            0x0000..=0x1FFF => self.ram.read(address),
-            0x8000..=0xFFFF => self.rom.read(address),
-            _ => 0
+        if self.is_bank_select(address) {
+            self.rom.selected_bank()
+        } else if self.ram.contains(address) {
+            self.ram.read(address)
+        } else if self.rom.contains(address) {
+            self.rom.read(address)
+        } else {
+            0
         }
     }
 
     fn write(&mut self, address: u16, value: u8) -> Result<(), &'static str> {
-        match address {
-            0x0000..=0x1FFF => self.ram.write(address, value),
-            0x8000..=0xFFFF => Err("Cannot write to ROM"),
-            _ => Err("Write to un-mapped address")
+        if self.is_bank_select(address) {
+            self.rom.select_bank(value)
+        } else if self.ram.contains(address) {
+            self.ram.write(address, value)
+        } else if self.rom.contains(address) {
+            Err("Cannot write to ROM")
+        } else {
+            Err("Write to un-mapped address")
         }
     }
 
@@ -49,4 +90,4 @@ impl Chip for Bus {
         let _ = self.rom.reset()?;
         Ok(())
     }
-}
\ No newline at end of file
+}
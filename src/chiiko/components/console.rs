@@ -0,0 +1,55 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+// Where the machine's text input and output go. The standard console uses the terminal;
+// a buffered console replays queued input lines and keeps everything printed, for tests
+// and tools that need to inspect a program's output.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Console {
+    Standard,
+    Buffered { input: VecDeque<String>, output: String },
+}
+
+impl Console {
+    pub fn buffered(input: &[&str]) -> Self {
+        Console::Buffered {
+            input: input.iter().map(|line| line.to_string()).collect(),
+            output: String::new(),
+        }
+    }
+
+    pub fn print(&mut self, text: &str) -> Result<(), &'static str> {
+        match self {
+            Console::Standard => {
+                print!("{}", text);
+                io::stdout().flush().map_err(|_| "Failed to write output")
+            },
+            Console::Buffered { output, .. } => {
+                output.push_str(text);
+                Ok(())
+            },
+        }
+    }
+
+    // Lines are returned with their trailing newline, as `read_line` gives them
+    pub fn read_line(&mut self) -> Result<String, &'static str> {
+        match self {
+            Console::Standard => {
+                let mut line = String::new();
+                io::stdin().read_line(&mut line).map_err(|_| "Failed to read input")?;
+                Ok(line)
+            },
+            Console::Buffered { input, .. } => input
+                .pop_front()
+                .map(|line| format!("{}\n", line))
+                .ok_or("No more input"),
+        }
+    }
+
+    pub fn output(&self) -> &str {
+        match self {
+            Console::Standard => "",
+            Console::Buffered { output, .. } => output,
+        }
+    }
+}
@@ -1,12 +1,15 @@
 use crate::chiiko::components::{
     chip::Chip, bus::Bus, cpu_operand::CpuOperand::*, instruction::Instruction, cpu_operand::CpuOperand,
+    random::Random, console::Console, host_call::HostCall,
 };
-use crate::operation::Operation;
+use crate::chiiko::save_state::SaveState;
+use crate::chiiko::machine_profile::MachineProfile;
+use crate::codec::{self, MAXIMUM_LENGTH};
 
-const RESET_VECTOR_ADDRESS: u16 = 0xFFFE; // The last two bytes of ROM (big endian)
-const NO_OPERAND: u8 = 0;
-const OPERAND_ERROR: u8 = 0xF;
-const STACK_ADDRESS: u16 = 0x1FFF;
+pub const BC_PAIR: u8 = 9;
+pub const HL_PAIR: u8 = 10;
+pub const IJ_PAIR: u8 = 11;
+pub const HALT_ADDRESS: u16 = 0xFFFF; // HALT parks the program counter here
 
 pub struct Cpu {
     pub accumulator: u8,
@@ -18,15 +21,28 @@ pub struct Cpu {
     pub j_register: u8,
     pub program_counter: u16,
     stack_pointer: u16,
+    frame_pointer: u16, // Base for stack relative operands, set up by ENTR
+    stack_address: u16,
+    entry_point: u16, // Where reset leaves the program counter
     pub status : u8,
     bus: Bus,
-    pub cycle_count: u8,
+    pub cycle_count: u64,
     pub instruction: Instruction,
+    pub random: Random,
+    pub console: Console,
+    trap_table: u16,
+    host_calls: bool,
+    pub exit_code: Option<u8>, // Set when a program stops through the exit host call
 }
 
 impl Cpu {
+    #[cfg(test)]
     pub fn new(bus: Bus) -> Self {
-        let mut cpu = Self {
+        Self::from_profile(bus, &MachineProfile::default())
+    }
+
+    pub fn from_profile(bus: Bus, profile: &MachineProfile) -> Self {
+        Self {
             accumulator: 0,
             b_register: 0,
             c_register: 0,
@@ -34,30 +50,31 @@ impl Cpu {
             l_register: 0,
             i_register: 0,
             j_register: 0,
-            program_counter: 0,
-            stack_pointer: STACK_ADDRESS,
+            program_counter: profile.rom_base,
+            stack_pointer: profile.stack_address,
+            frame_pointer: profile.stack_address,
+            stack_address: profile.stack_address,
+            entry_point: profile.rom_base,
             status : 0,
             cycle_count: 0,
             bus: bus,
             instruction: Instruction::default(),
-        };
-
-        cpu.program_counter = cpu.fetch_reset_vector();
-
-        cpu
+            random: profile.seed.map(Random::seeded).unwrap_or_else(Random::from_entropy),
+            console: Console::Standard,
+            trap_table: profile.trap_table,
+            host_calls: profile.host_calls,
+            exit_code: Option::None,
+        }
     }
 
     pub fn find(&self, source: CpuOperand) -> Result<u8, &'static str> {
         match source {
             Value(value) => Ok(value),
             Register(register_code) => self.read_register(register_code),
-            IndirectRegister(register_code) => Ok(
-            self.read(self.register_pointer(register_code).unwrap())
-            ),
-            ZeroPageAddress(address) => Ok(self.read(address as u16)),
-            IndirectZeroPageAddress(address) => Ok(self.read(self.read(address as u16) as u16)),
-            MemoryAddress(address) | JumpAddress(address) => Ok(self.read(address)),
-            IndirectMemoryAddress(address) => Ok(self.read(self.read(address) as u16)),
+            JumpAddress(address) => Ok(self.read(address)),
+            IndirectRegister(_) | ZeroPageAddress(_) | IndirectZeroPageAddress(_) |
+            MemoryAddress(_) | IndirectMemoryAddress(_) | IndexedAddress(..) |
+            IndexedZeroPage(..) | StackRelative(_) => Ok(self.read(self.resolve_address(&source)?)),
             None => Ok(0),
             Error => Err("Invalid source"),
         }
@@ -66,15 +83,13 @@ impl Cpu {
     pub fn send(&mut self, destination: CpuOperand, value: u8) -> Result<(), &'static str> {
         match destination {
             Register(register_code) => self.write_register(register_code, value),
-            IndirectRegister(register_code) => self.write(
-            self.read_register(register_code).unwrap() as u16, value
-            ),
-            ZeroPageAddress(address) => self.write(address as u16, value),
-            IndirectZeroPageAddress(address) => self.write(
-            self.read(address as u16) as u16, value
-            ),
-            MemoryAddress(address) | JumpAddress(address) => self.write(address, value),
-            IndirectMemoryAddress(address) => self.write(self.read(address) as u16, value),
+            JumpAddress(address) => self.write(address, value),
+            IndirectRegister(_) | ZeroPageAddress(_) | IndirectZeroPageAddress(_) |
+            MemoryAddress(_) | IndirectMemoryAddress(_) | IndexedAddress(..) | IndexedZeroPage(..) |
+            StackRelative(_) => {
+                let address = self.resolve_address(&destination)?;
+                self.write(address, value)
+            },
             Error | None | Value(_) => Err("Invalid destination"),
         }
     }
@@ -85,15 +100,35 @@ impl Cpu {
                 9..=11 => self.read_register_pair(*register_code),
                 _ => Err("Direct Register does not resolve to address"),
             },
-            IndirectRegister(register_code) => Ok(self.read_register(*register_code).unwrap() as u16),
+            IndirectRegister(register_code) => self.register_pointer(*register_code),
             ZeroPageAddress(address) => Ok(*address as u16),
-            IndirectZeroPageAddress(address) => Ok(self.read(*address as u16) as u16),
+            IndirectZeroPageAddress(address) => Ok(self.read_pointer(*address as u16)),
             MemoryAddress(address) => Ok(*address),
-            IndirectMemoryAddress(address) => Ok(self.read(*address) as u16),
+            IndirectMemoryAddress(address) => Ok(self.read_pointer(*address)),
+            IndexedAddress(base, register_code) => {
+                Ok(base.wrapping_add(self.read_index(*register_code)? as u16))
+            },
+            // Zero page indexing wraps within the zero page
+            IndexedZeroPage(base, register_code) => {
+                Ok(base.wrapping_add(self.read_index(*register_code)?) as u16)
+            },
+            StackRelative(offset) => Ok(self.frame_pointer.wrapping_add(*offset as i8 as u16)),
             Error | None | Value(_) | JumpAddress(_) => Err("Invalid destination"),
         }
     }
 
+    fn read_index(&self, register_code: u8) -> Result<u8, &'static str> {
+        match register_code {
+            5 | 6 => self.read_register(register_code),
+            _ => Err("Index register must be I or J"),
+        }
+    }
+
+    // Pointers are two bytes, high byte first, like every other address on the machine
+    pub fn read_pointer(&self, address: u16) -> u16 {
+        u16::from_be_bytes([self.read(address), self.read(address.wrapping_add(1))])
+    }
+
     // Returns register values as an Address
     pub fn register_pointer(&self, register_code: u8) -> Result<u16, &'static str> {
         match register_code {
@@ -165,69 +200,24 @@ impl Cpu {
         Ok(())
     }
 
-    pub fn fetch_reset_vector(&mut self) -> u16 {
-        let high = self.bus.read(RESET_VECTOR_ADDRESS);
-        let low = self.bus.read(RESET_VECTOR_ADDRESS + 1);
-        u16::from_be_bytes([high, low])
+    // Moves the program counter to `address`, and makes reset return there
+    pub fn set_entry_point(&mut self, address: u16) {
+        self.entry_point = address;
+        self.program_counter = address;
     }
 
     pub fn fetch_instruction(&mut self) -> Result<(), &'static str> {
-        let operation = self.fetch_operation();
-        let mode = self.fetch_grammar(&operation);
-        let [left, right] = [self.fetch_operand(mode >> 4), self.fetch_operand(mode & 0x0F)];
-
-        self.instruction = Instruction::new(operation, mode, left, right);
+        let bytes: Vec<u8> = (0..MAXIMUM_LENGTH as u16)
+            .map(|offset| self.bus.read(self.program_counter.wrapping_add(offset)))
+            .collect();
+        let (instruction, length) = codec::decode(&bytes)?;
 
-        Ok(())
-    }
-
-    fn fetch_operation(&mut self) -> Operation {
-        let byte = self.fetch_byte();
-        Operation::from_byte(byte)
-    }
-
-    fn fetch_grammar(&mut self, operation: &Operation) -> u8 {
-        if operation.has_default_mode() {
-            operation.default_mode
-        } else {
-            self.fetch_byte()
+        for _ in 0..length {
+            self.increment_pc();
         }
-    }
 
-    fn fetch_operand(&mut self, mode: u8) -> CpuOperand {
-        if mode == NO_OPERAND {
-            return CpuOperand::None
-        } else if mode == OPERAND_ERROR {
-            return CpuOperand::Error
-        }
-
-        // fetches 0-2 bytes depending on the mode
-        let value: u16 = match mode {
-            1..=5 => self.fetch_byte() as u16,
-            6..=8 => u16::from_be_bytes([self.fetch_byte(), self.fetch_byte()]),
-            _ => 0xFFFF // Fetch no bytes
-        };
-
-        match mode {
-            1 => CpuOperand::Value(value as u8),
-            2 => CpuOperand::Register(value as u8),
-            3 => CpuOperand::IndirectRegister(value as u8),
-            4 => CpuOperand::ZeroPageAddress(value as u8),
-            5 => CpuOperand::IndirectZeroPageAddress(value as u8),
-            6 => CpuOperand::MemoryAddress(value),
-            7 => CpuOperand::IndirectMemoryAddress(value),
-            8 => CpuOperand::JumpAddress(value),
-            9 => CpuOperand::Register(0),
-            10 => CpuOperand::Value(1),
-            11 => CpuOperand::Value(255),
-            _ => CpuOperand::Error,
-        }
-    }
-
-    fn fetch_byte(&mut self) -> u8 {
-        let byte = self.bus.read(self.program_counter);
-        self.increment_pc();
-        byte
+        self.instruction = instruction;
+        Ok(())
     }
 
     pub fn increment_pc(&mut self) {
@@ -244,8 +234,9 @@ impl Cpu {
         self.program_counter = address;
     }
 
+    // Displacements are signed and measured from the next instruction
     pub fn relative_jump(&mut self, offset: u8) {
-        self.program_counter = self.program_counter.wrapping_add(offset as u16);
+        self.program_counter = self.program_counter.wrapping_add(offset as i8 as u16);
     }
 
     pub fn pop(&mut self) -> Result<u8, &'static str> {
@@ -263,6 +254,113 @@ impl Cpu {
         Ok(())
     }
 
+    pub fn stack_pointer(&self) -> u16 {
+        self.stack_pointer
+    }
+
+    // The stack's base, where the stack pointer starts and returns to on reset
+    pub fn stack_address(&self) -> u16 {
+        self.stack_address
+    }
+
+    pub fn set_stack_pointer(&mut self, address: u16) {
+        self.stack_pointer = address;
+    }
+
+    pub fn frame_pointer(&self) -> u16 {
+        self.frame_pointer
+    }
+
+    pub fn set_frame_pointer(&mut self, address: u16) {
+        self.frame_pointer = address;
+    }
+
+    // Saves the caller's frame pointer and reserves `size` bytes of locals. The first
+    // local is at `%0`, the next at `%-1`; arguments pushed before CALL start at `%5`.
+    pub fn enter_frame(&mut self, size: u8) -> Result<(), &'static str> {
+        let [high, low] = self.frame_pointer.to_be_bytes();
+        self.push(high)?;
+        self.push(low)?;
+
+        self.frame_pointer = self.stack_pointer;
+        self.stack_pointer = self.stack_pointer.wrapping_sub(size as u16);
+        Ok(())
+    }
+
+    pub fn leave_frame(&mut self) -> Result<(), &'static str> {
+        self.stack_pointer = self.frame_pointer;
+
+        let low = self.pop()?;
+        let high = self.pop()?;
+        self.frame_pointer = u16::from_be_bytes([high, low]);
+        Ok(())
+    }
+
+    // Software interrupt: host calls are answered directly, anything else saves the return
+    // address and status on the stack and jumps through the vector table
+    pub fn trap(&mut self, number: u8) -> Result<(), &'static str> {
+        if let Some(call) = HostCall::from_number(number).filter(|_| self.host_calls) {
+            return self.service_host_call(call)
+        }
+
+        let vector = self.read_pointer(self.trap_table.wrapping_add(number as u16 * 2));
+        if vector == 0 {
+            return Err("Unhandled TRAP: no vector installed")
+        }
+
+        let [high, low] = self.program_counter.to_be_bytes();
+        self.push(high)?;
+        self.push(low)?;
+        self.push(self.status)?;
+        self.set_pc(vector);
+        Ok(())
+    }
+
+    pub fn return_from_trap(&mut self) -> Result<(), &'static str> {
+        self.status = self.pop()?;
+        let low = self.pop()?;
+        let high = self.pop()?;
+        self.set_pc(u16::from_be_bytes([high, low]));
+        Ok(())
+    }
+
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            registers: [
+                self.accumulator, self.b_register, self.c_register, self.h_register,
+                self.l_register, self.i_register, self.j_register,
+            ],
+            program_counter: self.program_counter,
+            stack_pointer: self.stack_pointer,
+            frame_pointer: self.frame_pointer,
+            status: self.status,
+            cycle_count: self.cycle_count,
+            ram: self.bus.export_ram(),
+            selected_bank: self.bus.selected_bank(),
+            random: self.random.clone(),
+            exit_code: self.exit_code,
+            console: self.console.clone(),
+        }
+    }
+
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), &'static str> {
+        for (code, value) in state.registers.iter().enumerate() {
+            self.write_register(code as u8, *value)?;
+        }
+
+        self.bus.import_ram(&state.ram)?;
+        self.bus.select_bank(state.selected_bank)?;
+        self.program_counter = state.program_counter;
+        self.stack_pointer = state.stack_pointer;
+        self.frame_pointer = state.frame_pointer;
+        self.status = state.status;
+        self.cycle_count = state.cycle_count;
+        self.random = state.random.clone();
+        self.exit_code = state.exit_code;
+        self.console = state.console.clone();
+        Ok(())
+    }
+
     pub fn clear_flags(&mut self) {
         self.status = 0;
     }
@@ -284,8 +382,16 @@ impl Cpu {
         self.status |= 0b0000_0100;
     }
 
-    pub fn set_interrupt(&mut self) {
-        self.status |= 0b1000_0000;
+    pub fn clear_carry(&mut self) {
+        self.status &= !0b0000_0100;
+    }
+
+    pub fn carry(&self) -> bool {
+        self.status & 0b0000_0100 != 0
+    }
+
+    pub fn set_overflow(&mut self) {
+        self.status |= 0b0000_1000;
     }
 }
 
@@ -314,10 +420,12 @@ impl Chip for Cpu {
         self.l_register = 0;
         self.i_register = 0;
         self.j_register = 0;
-        self.program_counter = self.fetch_reset_vector();
-        self.stack_pointer = STACK_ADDRESS;
+        self.program_counter = self.entry_point;
+        self.stack_pointer = self.stack_address;
+        self.frame_pointer = self.stack_address;
         self.status = 0;
         self.cycle_count = 0;
+        self.exit_code = Option::None;
         Ok(())
     }
 }
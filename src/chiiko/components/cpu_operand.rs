@@ -9,6 +9,9 @@ pub enum CpuOperand {
     MemoryAddress(u16),
     IndirectMemoryAddress(u16),
     JumpAddress(u16),
+    IndexedAddress(u16, u8), // Base address and the code of the index register (I or J)
+    IndexedZeroPage(u8, u8),
+    StackRelative(u8), // Signed offset from the frame pointer
     Error,
 }
 
@@ -18,7 +21,8 @@ impl CpuOperand {
         self, 
         CpuOperand::IndirectRegister(_) | CpuOperand::ZeroPageAddress(_) | 
         CpuOperand::IndirectZeroPageAddress(_) | CpuOperand::MemoryAddress(_) | 
-        CpuOperand::IndirectMemoryAddress(_))
+        CpuOperand::IndirectMemoryAddress(_) | CpuOperand::IndexedAddress(..) |
+        CpuOperand::IndexedZeroPage(..) | CpuOperand::StackRelative(_))
     }
 
     pub fn is_register(&self) -> bool {
@@ -41,8 +45,4 @@ impl CpuOperand {
             _ => false
         }
     }
-
-    pub fn is_none(&self) -> bool {
-        matches!(self, CpuOperand::None)
-    }
 }
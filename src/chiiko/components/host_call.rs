@@ -0,0 +1,69 @@
+use std::fs;
+
+use crate::chiiko::components::{chip::Chip, cpu::Cpu, cpu::BC_PAIR, cpu::HL_PAIR, cpu::IJ_PAIR, cpu::HALT_ADDRESS};
+
+const FILENAME_LIMIT: u16 = 0xFF;
+
+// Trap numbers the emulator answers itself when host calls are enabled, instead of
+// jumping through the program's vector table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HostCall {
+    PrintNumber, // Prints HL as a decimal number
+    ReadFile,    // Reads the file named at HL into IJ, at most BC bytes; BC becomes the count
+    Exit,        // Stops the machine with A as the exit code
+}
+
+impl HostCall {
+    pub fn from_number(number: u8) -> Option<Self> {
+        match number {
+            0xF0 => Some(HostCall::PrintNumber),
+            0xF1 => Some(HostCall::ReadFile),
+            0xF2 => Some(HostCall::Exit),
+            _ => None,
+        }
+    }
+}
+
+impl Cpu {
+    pub fn service_host_call(&mut self, call: HostCall) -> Result<(), &'static str> {
+        match call {
+            HostCall::PrintNumber => {
+                let number = self.read_register_pair(HL_PAIR)?;
+                self.console.print(&format!("{}\n", number))
+            },
+            HostCall::ReadFile => {
+                let filename = self.read_host_string(self.read_register_pair(HL_PAIR)?)?;
+                let destination = self.read_register_pair(IJ_PAIR)?;
+                let limit = self.read_register_pair(BC_PAIR)?;
+
+                // A missing file is the program's problem, not the machine's: report it with carry
+                self.clear_carry();
+                let Ok(contents) = fs::read(&filename) else {
+                    self.set_carry();
+                    return self.write_register_pair(BC_PAIR, 0)
+                };
+
+                let count = contents.len().min(limit as usize) as u16;
+                for (offset, byte) in contents.iter().take(count as usize).enumerate() {
+                    self.write(destination.wrapping_add(offset as u16), *byte)?;
+                }
+
+                self.write_register_pair(BC_PAIR, count)
+            },
+            HostCall::Exit => {
+                self.exit_code = Some(self.accumulator);
+                self.set_pc(HALT_ADDRESS);
+                Ok(())
+            },
+        }
+    }
+
+    fn read_host_string(&self, address: u16) -> Result<String, &'static str> {
+        let bytes: Vec<u8> = (0..FILENAME_LIMIT)
+            .map(|offset| self.read(address.wrapping_add(offset)))
+            .take_while(|byte| *byte != 0)
+            .collect();
+
+        String::from_utf8(bytes).map_err(|_| "Invalid UTF-8 in file name")
+    }
+}
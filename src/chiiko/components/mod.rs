@@ -1,12 +1,15 @@
-mod chip;
-mod memory_exchange;
-mod bus;
-mod ram;
-mod rom;
-mod cpu;
-mod cpu_operand;
-mod instruction;
-mod alu;
+pub mod chip;
+pub mod memory_exchange;
+pub mod bus;
+pub mod ram;
+pub mod rom;
+pub mod cpu;
+pub mod cpu_operand;
+pub mod instruction;
+pub mod alu;
+pub mod random;
+pub mod console;
+pub mod host_call;
 
 #[cfg(test)]
 mod test;
@@ -3,42 +3,40 @@ use crate::chiiko::components::{chip::Chip, memory_exchange::MemoryExchange};
 const RAM_SIZE: usize = 0x2000;
 
 pub struct Ram {
-    memory: [u8; RAM_SIZE],
+    memory: Vec<u8>,
     base_address: u16,
 }
 
 impl Default for Ram {
     fn default() -> Self {
         Self { 
-            memory: [0; RAM_SIZE], 
+            memory: vec![0; RAM_SIZE], 
             base_address: 0, 
         }
     }
 }
 
 impl Ram {
-    pub fn new(memory: &[u8], base_address: u16) -> Self {
-        let mut ram = Self::default();
-        let _ = ram.set_base_address(base_address);
-        let _ = ram.import(0, memory);
-
-        ram
-    }
-
-    fn set_base_address(&mut self, base_address: u16) -> Result<(), &'static str> {
-        self.base_address = base_address;
-        Ok(())
+    pub fn with_size(size: usize, base_address: u16) -> Self {
+        Self {
+            memory: vec![0; size],
+            base_address,
+        }
     }
 
     fn offset(&self, address: u16) -> Option<usize> {
         let offset = address.wrapping_sub(self.base_address) as usize;
 
-        if offset < RAM_SIZE {
+        if offset < self.memory.len() {
             Some(offset)
         } else {
             None
         }
     }
+
+    pub fn contains(&self, address: u16) -> bool {
+        self.offset(address).is_some()
+    }
 }
 
 impl Chip for Ram {
@@ -62,7 +60,7 @@ impl Chip for Ram {
     }
 
     fn reset(&mut self) -> Result<(), &'static str> {
-        self.memory = [0; RAM_SIZE];
+        self.memory.fill(0);
         Ok(())
     }
 }
@@ -72,7 +70,7 @@ impl MemoryExchange for Ram {
         let start = start_address as usize;
         let end = data.len() + start;
 
-        if end > RAM_SIZE {
+        if end > self.memory.len() {
             return Err("Imported data is too large")
         }
 
@@ -83,4 +81,4 @@ impl MemoryExchange for Ram {
     fn export(&self) -> Vec<u8> {
         self.memory.to_vec()
     }
-}
\ No newline at end of file
+}
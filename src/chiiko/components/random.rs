@@ -0,0 +1,46 @@
+use std::collections::VecDeque;
+
+// The machine's source of RAND values. Seeded generators use SplitMix64 so a seed gives the
+// same sequence on every platform; scripted sources replay fixed values for tests.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Random {
+    Seeded { state: u64 },
+    Scripted { values: VecDeque<u8> },
+}
+
+impl Random {
+    pub fn seeded(seed: u64) -> Self {
+        Random::Seeded { state: seed }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self::seeded(rand::random::<u64>())
+    }
+
+    // Values are handed out in order and then repeat
+    pub fn scripted(values: &[u8]) -> Self {
+        Random::Scripted { values: values.iter().copied().collect() }
+    }
+
+    // Returns a value in `0..bound`, where a bound of 0 means the whole byte range
+    pub fn below(&mut self, bound: u8) -> u8 {
+        let value = match self {
+            Random::Seeded { state } => {
+                *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut mixed = *state;
+                mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                ((mixed ^ (mixed >> 31)) >> 32) as u32
+            },
+            Random::Scripted { values } => match values.pop_front() {
+                Some(value) => {
+                    values.push_back(value);
+                    value as u32
+                },
+                None => 0,
+            },
+        };
+
+        if bound == 0 { value as u8 } else { (value % bound as u32) as u8 }
+    }
+}
@@ -1,59 +1,84 @@
 use crate::chiiko::components::{chip::Chip, memory_exchange::MemoryExchange};
 
-const ROM_SIZE: usize = 0x8000; // 32 KB
+pub const BANK_SIZE: usize = 0x4000; // 16 KB
+const MINIMUM_BANKS: usize = 2;
+const MAXIMUM_BANKS: usize = 0x100; // The bank select register is a single byte
 
+// The ROM window is split in two: the lower half shows whichever bank is selected and the
+// upper half always shows the last (fixed) bank, which holds the reset vector.
+// A two bank image therefore behaves exactly like a flat 32 KB ROM.
 pub struct Rom {
-    memory: [u8; ROM_SIZE],
+    banks: Vec<Vec<u8>>,
+    bank_size: usize,
     base_address: u16,
+    selected_bank: u8,
 }
 
-impl Default for Rom {    
+impl Default for Rom {
     fn default() -> Self {
-        Self { 
-            memory: [0; ROM_SIZE], 
-            base_address: 0, 
+        Self {
+            banks: vec![vec![0; BANK_SIZE]; MINIMUM_BANKS],
+            bank_size: BANK_SIZE,
+            base_address: 0,
+            selected_bank: 0,
         }
     }
 }
 
 impl Rom {
-    pub fn new(memory: &[u8], base_address: u16) -> Self {
-        let mut rom = Self::default();
-        let _ = rom.set_base_address(base_address);
-        let _ = rom.import(0, memory);
-        let _ = rom.set_reset_vector();
+    // `window_size` is the span of the address space given to ROM; half of it is the
+    // switchable window and half the fixed bank
+    pub fn with_window(memory: &[u8], base_address: u16, window_size: usize) -> Result<Self, &'static str> {
+        let bank_size = window_size / 2;
+        let mut rom = Self {
+            banks: vec![vec![0; bank_size]; MINIMUM_BANKS],
+            bank_size,
+            base_address,
+            selected_bank: 0,
+        };
+        rom.import(0, memory)?;
+
+        Ok(rom)
+    }
 
-        rom
+    pub fn fixed_bank(&self) -> usize {
+        self.banks.len() - 1
     }
 
-    fn set_base_address(&mut self, base_address: u16) -> Result<(), &'static str> {
-        self.base_address = base_address;
-        Ok(())
+    pub fn selected_bank(&self) -> u8 {
+        self.selected_bank
     }
 
-    fn set_reset_vector(&mut self) -> Result<(), &'static str> {
-        // Addresses are read in little-endian
-        let reset_address = ROM_SIZE - 2;
-        self.memory[reset_address] = (self.base_address >> 8) as u8;
-        self.memory[reset_address + 1] = (self.base_address & 0xFF) as u8;
+    pub fn select_bank(&mut self, bank: u8) -> Result<(), &'static str> {
+        if bank as usize >= self.fixed_bank() {
+            return Err("Cannot select fixed or missing ROM bank")
+        }
+
+        self.selected_bank = bank;
         Ok(())
     }
 
-    fn offset(&self, address: u16) -> Option<usize> {
+    fn offset(&self, address: u16) -> Option<(usize, usize)> {
         let offset = address.wrapping_sub(self.base_address) as usize;
 
-        if offset < ROM_SIZE {
-            Some(offset)
+        if offset < self.bank_size {
+            Some((self.selected_bank as usize, offset))
+        } else if offset < self.bank_size * 2 {
+            Some((self.fixed_bank(), offset - self.bank_size))
         } else {
             None
         }
     }
+
+    pub fn contains(&self, address: u16) -> bool {
+        self.offset(address).is_some()
+    }
 }
 
 impl Chip for Rom {
     fn read(&self, address: u16) -> u8 {
         self.offset(address)
-            .map(|index| self.memory[index])
+            .map(|(bank, index)| self.banks[bank][index])
             .unwrap_or(0xFF)
     }
 
@@ -66,25 +91,36 @@ impl Chip for Rom {
     }
 
     fn reset(&mut self) -> Result<(), &'static str> {
-        // ROM does not change on reset
+        // ROM contents do not change on reset, but the bank window returns to bank 0
+        self.selected_bank = 0;
         Ok(())
     }
 }
 
 impl MemoryExchange for Rom {
+    // Addresses are offsets into the whole image, so data can span several banks
     fn import(&mut self, start_address: u16, data: &[u8]) -> Result<(), &'static str> {
         let start = start_address as usize;
         let end = data.len() + start;
+        let bank_count = end.div_ceil(self.bank_size).max(MINIMUM_BANKS);
 
-        if end > ROM_SIZE {
+        if bank_count > MAXIMUM_BANKS {
             return Err("Imported data is too large")
         }
 
-        self.memory[start..end].copy_from_slice(data);
+        if bank_count > self.banks.len() {
+            self.banks.resize(bank_count, vec![0; self.bank_size]);
+        }
+
+        for (offset, byte) in data.iter().enumerate() {
+            let index = start + offset;
+            self.banks[index / self.bank_size][index % self.bank_size] = *byte;
+        }
+
         Ok(())
     }
 
     fn export(&self) -> Vec<u8> {
-        self.memory.to_vec()
+        self.banks.concat()
     }
-}
\ No newline at end of file
+}
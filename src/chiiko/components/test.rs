@@ -0,0 +1,108 @@
+use crate::chiiko::components::{
+    chip::Chip, memory_exchange::MemoryExchange, bus::Bus, ram::Ram, rom::Rom, rom::BANK_SIZE,
+};
+use crate::chiiko::components::random::Random;
+use crate::chiiko::MachineProfile;
+
+const BANK_SELECT_ADDRESS: u16 = 0x7FFF;
+
+fn banked_image(bank_count: usize) -> Vec<u8> {
+    (0..bank_count).flat_map(|bank| vec![bank as u8; BANK_SIZE]).collect()
+}
+
+#[test]
+fn flat_image_fills_both_windows() {
+    let rom = Rom::with_window(&banked_image(2), 0x8000, 0x8000).unwrap();
+
+    assert_eq!(rom.fixed_bank(), 1);
+    assert_eq!(rom.read(0x8000), 0);
+    assert_eq!(rom.read(0xC000), 1);
+}
+
+#[test]
+fn reset_vector_is_in_fixed_bank() {
+    let rom = Rom::with_window(&banked_image(4), 0x8000, 0x8000).unwrap();
+
+    assert_eq!(rom.fixed_bank(), 3);
+    assert_eq!(rom.read(0xFFFE), 3);
+    assert_eq!(rom.read(0xC000), 3);
+}
+
+#[test]
+fn selecting_bank_changes_lower_window_only() {
+    let mut rom = Rom::with_window(&banked_image(4), 0x8000, 0x8000).unwrap();
+
+    rom.select_bank(2).unwrap();
+
+    assert_eq!(rom.read(0x8000), 2);
+    assert_eq!(rom.read(0xC000), 3);
+}
+
+#[test]
+fn cannot_select_fixed_or_missing_bank() {
+    let mut rom = Rom::with_window(&banked_image(3), 0x8000, 0x8000).unwrap();
+
+    assert!(rom.select_bank(2).is_err());
+    assert!(rom.select_bank(9).is_err());
+    assert_eq!(rom.selected_bank(), 0);
+}
+
+#[test]
+fn reset_returns_to_first_bank() {
+    let mut rom = Rom::with_window(&banked_image(3), 0x8000, 0x8000).unwrap();
+
+    rom.select_bank(1).unwrap();
+    rom.reset().unwrap();
+
+    assert_eq!(rom.selected_bank(), 0);
+}
+
+#[test]
+fn export_returns_every_bank() {
+    let rom = Rom::with_window(&banked_image(5), 0x8000, 0x8000).unwrap();
+
+    assert_eq!(rom.export().len(), 5 * BANK_SIZE);
+}
+
+#[test]
+fn bus_writes_bank_select_register() {
+    let mut bus = Bus::new(Ram::default(), Rom::with_window(&banked_image(4), 0x8000, 0x8000).unwrap());
+
+    bus.write(BANK_SELECT_ADDRESS, 1).unwrap();
+
+    assert_eq!(bus.read(BANK_SELECT_ADDRESS), 1);
+    assert_eq!(bus.read(0x8000), 1);
+    assert!(bus.write(BANK_SELECT_ADDRESS, 3).is_err());
+}
+
+#[test]
+fn bus_follows_profile_memory_map() {
+    let profile = MachineProfile::parse(
+        "ram.base = 0x1000\nram.size = 0x400\nstack = 0x13FF\ntrap_table = 0x1000\nrom.base = 0xF000\nrom.size = 0x1000"
+    ).unwrap();
+    let mut bus = Bus::from_profile(&profile, &[0xAB]).unwrap();
+
+    assert!(bus.write(0x1000, 7).is_ok());
+    assert_eq!(bus.read(0x1000), 7);
+    assert!(bus.write(0x0000, 7).is_err());
+    assert_eq!(bus.read(0xF000), 0xAB);
+    assert_eq!(bus.read(0xFFFE), 0);
+}
+
+#[test]
+fn seeded_random_is_reproducible() {
+    let mut first = Random::seeded(99);
+    let mut second = Random::seeded(99);
+    let mut other = Random::seeded(100);
+    let sequence: Vec<u8> = (0..8).map(|_| first.below(0)).collect();
+
+    assert_eq!(sequence, (0..8).map(|_| second.below(0)).collect::<Vec<u8>>());
+    assert_ne!(sequence, (0..8).map(|_| other.below(0)).collect::<Vec<u8>>());
+}
+
+#[test]
+fn scripted_random_repeats_and_respects_bound() {
+    let mut random = Random::scripted(&[3, 12]);
+
+    assert_eq!([random.below(0), random.below(10), random.below(0)], [3, 2, 3]);
+}
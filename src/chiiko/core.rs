@@ -1,15 +1,112 @@
-// use crate::chiiko::cpu::Cpu;
-use crate::chiiko::components;
+use crate::binary::container::RomContainer;
+use crate::chiiko::components::{alu::Alu, bus::Bus, chip::Chip, cpu::Cpu, cpu::HALT_ADDRESS, random::Random};
+use crate::chiiko::machine_profile::MachineProfile;
+use crate::chiiko::save_state::SaveState;
+use crate::disassembler::{Disassembler, Line};
+
+// Why a run finished without a fault
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stop {
+    Halted,
+    Exited(u8), // The program asked for an exit code through the exit host call
+    CycleLimit,
+}
 
-#[derive(Debug, PartialEq)]
 pub struct Chiiko {
-    // pub cpu: Cpu,
+    pub cpu: Cpu,
 }
 
 impl Chiiko {
-    pub fn new() -> Self {
-        Self {
-            // cpu: Cpu::new(),
+    // Builds the bus described by `profile` and loads `rom_image` into it, ready to run from
+    // the top of ROM
+    pub fn from_profile(profile: &MachineProfile, rom_image: &[u8]) -> Result<Self, String> {
+        profile.validate()?;
+        let bus = Bus::from_profile(profile, rom_image)?;
+
+        Ok(Self {
+            cpu: Cpu::from_profile(bus, profile),
+        })
+    }
+
+    pub fn from_container(profile: &MachineProfile, container: &RomContainer) -> Result<Self, String> {
+        if container.profile != profile.name {
+            return Err(format!(
+                "ROM requires the {} machine profile, but this machine is {}",
+                container.profile, profile.name
+            ))
+        }
+
+        if container.load_address != profile.rom_base {
+            return Err(format!(
+                "ROM loads at {:#06X}, but this machine's ROM starts at {:#06X}",
+                container.load_address, profile.rom_base
+            ))
+        }
+
+        let rom_end = profile.rom_base as usize + profile.rom_size;
+        if (container.entry_point as usize) < profile.rom_base as usize || container.entry_point as usize >= rom_end {
+            return Err(format!("Entry point {:#06X} is outside ROM", container.entry_point))
         }
+
+        // The header names the entry point, so the image's reset vector is never consulted
+        let mut chiiko = Self::from_profile(profile, &container.rom)?;
+        chiiko.cpu.set_entry_point(container.entry_point);
+
+        for (address, bytes) in &container.data {
+            for (offset, byte) in bytes.iter().enumerate() {
+                chiiko.cpu.write(address.wrapping_add(offset as u16), *byte)?;
+            }
+        }
+
+        Ok(chiiko)
+    }
+
+    pub fn seed_random(&mut self, seed: u64) {
+        self.cpu.random = Random::seeded(seed);
+    }
+
+    // RAND returns these values in order, repeating them once they run out
+    pub fn script_random(&mut self, values: &[u8]) {
+        self.cpu.random = Random::scripted(values);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.cpu.program_counter == HALT_ADDRESS
+    }
+
+    // Fetches and executes one instruction. Faults name the address of the instruction.
+    pub fn step(&mut self) -> Result<(), String> {
+        let address = self.cpu.program_counter;
+
+        self.cpu.fetch_instruction()
+            .and_then(|_| self.cpu.execute())
+            .and_then(|_| self.cpu.tick())
+            .map_err(|error| format!("{} at {:#06X}", error, address))
+    }
+
+    // Steps until the program halts, or until `cycle_limit` cycles have been counted
+    pub fn run(&mut self, cycle_limit: Option<u64>) -> Result<Stop, String> {
+        while !self.is_halted() {
+            if cycle_limit.is_some_and(|limit| self.cpu.cycle_count >= limit) {
+                return Ok(Stop::CycleLimit)
+            }
+
+            self.step()?;
+        }
+
+        Ok(self.cpu.exit_code.map_or(Stop::Halted, Stop::Exited))
+    }
+
+    // The instruction about to run, decoded from the live memory map
+    pub fn trace(&self, disassembler: &Disassembler) -> Line {
+        disassembler.decode(&|address| self.cpu.read(address), self.cpu.program_counter)
+    }
+
+    pub fn save_state(&self) -> SaveState {
+        self.cpu.save_state()
+    }
+
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), String> {
+        Ok(self.cpu.load_state(state)?)
     }
 }
@@ -0,0 +1,213 @@
+use std::fs;
+
+const DEFAULT_RAM_BASE: u16 = 0x0000;
+const DEFAULT_RAM_SIZE: usize = 0x2000;
+const DEFAULT_ROM_BASE: u16 = 0x8000;
+const DEFAULT_ROM_SIZE: usize = 0x8000; // 32 KB window: one switchable and one fixed bank
+const DEFAULT_STACK_ADDRESS: u16 = 0x1FFF;
+const DEFAULT_RESET_VECTOR_ADDRESS: u16 = 0xFFFE; // The last two bytes of ROM (big endian)
+const DEFAULT_BANK_SELECT_ADDRESS: u16 = 0x7FFF;
+const DEFAULT_TRAP_TABLE_ADDRESS: u16 = 0x1C00; // 256 vectors of two bytes, below the stack
+pub const TRAP_TABLE_SIZE: usize = 0x200;
+const ADDRESS_SPACE: usize = 0x10000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Device {
+    BankSelect(u16),
+}
+
+impl Device {
+    pub fn address(&self) -> u16 {
+        match self {
+            Device::BankSelect(address) => *address,
+        }
+    }
+
+    fn from_key(key: &str, address: u16) -> Result<Self, String> {
+        match key {
+            "bank_select" => Ok(Device::BankSelect(address)),
+            _ => Err(format!("Unknown device: {}", key)),
+        }
+    }
+}
+
+// Describes where everything lives in a machine. Profiles are read from small
+// `key = value` files; anything left out keeps the standard Chiiko value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachineProfile {
+    pub name: String,
+    pub ram_base: u16,
+    pub ram_size: usize,
+    pub rom_base: u16,
+    pub rom_size: usize,
+    pub stack_address: u16,
+    pub reset_vector_address: u16,
+    pub devices: Vec<Device>,
+    pub seed: Option<u64>, // Fixes the RAND sequence; a fresh seed is drawn when absent
+    pub trap_table: u16,
+    pub host_calls: bool, // Lets the emulator answer host call traps itself; off unless asked for
+}
+
+impl Default for MachineProfile {
+    fn default() -> Self {
+        Self {
+            name: "standard".to_string(),
+            ram_base: DEFAULT_RAM_BASE,
+            ram_size: DEFAULT_RAM_SIZE,
+            rom_base: DEFAULT_ROM_BASE,
+            rom_size: DEFAULT_ROM_SIZE,
+            stack_address: DEFAULT_STACK_ADDRESS,
+            reset_vector_address: DEFAULT_RESET_VECTOR_ADDRESS,
+            devices: vec![Device::BankSelect(DEFAULT_BANK_SELECT_ADDRESS)],
+            seed: None,
+            trap_table: DEFAULT_TRAP_TABLE_ADDRESS,
+            host_calls: false,
+        }
+    }
+}
+
+impl MachineProfile {
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let raw = fs::read_to_string(filename)
+            .map_err(|error| format!("Failed to read file: {} {}", filename, error))?;
+        Self::parse(&raw)
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut profile = Self::default();
+        let mut devices_listed = false;
+
+        for (index, line) in raw.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected `key = value`", index + 1))?;
+
+            if key == "name" {
+                profile.name = value.to_string();
+                continue;
+            }
+
+            let number = Self::parse_number(value)
+                .ok_or_else(|| format!("Line {}: invalid number `{}`", index + 1, value))?;
+            let address = || u16::try_from(number)
+                .map_err(|_| format!("Line {}: address out of range `{}`", index + 1, value));
+
+            match key {
+                "ram.base" => profile.ram_base = address()?,
+                "ram.size" => profile.ram_size = number,
+                "rom.base" => profile.rom_base = address()?,
+                "rom.size" => profile.rom_size = number,
+                "stack" => profile.stack_address = address()?,
+                "reset_vector" => profile.reset_vector_address = address()?,
+                "seed" => profile.seed = Some(number as u64),
+                "trap_table" => profile.trap_table = address()?,
+                "host_calls" => profile.host_calls = number != 0,
+                _ => {
+                    let device = key
+                        .strip_prefix("device.")
+                        .ok_or_else(|| format!("Line {}: unknown key `{}`", index + 1, key))?;
+
+                    // Listing any device replaces the standard set
+                    if !devices_listed {
+                        profile.devices.clear();
+                        devices_listed = true;
+                    }
+
+                    profile.devices.push(Device::from_key(device, address()?)?);
+                },
+            }
+        }
+
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn parse_number(value: &str) -> Option<usize> {
+        let value = value.to_lowercase().replace('_', "");
+
+        if let Some(hex) = value.strip_prefix("0x") {
+            usize::from_str_radix(hex, 16).ok()
+        } else if let Some(binary) = value.strip_prefix("0b") {
+            usize::from_str_radix(binary, 2).ok()
+        } else {
+            value.parse().ok()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ram_size == 0 {
+            return Err("RAM size must not be zero".to_string())
+        }
+
+        if self.rom_size < 4 || !self.rom_size.is_multiple_of(2) {
+            return Err("ROM size must be an even number of bytes".to_string())
+        }
+
+        if self.ram_base as usize + self.ram_size > ADDRESS_SPACE {
+            return Err("RAM extends past the end of the address space".to_string())
+        }
+
+        if self.rom_base as usize + self.rom_size > ADDRESS_SPACE {
+            return Err("ROM extends past the end of the address space".to_string())
+        }
+
+        let mut regions: Vec<(String, usize, usize)> = vec![
+            ("RAM".to_string(), self.ram_base as usize, self.ram_size),
+            ("ROM".to_string(), self.rom_base as usize, self.rom_size),
+        ];
+        regions.extend(self.devices.iter().map(|device| {
+            (format!("{:?}", device), device.address() as usize, 1)
+        }));
+
+        for (index, (name, start, size)) in regions.iter().enumerate() {
+            for (other, other_start, other_size) in &regions[index + 1..] {
+                if start < &(other_start + other_size) && other_start < &(start + size) {
+                    return Err(format!("{} overlaps {}", name, other))
+                }
+            }
+        }
+
+        // Programs install their own vectors, so the whole table must be writable
+        let table_end = self.trap_table as usize + TRAP_TABLE_SIZE;
+        if !self.in_ram(self.trap_table) || table_end > self.ram_base as usize + self.ram_size {
+            return Err(format!("Trap table {:#06X} does not fit inside RAM", self.trap_table))
+        }
+
+        if !self.in_ram(self.stack_address) {
+            return Err(format!("Stack address {:#06X} is outside RAM", self.stack_address))
+        }
+
+        // The vector must sit in the fixed half of the ROM window so bank switching cannot hide it
+        let fixed_start = self.fixed_bank_base() as usize;
+        let vector = self.reset_vector_address as usize;
+        if vector < fixed_start || vector + 2 > self.rom_base as usize + self.rom_size {
+            return Err(format!(
+                "Reset vector {:#06X} is outside the fixed ROM bank", self.reset_vector_address
+            ))
+        }
+
+        Ok(())
+    }
+
+    pub fn bank_size(&self) -> usize {
+        self.rom_size / 2
+    }
+
+    pub fn fixed_bank_base(&self) -> u16 {
+        (self.rom_base as usize + self.bank_size()) as u16
+    }
+
+    pub fn in_ram(&self, address: u16) -> bool {
+        (address as usize).wrapping_sub(self.ram_base as usize) < self.ram_size
+    }
+
+    pub fn bank_select_address(&self) -> Option<u16> {
+        self.devices.iter().map(|device| match device {
+            Device::BankSelect(address) => *address,
+        }).next()
+    }
+}
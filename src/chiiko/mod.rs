@@ -1,7 +1,11 @@
 mod core;
-mod components;
+pub mod components;
+pub mod machine_profile;
+pub mod save_state;
 
 #[cfg(test)]
 mod test;
 
-pub use core::Chiiko;
+pub use core::{Chiiko, Stop};
+pub use machine_profile::MachineProfile;
+pub use save_state::SaveState;
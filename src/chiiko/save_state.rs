@@ -0,0 +1,19 @@
+use crate::chiiko::components::{console::Console, random::Random};
+
+// Everything that changes while a program runs. ROM contents and the machine profile are
+// not included, so a state can only be restored into the machine that produced it. Text
+// already written to a standard console stays on the terminal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveState {
+    pub registers: [u8; 7],
+    pub program_counter: u16,
+    pub stack_pointer: u16,
+    pub frame_pointer: u16,
+    pub status: u8,
+    pub cycle_count: u64,
+    pub ram: Vec<u8>,
+    pub selected_bank: u8,
+    pub random: Random,
+    pub exit_code: Option<u8>,
+    pub console: Console,
+}
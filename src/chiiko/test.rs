@@ -0,0 +1,213 @@
+use crate::binary::container::RomContainer;
+use crate::chiiko::{Chiiko, MachineProfile, Stop, machine_profile::Device};
+use crate::chiiko::components::{chip::Chip, console::Console, random::Random};
+use crate::disassembler::Disassembler;
+
+#[test]
+fn standard_profile_file_matches_default() {
+    let profile = MachineProfile::parse(include_str!("../../profiles/standard.profile")).unwrap();
+
+    assert_eq!(profile, MachineProfile::default());
+}
+
+#[test]
+fn compact_profile_keeps_default_devices() {
+    let profile = MachineProfile::parse(include_str!("../../profiles/compact.profile")).unwrap();
+
+    assert_eq!(profile.ram_size, 0x400);
+    assert_eq!(profile.bank_size(), 0x2000);
+    assert_eq!(profile.fixed_bank_base(), 0xE000);
+    assert_eq!(profile.devices, vec![Device::BankSelect(0x7FFF)]);
+}
+
+#[test]
+fn listed_devices_replace_defaults() {
+    let profile = MachineProfile::parse("device.bank_select = 0x4000").unwrap();
+
+    assert_eq!(profile.bank_select_address(), Some(0x4000));
+    assert_eq!(profile.devices.len(), 1);
+}
+
+#[test]
+fn rejects_overlapping_regions() {
+    let error = MachineProfile::parse("ram.size = 0x9000").unwrap_err();
+
+    assert_eq!(error, "RAM overlaps ROM");
+}
+
+#[test]
+fn rejects_device_inside_ram() {
+    assert!(MachineProfile::parse("device.bank_select = 0x0010").is_err());
+}
+
+#[test]
+fn rejects_stack_outside_ram() {
+    assert!(MachineProfile::parse("stack = 0x3000").is_err());
+}
+
+#[test]
+fn rejects_reset_vector_outside_fixed_bank() {
+    assert!(MachineProfile::parse("reset_vector = 0x9000").is_err());
+    assert!(MachineProfile::parse("reset_vector = 0xFFFF").is_err());
+}
+
+#[test]
+fn rejects_unknown_keys_and_bad_numbers() {
+    assert!(MachineProfile::parse("ram.colour = 3").is_err());
+    assert!(MachineProfile::parse("ram.size = lots").is_err());
+    assert!(MachineProfile::parse("device.printer = 0x7000").is_err());
+}
+
+#[test]
+fn machine_starts_at_the_top_of_profile_rom() {
+    let profile = MachineProfile::parse(include_str!("../../profiles/compact.profile")).unwrap();
+    let chiiko = Chiiko::from_profile(&profile, &[0x71]).unwrap();
+
+    assert_eq!(chiiko.cpu.program_counter, 0xC000);
+}
+
+fn container(profile: &str) -> RomContainer {
+    RomContainer {
+        title: "test".to_string(),
+        entry_point: 0x8004,
+        load_address: 0x8000,
+        profile: profile.to_string(),
+        rom: vec![0x71; 8],
+        data: vec![(0x0010, vec![1, 2, 3])],
+        symbols: Vec::new(),
+    }
+}
+
+#[test]
+fn container_entry_point_replaces_reset_vector() {
+    let mut chiiko = Chiiko::from_container(&MachineProfile::default(), &container("standard")).unwrap();
+
+    assert_eq!(chiiko.cpu.program_counter, 0x8004);
+    assert_eq!([chiiko.cpu.read(0xFFFE), chiiko.cpu.read(0xFFFF)], [0, 0]);
+
+    chiiko.cpu.set_pc(0x8000);
+    chiiko.cpu.reset().unwrap();
+    assert_eq!(chiiko.cpu.program_counter, 0x8004);
+}
+
+#[test]
+fn container_data_is_loaded_into_ram() {
+    let chiiko = Chiiko::from_container(&MachineProfile::default(), &container("standard")).unwrap();
+
+    assert_eq!([chiiko.cpu.read(0x10), chiiko.cpu.read(0x11), chiiko.cpu.read(0x12)], [1, 2, 3]);
+}
+
+#[test]
+fn container_must_match_machine_profile() {
+    let error = Chiiko::from_container(&MachineProfile::default(), &container("compact")).err().unwrap();
+
+    assert_eq!(error, "ROM requires the compact machine profile, but this machine is standard");
+}
+
+#[test]
+fn container_entry_point_must_be_in_rom() {
+    let mut container = container("standard");
+    container.entry_point = 0x0100;
+
+    assert!(Chiiko::from_container(&MachineProfile::default(), &container).is_err());
+}
+
+#[test]
+fn profile_seed_fixes_random_source() {
+    let profile = MachineProfile::parse("seed = 0x2A").unwrap();
+    let chiiko = Chiiko::from_profile(&profile, &[]).unwrap();
+
+    assert_eq!(profile.seed, Some(42));
+    assert_eq!(chiiko.cpu.random, Random::seeded(42));
+}
+
+#[test]
+fn save_state_restores_registers_ram_and_random() {
+    let mut chiiko = Chiiko::from_profile(&MachineProfile::default(), &[]).unwrap();
+    chiiko.seed_random(7);
+    chiiko.cpu.console = Console::buffered(&["12"]);
+    chiiko.cpu.accumulator = 5;
+    chiiko.cpu.write(0x0100, 0xAA).unwrap();
+    let state = chiiko.save_state();
+    let expected = chiiko.cpu.random.clone().below(0);
+
+    chiiko.cpu.accumulator = 0;
+    chiiko.cpu.write(0x0100, 0).unwrap();
+    chiiko.cpu.random.below(0);
+    chiiko.cpu.console.read_line().unwrap();
+    chiiko.cpu.console.print("done").unwrap();
+    chiiko.cpu.exit_code = Some(1);
+    chiiko.load_state(&state).unwrap();
+
+    assert_eq!(chiiko.cpu.accumulator, 5);
+    assert_eq!(chiiko.cpu.read(0x0100), 0xAA);
+    assert_eq!(chiiko.cpu.random.below(0), expected);
+    assert_eq!(chiiko.cpu.exit_code, None);
+    assert_eq!(chiiko.cpu.console, Console::buffered(&["12"]));
+}
+
+#[test]
+fn host_calls_are_off_unless_the_profile_enables_them() {
+    let profile = MachineProfile::parse("host_calls = 1\ntrap_table = 0x1000\n").unwrap();
+
+    assert!(!MachineProfile::default().host_calls);
+    assert!(profile.host_calls);
+    assert_eq!(profile.trap_table, 0x1000);
+}
+
+#[test]
+fn trap_table_must_fit_inside_ram() {
+    assert!(MachineProfile::parse("trap_table = 0x1F00").is_err());
+    assert!(MachineProfile::parse("ram.size = 0x0400\nstack = 0x03FF").is_err());
+}
+
+#[test]
+fn compact_profile_traps_through_its_own_table() {
+    let profile = MachineProfile::parse(include_str!("../../profiles/compact.profile")).unwrap();
+    let mut rom = vec![0; 0x4000];
+    rom[0..2].copy_from_slice(&[0x74, 0x01]); // TRAP 1
+    let mut chiiko = Chiiko::from_profile(&profile, &rom).unwrap();
+    chiiko.cpu.write(profile.trap_table + 2, 0xC0).unwrap();
+    chiiko.cpu.write(profile.trap_table + 3, 0x10).unwrap();
+
+    chiiko.step().unwrap();
+
+    assert_eq!(chiiko.cpu.program_counter, 0xC010);
+    assert_eq!(chiiko.cpu.stack_pointer(), 0x03FF - 3);
+}
+
+#[test]
+fn trace_decodes_instruction_at_program_counter() {
+    let mut rom = vec![0; 0x8000];
+    rom[0..2].copy_from_slice(&[0x23, 0xFE]); // NEG back to itself
+    let chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+
+    let line = chiiko.trace(&Disassembler::default());
+
+    assert_eq!(line.address, 0x8000);
+    assert_eq!(line.text, "NEG :0x8000");
+}
+
+#[test]
+fn run_stops_at_cycle_limit() {
+    let mut rom = vec![0; 0x8000];
+    rom[0..3].copy_from_slice(&[0x32, 0x80, 0x00]); // JUMP to itself
+    let mut chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+
+    assert_eq!(chiiko.run(Some(10)), Ok(Stop::CycleLimit));
+    assert_eq!(chiiko.cpu.cycle_count, 10);
+}
+
+#[test]
+fn run_reports_halt_and_exit_code() {
+    let mut rom = vec![0; 0x8000];
+    rom[0] = 0x70; // HALT
+    let mut chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+    assert_eq!(chiiko.run(None), Ok(Stop::Halted));
+
+    rom[0..2].copy_from_slice(&[0x74, 0xF2]); // TRAP to the exit host call
+    let profile = MachineProfile { host_calls: true, ..MachineProfile::default() };
+    let mut chiiko = Chiiko::from_profile(&profile, &rom).unwrap();
+    chiiko.cpu.accumulator = 4;
+    assert_eq!(chiiko.run(None), Ok(Stop::Exited(4)));
+}
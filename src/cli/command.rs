@@ -0,0 +1,247 @@
+use crate::assembler::parser::Parser;
+use crate::binary::Format;
+
+pub const USAGE: &str = "\
+Usage: chiiko <command> [options] <input>
+
+Commands:
+  asm      Assemble a .ku source file into an image
+  run      Run an image, a ROM container or a .ku source file
+  disasm   Disassemble an image, a ROM container or a .ku source file
+  debug    Step through a program interactively
+  tui      Run a program in a full-screen terminal view with live registers, stack and memory
+  gdb      Wait for a GDB remote protocol debugger on a local TCP port
+  cfg      Print the control-flow graph of each routine as Graphviz DOT
+  check    Check a .ku source file without writing anything
+  test     Run the .kutest files in a directory, or a single test file
+  regress  Run every .ku in a directory and compare the results with its .expected files,
+           on the machine in the directory's regress.profile when there is one
+  fmt      Rewrite a .ku source file, or every one in a directory, in the canonical style
+  lsp      Serve the language server protocol over stdin and stdout
+  dap      Serve the debug adapter protocol over stdin and stdout
+
+Options:
+  -o, --output <file>    Where asm writes the image (default: the input name with .rom), or the
+                         directory cfg writes one <routine>.dot file to (default: stdout)
+  -f, --format <name>    Image format for asm: raw, ihex, annotated, base2 or container. Without
+                         one, .bin and .hex outputs are images and anything else is a container
+  -p, --profile <file>   Machine profile (default: the standard machine)
+  --cycles <n>           Stop run after n cycles
+  --seed <n>             Seed RAND for run and debug, in place of the profile's seed
+  --host-calls           Let the emulator answer TRAP 0xF0-0xF2 (print a number, read a file
+                         and exit) for run, debug, tui, gdb, cfg, test and regress
+  --port <n>             The local port gdb listens on (default: 1234)
+  --trace                Print each instruction to stderr as run executes it
+  --check                Make fmt report the files that need formatting instead of rewriting them
+  --bless                Make regress write .expected files from the results instead of comparing
+";
+
+// How `asm` writes its output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Image(Format),
+    Container,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "container" | "rom" => Some(OutputFormat::Container),
+            _ => Format::from_name(name).map(OutputFormat::Image),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Assemble { input: String, output: Option<String>, format: Option<OutputFormat>, profile: Option<String> },
+    Run {
+        input: String,
+        profile: Option<String>,
+        cycle_limit: Option<u64>,
+        trace: bool,
+        seed: Option<u64>,
+        host_calls: bool,
+    },
+    Disassemble { input: String, profile: Option<String> },
+    Debug { input: String, profile: Option<String>, seed: Option<u64>, host_calls: bool },
+    Tui { input: String, profile: Option<String>, host_calls: bool },
+    Gdb { input: String, profile: Option<String>, port: Option<u16>, host_calls: bool },
+    Graph { input: String, output: Option<String>, profile: Option<String>, host_calls: bool },
+    Check { input: String },
+    Test { input: String, host_calls: bool },
+    Regress { input: String, profile: Option<String>, cycle_limit: Option<u64>, bless: bool, host_calls: bool },
+    Format { input: String, check: bool },
+    LanguageServer,
+    DebugAdapter,
+    Help,
+}
+
+// Everything that can be given on the command line, before it is checked against the command
+#[derive(Default)]
+struct Arguments {
+    input: Option<String>,
+    output: Option<String>,
+    format: Option<OutputFormat>,
+    profile: Option<String>,
+    cycle_limit: Option<u64>,
+    trace: bool,
+    check: bool,
+    bless: bool,
+    host_calls: bool,
+    port: Option<u16>,
+    seed: Option<u64>,
+}
+
+impl Command {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let Some((name, rest)) = args.split_first() else {
+            return Ok(Command::Help)
+        };
+
+        let allowed: &[&str] = match name.as_str() {
+            "asm" => &["--output", "--format", "--profile"],
+            "run" => &["--profile", "--cycles", "--trace", "--seed", "--host-calls"],
+            "debug" => &["--profile", "--seed", "--host-calls"],
+            "tui" => &["--profile", "--host-calls"],
+            "disasm" => &["--profile"],
+            "cfg" => &["--output", "--profile", "--host-calls"],
+            "gdb" => &["--profile", "--port", "--host-calls"],
+            "check" => &[],
+            "test" => &["--host-calls"],
+            "regress" => &["--profile", "--cycles", "--bless", "--host-calls"],
+            "fmt" => &["--check"],
+            "lsp" if rest.is_empty() => return Ok(Command::LanguageServer),
+            "lsp" => return Err("lsp takes no arguments".to_string()),
+            "dap" if rest.is_empty() => return Ok(Command::DebugAdapter),
+            "dap" => return Err("dap takes no arguments".to_string()),
+            "help" | "-h" | "--help" => return Ok(Command::Help),
+            _ => return Err(format!("Unknown command: {}", name)),
+        };
+
+        let arguments = Self::parse_arguments(rest, allowed)?;
+        let input = arguments.input.ok_or_else(|| format!("Missing input file for {}", name))?;
+
+        Ok(match name.as_str() {
+            "asm" => Command::Assemble {
+                input,
+                output: arguments.output,
+                format: arguments.format,
+                profile: arguments.profile,
+            },
+            "run" => Command::Run {
+                input,
+                profile: arguments.profile,
+                cycle_limit: arguments.cycle_limit,
+                trace: arguments.trace,
+                seed: arguments.seed,
+                host_calls: arguments.host_calls,
+            },
+            "disasm" => Command::Disassemble { input, profile: arguments.profile },
+            "debug" => Command::Debug {
+                input,
+                profile: arguments.profile,
+                seed: arguments.seed,
+                host_calls: arguments.host_calls,
+            },
+            "tui" => Command::Tui { input, profile: arguments.profile, host_calls: arguments.host_calls },
+            "gdb" => Command::Gdb {
+                input,
+                profile: arguments.profile,
+                port: arguments.port,
+                host_calls: arguments.host_calls,
+            },
+            "cfg" => Command::Graph {
+                input,
+                output: arguments.output,
+                profile: arguments.profile,
+                host_calls: arguments.host_calls,
+            },
+            "fmt" => Command::Format { input, check: arguments.check },
+            "test" => Command::Test { input, host_calls: arguments.host_calls },
+            "regress" => Command::Regress {
+                input,
+                profile: arguments.profile,
+                cycle_limit: arguments.cycle_limit,
+                bless: arguments.bless,
+                host_calls: arguments.host_calls,
+            },
+            _ => Command::Check { input },
+        })
+    }
+
+    fn parse_arguments(args: &[String], allowed: &[&str]) -> Result<Arguments, String> {
+        let mut arguments = Arguments::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                if arguments.input.replace(arg.to_string()).is_some() {
+                    return Err(format!("Unexpected argument: {}", arg))
+                }
+                continue
+            }
+
+            let option = match arg.as_str() {
+                "-o" => "--output",
+                "-f" => "--format",
+                "-p" => "--profile",
+                option => option,
+            };
+            if !allowed.contains(&option) {
+                return Err(format!("Unknown option: {}", arg))
+            }
+
+            // Flags take no value
+            match option {
+                "--trace" => {
+                    arguments.trace = true;
+                    continue
+                },
+                "--check" => {
+                    arguments.check = true;
+                    continue
+                },
+                "--bless" => {
+                    arguments.bless = true;
+                    continue
+                },
+                "--host-calls" => {
+                    arguments.host_calls = true;
+                    continue
+                },
+                _ => (),
+            }
+
+            let value = args.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+            match option {
+                "--output" => arguments.output = Some(value.to_string()),
+                "--profile" => arguments.profile = Some(value.to_string()),
+                "--format" => {
+                    let format = OutputFormat::from_name(value)
+                        .ok_or_else(|| format!("Unknown format: {}", value))?;
+                    arguments.format = Some(format);
+                },
+                "--port" => {
+                    let port = Parser::normalize_number(&value.to_uppercase())
+                        .ok()
+                        .and_then(|port| u16::try_from(port).ok())
+                        .ok_or_else(|| format!("Invalid port: {}", value))?;
+                    arguments.port = Some(port);
+                },
+                "--seed" => {
+                    let seed = Parser::normalize_number(&value.to_uppercase())
+                        .map_err(|_| format!("Invalid seed: {}", value))?;
+                    arguments.seed = Some(seed as u64);
+                },
+                _ => {
+                    let cycles = Parser::normalize_number(&value.to_uppercase())
+                        .map_err(|_| format!("Invalid cycle count: {}", value))?;
+                    arguments.cycle_limit = Some(cycles as u64);
+                },
+            }
+        }
+
+        Ok(arguments)
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+
+use crate::assembler::lexer::Lexer;
+use crate::assembler::linter;
+use crate::assembler::parser::Parser;
+use crate::assembler::source::Source;
+use crate::assembler::encoder::{Encoder, program::Program, symbol_table::SymbolTable, syntax_checker::SyntaxChecker};
+use crate::binary::{Binary, Format, container::RomContainer, container::SymbolKind};
+use crate::chiiko::{Chiiko, MachineProfile, Stop};
+use crate::cli::{command::Command, command::OutputFormat, command::USAGE, debugger::Debugger, failure::Failure};
+use crate::control_flow::ControlFlowGraph;
+use crate::dap;
+use crate::disassembler::Disassembler;
+use crate::formatter;
+use crate::gdb::{self, Stub};
+use crate::lsp;
+use crate::testing::{self, regression, regression::Verdict, suite::Suite};
+use crate::tui::{self, Tui};
+
+const DEFAULT_OUTPUT_EXTENSION: &str = "rom"; // A ROM container, which keeps RAM data
+const DEFAULT_GDB_PORT: u16 = 1234;
+
+// Runs a command line (without the program name) and returns the process exit code
+pub fn run(args: &[String]) -> u8 {
+    let result = Command::parse(args)
+        .map_err(Failure::Usage)
+        .and_then(execute);
+
+    match result {
+        Ok(code) => code,
+        Err(failure) => {
+            eprintln!("{}", failure);
+            failure.exit_code()
+        },
+    }
+}
+
+fn execute(command: Command) -> Result<u8, Failure> {
+    match command {
+        Command::Assemble { input, output, format, profile } => {
+            let profile = load_profile(profile.as_deref())?;
+            let output = output.unwrap_or_else(|| {
+                Path::new(&input).with_extension(DEFAULT_OUTPUT_EXTENSION).to_string_lossy().to_string()
+            });
+            let format = format.unwrap_or_else(|| {
+                Format::from_extension(&output).map_or(OutputFormat::Container, OutputFormat::Image)
+            });
+
+            let (program, table) = assemble(&input, &profile)?;
+            report_warnings(&input, &profile)?;
+            write_output(&program, &table, &input, &output, format, &profile)?;
+            Ok(0)
+        },
+        Command::Run { input, profile, cycle_limit, trace, seed, host_calls } => {
+            let profile = configure(load_profile(profile.as_deref())?, seed, host_calls);
+            let container = load(&input, &profile)?;
+            let mut chiiko = Chiiko::from_container(&profile, &container).map_err(Failure::Load)?;
+
+            let disassembler = Disassembler::with_labels(labels(&container));
+            let within_limit = |chiiko: &Chiiko| cycle_limit.is_none_or(|limit| chiiko.cpu.cycle_count < limit);
+            while trace && !chiiko.is_halted() && within_limit(&chiiko) {
+                eprintln!("{}", chiiko.trace(&disassembler));
+                chiiko.step().map_err(Failure::Fault)?;
+            }
+
+            match chiiko.run(cycle_limit).map_err(Failure::Fault)? {
+                Stop::Halted => Ok(0),
+                Stop::Exited(code) => Ok(code),
+                Stop::CycleLimit => Err(Failure::Fault(format!(
+                    "Stopped after {} cycles without halting", chiiko.cpu.cycle_count
+                ))),
+            }
+        },
+        Command::Disassemble { input, profile } => {
+            let profile = load_profile(profile.as_deref())?;
+            let container = load(&input, &profile)?;
+
+            let disassembler = Disassembler::with_labels(labels(&container));
+            for line in disassembler.disassemble(container.used_rom(), container.load_address) {
+                println!("{}", line);
+            }
+            Ok(0)
+        },
+        Command::Tui { input, profile, host_calls } => {
+            let profile = configure(load_profile(profile.as_deref())?, None, host_calls);
+            let container = load(&input, &profile)?;
+            let chiiko = Chiiko::from_container(&profile, &container).map_err(Failure::Load)?;
+
+            let tui = Tui::new(chiiko, container.used_rom(), container.load_address, labels(&container));
+            tui::run(tui).map_err(|error| Failure::Load(format!("Terminal UI failed: {}", error)))?;
+            Ok(0)
+        },
+        Command::Gdb { input, profile, port, host_calls } => {
+            let profile = configure(load_profile(profile.as_deref())?, None, host_calls);
+            let container = load(&input, &profile)?;
+            let chiiko = Chiiko::from_container(&profile, &container).map_err(Failure::Load)?;
+
+            let failure = |error: io::Error| Failure::Load(format!("GDB server failed: {}", error));
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port.unwrap_or(DEFAULT_GDB_PORT))).map_err(failure)?;
+            eprintln!("Waiting for a debugger on {}", listener.local_addr().map_err(failure)?);
+            gdb::serve(&listener, Stub::new(chiiko)).map_err(failure)?;
+            Ok(0)
+        },
+        Command::Graph { input, output, profile, host_calls } => {
+            let profile = configure(load_profile(profile.as_deref())?, None, host_calls);
+            let container = load(&input, &profile)?;
+
+            let graph = ControlFlowGraph::build(
+                container.used_rom(), container.load_address, container.entry_point, labels(&container), profile.host_calls
+            );
+
+            for routine in &graph.routines {
+                for problem in &routine.problems {
+                    eprintln!("{}: warning: {}", input, problem);
+                }
+
+                let dot = graph.to_dot(routine);
+                match &output {
+                    Some(directory) => {
+                        let path = Path::new(directory).join(format!("{}.dot", routine.name));
+                        fs::create_dir_all(directory)
+                            .and_then(|_| fs::write(&path, dot))
+                            .map_err(|error| Failure::Load(format!(
+                                "Failed to write file: {} {}", path.display(), error
+                            )))?;
+                    },
+                    None => print!("{}", dot),
+                }
+            }
+            Ok(0)
+        },
+        Command::Debug { input, profile, seed, host_calls } => {
+            let profile = configure(load_profile(profile.as_deref())?, seed, host_calls);
+            let container = load(&input, &profile)?;
+            let chiiko = Chiiko::from_container(&profile, &container).map_err(Failure::Load)?;
+
+            let mut debugger = Debugger::new(chiiko, labels(&container));
+            debugger.repl(io::stdin().lock(), &mut io::stdout())
+                .map_err(|error| Failure::Load(format!("Debugger I/O failed: {}", error)))?;
+            Ok(0)
+        },
+        Command::Check { input } => {
+            let profile = MachineProfile::default();
+            assemble(&input, &profile)?;
+            report_warnings(&input, &profile)?;
+            Ok(0)
+        },
+        Command::Test { input, host_calls } => {
+            let files = testing::discover(Path::new(&input)).map_err(Failure::Load)?;
+            let (mut passed, mut failed) = (0, 0);
+
+            for file in files {
+                let filename = file.to_string_lossy().to_string();
+                let suite = Suite::from_file(&filename).map_err(Failure::Load)?;
+
+                // The program and profile are named relative to the test file
+                let directory = file.parent().unwrap_or(Path::new(""));
+                let profile = suite.profile.as_ref().map(|name| directory.join(name).to_string_lossy().to_string());
+                let profile = configure(load_profile(profile.as_deref())?, None, host_calls);
+                let container = load(&directory.join(&suite.program).to_string_lossy(), &profile)?;
+
+                for case in &suite.cases {
+                    let problems = testing::run_case(case, &container, &profile);
+                    if problems.is_empty() {
+                        passed += 1;
+                        println!("test {} [{}] ... ok", filename, case.name);
+                        continue;
+                    }
+
+                    failed += 1;
+                    println!("test {} [{}] ... FAILED", filename, case.name);
+                    for problem in problems {
+                        println!("    {}", problem.replace('\n', "\n    ").trim_end());
+                    }
+                }
+            }
+
+            println!("{} passed, {} failed", passed, failed);
+            match failed {
+                0 => Ok(0),
+                _ => Err(Failure::TestsFailed(failed)),
+            }
+        },
+        Command::Regress { input, profile, cycle_limit, bless, host_calls } => {
+            // A profile given on the command line wins over the corpus's own
+            let profile = profile.or_else(|| {
+                regression::profile(Path::new(&input)).map(|path| path.to_string_lossy().to_string())
+            });
+            let profile = configure(load_profile(profile.as_deref())?, None, host_calls);
+            let cycle_limit = cycle_limit.unwrap_or(regression::DEFAULT_CYCLE_LIMIT);
+            let mut failed = 0;
+
+            for program in regression::programs(Path::new(&input)).map_err(Failure::Load)? {
+                let filename = program.to_string_lossy().to_string();
+                let recorded = load(&filename, &profile)
+                    .map_err(|failure| failure.to_string())
+                    .and_then(|container| {
+                        regression::record(&container, &profile, &regression::input(&program), cycle_limit)
+                    });
+
+                let verdict = match recorded {
+                    Ok(actual) => regression::compare(&program, &actual, bless).map_err(Failure::Load)?,
+                    Err(error) => Verdict::Failed(format!("    {}\n", error)),
+                };
+
+                match verdict {
+                    Verdict::Passed => println!("regress {} ... ok", filename),
+                    Verdict::Blessed => println!("regress {} ... blessed", filename),
+                    Verdict::Missing => {
+                        failed += 1;
+                        println!("regress {} ... FAILED\n    No .expected file; run with --bless to create it", filename);
+                    },
+                    Verdict::Failed(diff) => {
+                        failed += 1;
+                        print!("regress {} ... FAILED\n{}", filename, diff);
+                    },
+                }
+            }
+
+            match failed {
+                0 => Ok(0),
+                _ => Err(Failure::TestsFailed(failed)),
+            }
+        },
+        Command::Format { input, check } => {
+            // A directory stands for every program directly inside it
+            let files = match Path::new(&input).is_dir() {
+                true => regression::programs(Path::new(&input))
+                    .map_err(Failure::Load)?
+                    .iter()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect(),
+                false => vec![input],
+            };
+
+            let mut unformatted = Vec::new();
+            for file in files {
+                if !format_file(&file, check)? {
+                    unformatted.push(file);
+                }
+            }
+
+            match unformatted.is_empty() {
+                true => Ok(0),
+                false => Err(Failure::Unformatted(unformatted)),
+            }
+        },
+        Command::LanguageServer => {
+            lsp::serve(io::stdin().lock(), &mut io::stdout())
+                .map_err(|error| Failure::Load(format!("Language server I/O failed: {}", error)))
+        },
+        Command::DebugAdapter => {
+            // The adapter reads requests on a thread of its own, which needs stdin unlocked
+            dap::serve(BufReader::new(io::stdin()), &mut io::stdout())
+                .map(|()| 0)
+                .map_err(|error| Failure::Load(format!("Debug adapter I/O failed: {}", error)))
+        },
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(0)
+        },
+    }
+}
+
+fn load_profile(filename: Option<&str>) -> Result<MachineProfile, Failure> {
+    match filename {
+        Some(filename) => MachineProfile::from_file(filename).map_err(Failure::Load),
+        None => Ok(MachineProfile::default()),
+    }
+}
+
+// A seed from the command line wins over the profile's, and --host-calls turns host calls on
+// whatever the profile says
+fn configure(mut profile: MachineProfile, seed: Option<u64>, host_calls: bool) -> MachineProfile {
+    profile.seed = seed.or(profile.seed);
+    profile.host_calls |= host_calls;
+    profile
+}
+
+pub fn assemble(filename: &str, profile: &MachineProfile) -> Result<(Program, SymbolTable), Failure> {
+    let failure = |error| Failure::Assembly(filename.to_string(), error);
+    let source = Source::from_file(filename).map_err(failure)?;
+
+    let mut lexer = Lexer::new(&source.raw);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+    SyntaxChecker::check(parser.instructions.clone()).map_err(failure)?;
+
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode_for(&parser.instructions, &mut table, profile).map_err(failure)?;
+    Ok((program, table))
+}
+
+// Lint warnings go to stderr and never stop a build
+fn report_warnings(filename: &str, profile: &MachineProfile) -> Result<(), Failure> {
+    let source = Source::from_file(filename).map_err(|error| Failure::Assembly(filename.to_string(), error))?;
+
+    for warning in linter::lint(&source.raw, profile) {
+        eprintln!("{}:{}: warning: {}", filename, warning.line, warning);
+    }
+    Ok(())
+}
+
+// Whatever the input is, it ends up as a container: .ku files are assembled, containers are
+// read as they are, and bare images start at the top of ROM with no symbols.
+fn load(filename: &str, profile: &MachineProfile) -> Result<RomContainer, Failure> {
+    if Path::new(filename).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ku")) {
+        let (program, table) = assemble(filename, profile)?;
+        return Ok(RomContainer::from_program(&program, &table, &title(filename), &profile.name, profile.rom_base))
+    }
+
+    let data = fs::read(filename)
+        .map_err(|error| Failure::Load(format!("Failed to read file: {} {}", filename, error)))?;
+    if RomContainer::is_container(&data) {
+        return RomContainer::parse(&data)
+            .map_err(|error| Failure::Load(format!("Failed to load {}: {}", filename, error)))
+    }
+
+    let format = Format::from_extension(filename).unwrap_or_else(|| Format::detect(&data));
+    let binary = Binary::from_data(&data, format, profile.rom_base)
+        .map_err(|error| Failure::Load(format!("Failed to load {}: {}", filename, error)))?;
+
+    Ok(RomContainer::from_image(binary.bytes, &title(filename), profile))
+}
+
+fn write_output(
+program: &Program,
+table: &SymbolTable,
+input: &str,
+output: &str,
+format: OutputFormat,
+profile: &MachineProfile
+) -> Result<(), Failure> {
+    match format {
+        OutputFormat::Container => {
+            RomContainer::from_program(program, table, &title(input), &profile.name, profile.rom_base)
+                .save(output)
+                .map_err(Failure::Load)
+        },
+        OutputFormat::Image(format) => {
+            if !program.data.is_empty() {
+                return Err(Failure::Load(format!(
+                    "{} has {} RAM data segments, which are only kept in ROM containers: use --format container",
+                    input, program.data.len()
+                )))
+            }
+
+            let container = RomContainer::from_program(program, table, &title(input), &profile.name, profile.rom_base);
+            Binary::from_image(container.image(profile)).save(output, format, profile.rom_base).map_err(Failure::Load)
+        },
+    }
+}
+
+// Rewrites one file in the canonical style, or with `check` returns false if it would change
+fn format_file(filename: &str, check: bool) -> Result<bool, Failure> {
+    let failure = |error| Failure::Assembly(filename.to_string(), error);
+    let source = Source::from_file(filename).map_err(failure)?;
+    let formatted = formatter::format(&source.raw).map_err(failure)?;
+
+    if formatted == source.raw {
+        return Ok(true)
+    } else if check {
+        return Ok(false)
+    }
+
+    fs::write(filename, formatted)
+        .map_err(|error| Failure::Load(format!("Failed to write file: {} {}", filename, error)))?;
+    Ok(true)
+}
+
+fn title(filename: &str) -> String {
+    Path::new(filename)
+        .file_stem()
+        .map_or_else(|| filename.to_string(), |stem| stem.to_string_lossy().to_string())
+}
+
+fn labels(container: &RomContainer) -> HashMap<u16, String> {
+    container.symbols
+        .iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Label)
+        .map(|symbol| (symbol.address, symbol.name.clone()))
+        .collect()
+}
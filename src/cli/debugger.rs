@@ -0,0 +1,200 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufRead, Write};
+
+use crate::assembler::parser::Parser;
+use crate::chiiko::{Chiiko, SaveState};
+use crate::chiiko::components::chip::Chip;
+use crate::disassembler::Disassembler;
+
+const PROMPT: &str = "(chiiko) ";
+const MEMORY_ROW_WIDTH: u16 = 16;
+const DEFAULT_MEMORY_LENGTH: u16 = 64;
+
+const HELP: &str = "\
+step [n]            Run n instructions (default 1)
+continue            Run until a breakpoint or the program halts
+break <address>     Stop before the instruction at an address or label
+delete <address>    Remove a breakpoint
+registers           Show the registers and flags
+memory <address> [length]
+save                Remember the machine state
+restore             Return to the last saved state
+quit
+An empty line repeats the last command.
+";
+
+// An interactive front end for stepping a machine. Addresses can be given as numbers
+// or as label names from the program's symbols.
+pub struct Debugger {
+    chiiko: Chiiko,
+    disassembler: Disassembler,
+    symbols: HashMap<String, u16>,
+    breakpoints: BTreeSet<u16>,
+    saved: Option<SaveState>,
+}
+
+impl Debugger {
+    pub fn new(chiiko: Chiiko, labels: HashMap<u16, String>) -> Self {
+        let symbols = labels.iter().map(|(address, name)| (name.to_uppercase(), *address)).collect();
+
+        Self {
+            chiiko,
+            disassembler: Disassembler::with_labels(labels),
+            symbols,
+            breakpoints: BTreeSet::new(),
+            saved: None,
+        }
+    }
+
+    // Reads commands until `quit` or the end of input
+    pub fn repl(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        write!(output, "{}\n{}", self.location(), PROMPT)?;
+        output.flush()?;
+
+        let mut last = String::from("step");
+        for line in input.lines() {
+            let line = line?;
+            let line = if line.trim().is_empty() { last.clone() } else { line };
+
+            let Some(reply) = self.command(&line) else {
+                break
+            };
+            write!(output, "{}{}", reply, PROMPT)?;
+            output.flush()?;
+            last = line;
+        }
+
+        Ok(())
+    }
+
+    // Runs one command and returns what to show for it, or nothing when the session is over
+    pub fn command(&mut self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((name, arguments)) = words.split_first() else {
+            return Some(String::new())
+        };
+
+        let reply = match (*name, arguments) {
+            ("step" | "s", []) => self.step(1),
+            ("step" | "s", [count]) => match Parser::normalize_number(&count.to_uppercase()) {
+                Ok(count) => self.step(count),
+                Err(_) => format!("Invalid count: {}\n", count),
+            },
+            ("continue" | "c", []) => self.resume(),
+            ("break" | "b", [address]) => match self.address(address) {
+                Some(address) => {
+                    self.breakpoints.insert(address);
+                    format!("Breakpoint at {:#06X}\n", address)
+                },
+                None => format!("Unknown address: {}\n", address),
+            },
+            ("delete" | "d", [address]) => match self.address(address) {
+                Some(address) if self.breakpoints.remove(&address) => {
+                    format!("Removed breakpoint at {:#06X}\n", address)
+                },
+                Some(address) => format!("No breakpoint at {:#06X}\n", address),
+                None => format!("Unknown address: {}\n", address),
+            },
+            ("registers" | "r", []) => self.registers(),
+            ("memory" | "m", [address]) => self.memory(address, DEFAULT_MEMORY_LENGTH),
+            ("memory" | "m", [address, length]) => match Parser::normalize_number(&length.to_uppercase()) {
+                Ok(length) => self.memory(address, length.min(u16::MAX as usize) as u16),
+                Err(_) => format!("Invalid length: {}\n", length),
+            },
+            ("save", []) => {
+                self.saved = Some(self.chiiko.save_state());
+                format!("Saved at {:#06X}\n", self.chiiko.cpu.program_counter)
+            },
+            ("restore", []) => match &self.saved {
+                Some(state) => match self.chiiko.load_state(state) {
+                    Ok(()) => format!("{}\n", self.location()),
+                    Err(error) => format!("Restore failed: {}\n", error),
+                },
+                None => "Nothing saved\n".to_string(),
+            },
+            ("help" | "h", []) => HELP.to_string(),
+            ("quit" | "q", []) => return None,
+            _ => format!("Unknown command: {}\n", line.trim()),
+        };
+
+        Some(reply)
+    }
+
+    fn step(&mut self, count: usize) -> String {
+        for _ in 0..count {
+            if self.chiiko.is_halted() {
+                break
+            }
+
+            if let Err(fault) = self.chiiko.step() {
+                return format!("Fault: {}\n", fault)
+            }
+        }
+
+        format!("{}\n", self.location())
+    }
+
+    fn resume(&mut self) -> String {
+        while !self.chiiko.is_halted() {
+            if let Err(fault) = self.chiiko.step() {
+                return format!("Fault: {}\n", fault)
+            }
+
+            if self.breakpoints.contains(&self.chiiko.cpu.program_counter) {
+                return format!("Breakpoint\n{}\n", self.location())
+            }
+        }
+
+        format!("{}\n", self.location())
+    }
+
+    fn registers(&self) -> String {
+        let cpu = &self.chiiko.cpu;
+        let flags: String = [(0x01, 'Z'), (0x02, 'N'), (0x04, 'C'), (0x08, 'V'), (0x80, 'I')]
+            .iter()
+            .map(|(bit, name)| if cpu.status & bit != 0 { *name } else { '-' })
+            .collect();
+
+        format!(
+            "A={:02X} B={:02X} C={:02X} H={:02X} L={:02X} I={:02X} J={:02X}\n\
+            PC={:04X} SP={:04X} FP={:04X} F={} cycles={}\n",
+            cpu.accumulator, cpu.b_register, cpu.c_register, cpu.h_register, cpu.l_register,
+            cpu.i_register, cpu.j_register, cpu.program_counter, cpu.stack_pointer(),
+            cpu.frame_pointer(), flags, cpu.cycle_count,
+        )
+    }
+
+    fn memory(&self, address: &str, length: u16) -> String {
+        let Some(start) = self.address(address) else {
+            return format!("Unknown address: {}\n", address)
+        };
+        let mut text = String::new();
+
+        for row in (0..length).step_by(MEMORY_ROW_WIDTH as usize) {
+            let row_start = start.wrapping_add(row);
+            let bytes: Vec<String> = (0..MEMORY_ROW_WIDTH.min(length - row))
+                .map(|offset| format!("{:02X}", self.chiiko.cpu.read(row_start.wrapping_add(offset))))
+                .collect();
+            text.push_str(&format!("{:04X}  {}\n", row_start, bytes.join(" ")));
+        }
+
+        text
+    }
+
+    // The next instruction, or how the program stopped
+    fn location(&self) -> String {
+        match (self.chiiko.is_halted(), self.chiiko.cpu.exit_code) {
+            (true, Some(code)) => format!("Exited with code {}", code),
+            (true, None) => "Halted".to_string(),
+            _ => self.chiiko.trace(&self.disassembler).to_string(),
+        }
+    }
+
+    fn address(&self, text: &str) -> Option<u16> {
+        let text = text.trim_start_matches(':').to_uppercase();
+
+        self.symbols.get(&text).copied().or_else(|| {
+            Parser::normalize_number(&text).ok().and_then(|address| u16::try_from(address).ok())
+        })
+    }
+}
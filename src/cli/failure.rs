@@ -0,0 +1,51 @@
+use std::fmt;
+
+use crate::assembler::assembly_error::AssemblyError;
+
+const UNFORMATTED_EXIT_CODE: u8 = 1;
+const TESTS_FAILED_EXIT_CODE: u8 = 1;
+const USAGE_EXIT_CODE: u8 = 2;
+const ASSEMBLY_EXIT_CODE: u8 = 3;
+const LOAD_EXIT_CODE: u8 = 4;
+const FAULT_EXIT_CODE: u8 = 5;
+
+// Why a command failed. Each kind has its own exit code, so scripts can tell a bad
+// program apart from a machine that stopped on a fault.
+#[derive(Debug)]
+pub enum Failure {
+    Usage(String),
+    Assembly(String, AssemblyError), // The source file and what was wrong with it
+    Load(String),                    // Images, containers, profiles and output files
+    Fault(String),
+    Unformatted(Vec<String>), // Files `fmt --check` found would change
+    TestsFailed(usize),  // How many `test` cases failed
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Usage(_) => USAGE_EXIT_CODE,
+            Failure::Assembly(..) => ASSEMBLY_EXIT_CODE,
+            Failure::Load(_) => LOAD_EXIT_CODE,
+            Failure::Fault(_) => FAULT_EXIT_CODE,
+            Failure::Unformatted(_) => UNFORMATTED_EXIT_CODE,
+            Failure::TestsFailed(_) => TESTS_FAILED_EXIT_CODE,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Usage(message) => write!(f, "{}\nRun `chiiko help` for usage", message),
+            Failure::Assembly(filename, error) => write!(f, "{}: {}", filename, error),
+            Failure::Load(message) => write!(f, "{}", message),
+            Failure::Fault(message) => write!(f, "Machine fault: {}", message),
+            Failure::Unformatted(filenames) => {
+                let lines: Vec<String> = filenames.iter().map(|filename| format!("{} is not formatted", filename)).collect();
+                write!(f, "{}", lines.join("\n"))
+            },
+            Failure::TestsFailed(count) => write!(f, "{} test(s) failed", count),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+mod core;
+pub mod command;
+pub mod debugger;
+pub mod failure;
+
+#[cfg(test)]
+mod test;
+
+pub use core::run;
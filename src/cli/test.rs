@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::binary::Format;
+use crate::chiiko::{Chiiko, MachineProfile};
+use crate::cli::{command::Command, command::OutputFormat, debugger::Debugger, run};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(|arg| arg.to_string()).collect()
+}
+
+fn source_file(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    fs::write(&path, source).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn parses_subcommands_and_options() {
+    assert_eq!(
+        Command::parse(&args("asm -o out.hex --format ihex game.ku")),
+        Ok(Command::Assemble {
+            input: "game.ku".to_string(),
+            output: Some("out.hex".to_string()),
+            format: Some(OutputFormat::Image(Format::IntelHex)),
+            profile: None,
+        })
+    );
+    assert_eq!(
+        Command::parse(&args("run --cycles 0x100 --trace --seed 7 --host-calls game.rom")),
+        Ok(Command::Run {
+            input: "game.rom".to_string(),
+            profile: None,
+            cycle_limit: Some(256),
+            trace: true,
+            seed: Some(7),
+            host_calls: true,
+        })
+    );
+    assert_eq!(
+        Command::parse(&args("fmt --check game.ku")),
+        Ok(Command::Format { input: "game.ku".to_string(), check: true })
+    );
+    assert_eq!(Command::parse(&args("lsp")), Ok(Command::LanguageServer));
+    assert_eq!(Command::parse(&args("dap")), Ok(Command::DebugAdapter));
+    assert_eq!(Command::parse(&args("")), Ok(Command::Help));
+}
+
+#[test]
+fn rejects_unknown_commands_and_misplaced_options() {
+    assert!(Command::parse(&args("build game.ku")).is_err());
+    assert!(Command::parse(&args("check --trace game.ku")).is_err());
+    assert!(Command::parse(&args("run --check game.ku")).is_err());
+    assert!(Command::parse(&args("asm --format pdf game.ku")).is_err());
+    assert!(Command::parse(&args("run")).is_err());
+    assert!(Command::parse(&args("run one.ku two.ku")).is_err());
+}
+
+#[test]
+fn exit_codes_report_program_and_failures() {
+    let exits = source_file("chiiko_cli_exit.ku", "LOAD 7 A\nTRAP 0xF2\n");
+    let halts = source_file("chiiko_cli_halt.ku", "INC B\nHALT\n");
+    let spins = source_file("chiiko_cli_spin.ku", "LOOP:\n  JUMP :LOOP\n");
+    let broken = source_file("chiiko_cli_broken.ku", "JUMP :NOWHERE\n");
+    let unknown = source_file("chiiko_cli_unknown.ku", "FROB A\n");
+
+    assert_eq!(run(&args(&format!("run --host-calls {}", exits))), 7);
+    assert_eq!(run(&args(&format!("run {}", exits))), 5);
+    assert_eq!(run(&args(&format!("run {}", halts))), 0);
+    assert_eq!(run(&args(&format!("check {}", halts))), 0);
+    assert_eq!(run(&args(&format!("check {}", broken))), 3);
+    assert_eq!(run(&args(&format!("check {}", unknown))), 3);
+    assert_eq!(run(&args(&format!("run --cycles 50 {}", spins))), 5);
+    assert_eq!(run(&args("run chiiko_cli_missing.rom")), 4);
+    assert_eq!(run(&args("frobnicate")), 2);
+}
+
+#[test]
+fn fmt_rewrites_files_and_check_reports_them() {
+    let source = source_file("chiiko_cli_fmt.ku", "start:\n inc b ; twice\n  halt\n");
+
+    assert_eq!(run(&args(&format!("fmt --check {}", source))), 1);
+    assert_eq!(run(&args(&format!("fmt {}", source))), 0);
+    assert_eq!(fs::read_to_string(&source).unwrap(), "START:\n    INC  B ; twice\n    HALT\n");
+    assert_eq!(run(&args(&format!("fmt --check {}", source))), 0);
+}
+
+#[test]
+fn fmt_accepts_a_directory_of_programs() {
+    let directory = std::env::temp_dir().join("chiiko_cli_fmt_directory");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("one.ku"), "halt\n").unwrap();
+    fs::write(directory.join("two.ku"), "    HALT\n").unwrap();
+
+    assert_eq!(run(&args(&format!("fmt --check {}", directory.display()))), 1);
+    assert_eq!(run(&args(&format!("fmt {}", directory.display()))), 0);
+    assert_eq!(fs::read_to_string(directory.join("one.ku")).unwrap(), "    HALT\n");
+    assert_eq!(run(&args(&format!("fmt --check {}", directory.display()))), 0);
+}
+
+#[test]
+fn cfg_writes_a_dot_file_per_routine() {
+    let source = source_file("chiiko_cli_cfg.ku", "START:\n    CALL :TWICE\n    HALT\nTWICE:\n    INC B\n    RTRN\n");
+    let output = std::env::temp_dir().join("chiiko_cli_cfg");
+
+    assert_eq!(run(&args(&format!("cfg -o {} {}", output.display(), source))), 0);
+    assert!(fs::read_to_string(output.join("START.dot")).unwrap().starts_with("digraph \"START\""));
+    assert!(fs::read_to_string(output.join("TWICE.dot")).unwrap().contains("RTRN"));
+}
+
+#[test]
+fn test_runs_every_suite_in_a_directory() {
+    let directory = std::env::temp_dir().join("chiiko_cli_test");
+    fs::create_dir_all(directory.join("nested")).unwrap();
+    fs::write(directory.join("double.ku"), "DOUBLE:\n    ADD A\n    RTRN\n").unwrap();
+    fs::write(directory.join("pass.kutest"), "program = double.ku\n[twice]\ncall = DOUBLE\nA = 3\nexpect A = 6\n").unwrap();
+    fs::write(directory.join("nested/fail.kutest"), "program = ../double.ku\n[wrong]\ncall = DOUBLE\nexpect A = 1\n").unwrap();
+
+    assert_eq!(run(&args(&format!("test {}", directory.join("pass.kutest").display()))), 0);
+    assert_eq!(run(&args(&format!("test {}", directory.display()))), 1);
+}
+
+#[test]
+fn test_can_enable_host_calls() {
+    let directory = std::env::temp_dir().join("chiiko_cli_test_host_calls");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("show.ku"), "SHOW:\n    TRAP 0xF0\n    RTRN\n").unwrap();
+    fs::write(directory.join("show.kutest"), "program = show.ku\n[prints]\ncall = SHOW\nHL = 42\nexpect output = \"42\\n\"\n").unwrap();
+
+    assert_eq!(run(&args(&format!("test {}", directory.display()))), 1);
+    assert_eq!(run(&args(&format!("test --host-calls {}", directory.display()))), 0);
+}
+
+#[test]
+fn example_programs_match_their_expected_output() {
+    let corpus = concat!(env!("CARGO_MANIFEST_DIR"), "/test_binaries");
+
+    assert_eq!(run(&args(&format!("regress {}", corpus))), 0);
+    assert_eq!(run(&args(&format!("fmt --check {}", corpus))), 0);
+}
+
+#[test]
+fn assembled_container_runs_with_data() {
+    let source = source_file("chiiko_cli_data.ku", "ARRAY $0x10 [9]\nLOAD $0x10 A\nTRAP 0xF2\n");
+    let output = std::env::temp_dir().join("chiiko_cli_data.rom");
+    let output = output.to_str().unwrap();
+
+    assert_eq!(run(&args(&format!("asm -f container -o {} {}", output, source))), 0);
+    assert_eq!(run(&args(&format!("run --host-calls {}", output))), 9);
+}
+
+#[test]
+fn default_output_keeps_data_and_images_refuse_it() {
+    let source = source_file("chiiko_cli_default.ku", "ARRAY $0x10 [9]\nLOAD $0x10 A\nTRAP 0xF2\n");
+    let output = std::env::temp_dir().join("chiiko_cli_default.rom");
+    let _ = fs::remove_file(&output);
+
+    assert_eq!(run(&args(&format!("asm {}", source))), 0);
+    assert_eq!(run(&args(&format!("run --host-calls {}", output.display()))), 9);
+
+    let image = std::env::temp_dir().join("chiiko_cli_default.bin");
+    assert_eq!(run(&args(&format!("asm -o {} {}", image.display(), source))), 4);
+}
+
+#[test]
+fn bare_images_start_at_their_reset_vector() {
+    let source = source_file("chiiko_cli_vector.ku", "SUB:\n  RTRN\nSTART:\n  LOAD 7 A\n  TRAP 0xF2\n");
+    let image = std::env::temp_dir().join("chiiko_cli_vector.bin");
+    let image = image.to_str().unwrap();
+
+    assert_eq!(run(&args(&format!("asm -f raw -o {} {}", image, source))), 0);
+    assert_eq!(run(&args(&format!("run --host-calls {}", image))), 7);
+}
+
+#[test]
+fn debugger_stops_at_breakpoints() {
+    let mut rom = vec![0; 0x8000];
+    // INC B, INC B, HALT
+    rom[0..7].copy_from_slice(&[0x85, 0x2A, 0x01, 0x85, 0x2A, 0x01, 0x70]);
+    let chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+    let mut debugger = Debugger::new(chiiko, HashMap::from([(0x8003, "AGAIN".to_string())]));
+
+    assert_eq!(debugger.command("break AGAIN"), Some("Breakpoint at 0x8003\n".to_string()));
+    assert!(debugger.command("continue").unwrap().starts_with("Breakpoint\n8003"));
+    assert!(debugger.command("registers").unwrap().starts_with("A=00 B=01"));
+
+    assert_eq!(debugger.command("step 5"), Some("Halted\n".to_string()));
+    assert!(debugger.command("registers").unwrap().starts_with("A=00 B=02"));
+    assert_eq!(debugger.command("quit"), None);
+}
+
+#[test]
+fn debugger_shows_registers_and_memory() {
+    let mut rom = vec![0; 0x8000];
+    rom[0] = 0x70;
+    let chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+    let mut debugger = Debugger::new(chiiko, HashMap::new());
+
+    assert!(debugger.command("registers").unwrap().contains("PC=8000 SP=1FFF"));
+    assert_eq!(debugger.command("memory 0x8000 2"), Some("8000  70 00\n".to_string()));
+    assert_eq!(debugger.command("memory NOWHERE"), Some("Unknown address: NOWHERE\n".to_string()));
+}
+
+#[test]
+fn debugger_restores_saved_state() {
+    let mut rom = vec![0; 0x8000];
+    // INC B, HALT
+    rom[0..4].copy_from_slice(&[0x85, 0x2A, 0x01, 0x70]);
+    let chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+    let mut debugger = Debugger::new(chiiko, HashMap::new());
+
+    assert_eq!(debugger.command("restore"), Some("Nothing saved\n".to_string()));
+    assert_eq!(debugger.command("save"), Some("Saved at 0x8000\n".to_string()));
+    assert_eq!(debugger.command("step 2"), Some("Halted\n".to_string()));
+    assert!(debugger.command("restore").unwrap().starts_with("8000"));
+    assert!(debugger.command("registers").unwrap().starts_with("A=00 B=00"));
+}
@@ -0,0 +1,109 @@
+use crate::chiiko::components::{cpu_operand::CpuOperand, instruction::Instruction};
+use crate::operation::Operation;
+
+pub const MAXIMUM_LENGTH: usize = 8; // Opcode, mode byte and two indexed addresses
+const EXPLICIT_MODE: u8 = 0b1000_0000;
+
+// The one definition of how instructions are laid out in memory:
+//
+//   opcode | mode byte (only when bit 7 of the opcode is set) | left operand | right operand
+//
+// The mode byte is left out whenever the instruction uses its operation's default mode, and
+// each operand takes 0-3 bytes depending on its mode nibble. Addresses are big endian.
+pub fn encode(instruction: &Instruction) -> Vec<u8> {
+    let operation = &instruction.operation;
+    let mut bytes = Vec::with_capacity(MAXIMUM_LENGTH);
+
+    if instruction.mode == operation.default_mode {
+        bytes.push(operation.opcode & !EXPLICIT_MODE);
+    } else {
+        bytes.push(operation.opcode | EXPLICIT_MODE);
+        bytes.push(instruction.mode);
+    }
+
+    encode_operand(&mut bytes, instruction.mode >> 4, &instruction.left_operand);
+    encode_operand(&mut bytes, instruction.mode & 0x0F, &instruction.right_operand);
+    bytes
+}
+
+fn encode_operand(bytes: &mut Vec<u8>, nibble: u8, operand: &CpuOperand) {
+    // Implicit operands such as the accumulator are carried by the mode alone
+    if implicit_operand(nibble) != CpuOperand::Error || nibble == 0xF {
+        return;
+    }
+
+    match *operand {
+        CpuOperand::Value(byte) | CpuOperand::Register(byte) | CpuOperand::IndirectRegister(byte) |
+        CpuOperand::ZeroPageAddress(byte) | CpuOperand::IndirectZeroPageAddress(byte) |
+        CpuOperand::StackRelative(byte) => bytes.push(byte),
+        CpuOperand::MemoryAddress(word) | CpuOperand::IndirectMemoryAddress(word) |
+        CpuOperand::JumpAddress(word) => bytes.extend(word.to_be_bytes()),
+        CpuOperand::IndexedAddress(word, register) => {
+            bytes.extend(word.to_be_bytes());
+            bytes.push(register);
+        },
+        CpuOperand::IndexedZeroPage(byte, register) => bytes.extend([byte, register]),
+        CpuOperand::None | CpuOperand::Error => (),
+    }
+}
+
+// Returns the instruction at the start of `bytes` and how many bytes it took. The decoded
+// operation always carries its table opcode; whether a mode byte was present is implied
+// by `mode`, so `encode(decode(bytes))` reproduces any bytes the assembler produces.
+pub fn decode(bytes: &[u8]) -> Result<(Instruction, usize), &'static str> {
+    let mut length = 0;
+    let mut next = || {
+        let byte = bytes.get(length).copied().ok_or("Instruction is truncated");
+        length += 1;
+        byte
+    };
+
+    let opcode = next()?;
+    let mut operation = Operation::lookup_byte(opcode).ok_or("Illegal opcode")?;
+    operation.opcode &= !EXPLICIT_MODE;
+
+    let mode = if opcode & EXPLICIT_MODE != 0 { next()? } else { operation.default_mode };
+    let left = decode_operand(mode >> 4, &mut next)?;
+    let right = decode_operand(mode & 0x0F, &mut next)?;
+
+    Ok((Instruction::new(operation, mode, left, right), length))
+}
+
+fn decode_operand(
+nibble: u8,
+next: &mut impl FnMut() -> Result<u8, &'static str>
+) -> Result<CpuOperand, &'static str> {
+    let mut word = || -> Result<u16, &'static str> { Ok(u16::from_be_bytes([next()?, next()?])) };
+
+    Ok(match nibble {
+        0x1 => CpuOperand::Value(next()?),
+        0x2 => CpuOperand::Register(next()?),
+        0x3 => CpuOperand::IndirectRegister(next()?),
+        0x4 => CpuOperand::ZeroPageAddress(next()?),
+        0x5 => CpuOperand::IndirectZeroPageAddress(next()?),
+        0x6 => CpuOperand::MemoryAddress(word()?),
+        0x7 => CpuOperand::IndirectMemoryAddress(word()?),
+        0x8 => CpuOperand::JumpAddress(word()?),
+        0xC => {
+            let base = word()?;
+            CpuOperand::IndexedAddress(base, next()?)
+        },
+        0xD => {
+            let base = next()?;
+            CpuOperand::IndexedZeroPage(base, next()?)
+        },
+        0xE => CpuOperand::StackRelative(next()?),
+        _ => implicit_operand(nibble),
+    })
+}
+
+// Operands that are implied by the mode nibble and take no bytes
+pub fn implicit_operand(nibble: u8) -> CpuOperand {
+    match nibble {
+        0x0 => CpuOperand::None,
+        0x9 => CpuOperand::Register(0), // Accumulator
+        0xA => CpuOperand::Value(1),
+        0xB => CpuOperand::Value(255),
+        _ => CpuOperand::Error,
+    }
+}
@@ -0,0 +1,6 @@
+mod core;
+
+#[cfg(test)]
+mod test;
+
+pub use core::{encode, decode, implicit_operand, MAXIMUM_LENGTH};
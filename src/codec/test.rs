@@ -0,0 +1,117 @@
+use crate::chiiko::components::{cpu_operand::CpuOperand, instruction::Instruction};
+use crate::codec::{decode, encode, implicit_operand, MAXIMUM_LENGTH};
+use crate::operation::Operation;
+
+fn operations() -> Vec<Operation> {
+    (0..0x80).filter_map(Operation::lookup_byte).collect()
+}
+
+// An operand of the right shape for a mode nibble, with bytes that differ from side to side
+fn sample_operand(nibble: u8, seed: u8) -> CpuOperand {
+    let word = u16::from_be_bytes([seed, seed.wrapping_add(1)]);
+
+    match nibble {
+        0x1 => CpuOperand::Value(seed),
+        0x2 => CpuOperand::Register(seed % 7),
+        0x3 => CpuOperand::IndirectRegister(seed % 7),
+        0x4 => CpuOperand::ZeroPageAddress(seed),
+        0x5 => CpuOperand::IndirectZeroPageAddress(seed),
+        0x6 => CpuOperand::MemoryAddress(word),
+        0x7 => CpuOperand::IndirectMemoryAddress(word),
+        0x8 => CpuOperand::JumpAddress(word),
+        0xC => CpuOperand::IndexedAddress(word, 5),
+        0xD => CpuOperand::IndexedZeroPage(seed, 6),
+        0xE => CpuOperand::StackRelative(seed),
+        _ => implicit_operand(nibble),
+    }
+}
+
+fn operand_size(nibble: u8) -> usize {
+    match nibble {
+        0x1..=0x5 | 0xE => 1,
+        0x6..=0x8 | 0xD => 2,
+        0xC => 3,
+        _ => 0,
+    }
+}
+
+#[test]
+fn every_opcode_and_mode_round_trips() {
+    for operation in operations() {
+        for mode in 0..=0xFF_u8 {
+            let instruction = Instruction::new(
+                operation,
+                mode,
+                sample_operand(mode >> 4, 0x12),
+                sample_operand(mode & 0x0F, 0x9A),
+            );
+
+            let bytes = encode(&instruction);
+            let header = if mode == operation.default_mode { 1 } else { 2 };
+            assert_eq!(bytes.len(), header + operand_size(mode >> 4) + operand_size(mode & 0x0F));
+            assert!(bytes.len() <= MAXIMUM_LENGTH);
+
+            assert_eq!(decode(&bytes), Ok((instruction, bytes.len())), "{:02X?}", bytes);
+        }
+    }
+}
+
+#[test]
+fn every_encoding_round_trips() {
+    for operation in operations() {
+        for mode in 0..=0xFF_u8 {
+            let mut bytes = vec![operation.opcode | 0x80, mode];
+            bytes.extend((0..MAXIMUM_LENGTH as u8).map(|offset| offset.wrapping_mul(37)));
+
+            let (instruction, length) = decode(&bytes).unwrap();
+            if mode != operation.default_mode {
+                assert_eq!(encode(&instruction), bytes[..length]);
+            }
+        }
+    }
+}
+
+#[test]
+fn default_mode_omits_mode_byte() {
+    let operation = Operation::from_mnemonic("ADD");
+    let instruction = Instruction::new(
+        operation,
+        operation.default_mode,
+        CpuOperand::Register(1),
+        CpuOperand::Register(0), // Accumulator, implied by the default mode
+    );
+
+    assert_eq!(encode(&instruction), [0x00, 0x01]);
+    assert_eq!(decode(&[0x00, 0x01, 0xFF]), Ok((instruction, 2)));
+}
+
+#[test]
+fn explicit_mode_adds_mode_byte() {
+    let instruction = Instruction::new(
+        Operation::from_mnemonic("ADD"),
+        0x16,
+        CpuOperand::Value(0x05),
+        CpuOperand::MemoryAddress(0x1234),
+    );
+
+    assert_eq!(encode(&instruction), [0x80, 0x16, 0x05, 0x12, 0x34]);
+}
+
+#[test]
+fn decoded_operation_excludes_mode_bit() {
+    let (instruction, _) = decode(&[0x80, 0x12, 0x05, 0x01]).unwrap();
+    assert_eq!(instruction.operation.opcode, 0x00);
+}
+
+#[test]
+fn truncated_instruction_is_an_error() {
+    assert_eq!(decode(&[]), Err("Instruction is truncated"));
+    assert_eq!(decode(&[0x80]), Err("Instruction is truncated"));
+    assert_eq!(decode(&[0x80, 0x66, 0x12, 0x34, 0x56]), Err("Instruction is truncated"));
+}
+
+#[test]
+fn illegal_opcode_is_an_error() {
+    let illegal = (0..0x80).find(|byte| Operation::lookup_byte(*byte).is_none()).unwrap();
+    assert_eq!(decode(&[illegal, 0, 0]), Err("Illegal opcode"));
+}
@@ -0,0 +1,40 @@
+use crate::disassembler::Line;
+
+// How control leaves a basic block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exit {
+    Next,      // Falls into the block that follows
+    Jump,
+    Branch,    // Either the target or the next instruction
+    Call(u16), // Continues after the routine at the address returns
+    Return,
+    Halt,
+    Indirect,  // Jumps through a register pair, so the target is unknown
+    Invalid,   // Runs into bytes that do not decode, or off the end of the image
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    Next,
+    Jump,
+    Taken,
+    NotTaken,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+    pub start: u16,
+    pub end: u16, // The address after the last instruction
+    pub lines: Vec<Line>,
+    pub successors: Vec<(u16, Edge)>,
+    pub exit: Exit,
+}
+
+// The blocks reachable from an entry point without following calls
+#[derive(Clone, Debug, PartialEq)]
+pub struct Routine {
+    pub name: String,
+    pub entry: u16,
+    pub blocks: Vec<u16>,
+    pub problems: Vec<String>, // Ways the routine can end without HALT, RTRN or a jump
+}
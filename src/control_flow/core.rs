@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::chiiko::components::{cpu_operand::CpuOperand, host_call::HostCall};
+use crate::codec::{self, MAXIMUM_LENGTH};
+use crate::control_flow::block::{BasicBlock, Edge, Exit, Routine};
+use crate::disassembler::{Disassembler, Line};
+use crate::operation::group::{Group, SubroutineVariant, SystemVariant};
+
+// One decoded instruction and where it can go next
+struct Step {
+    line: Line,
+    next: u16,
+    successors: Vec<(u16, Edge)>,
+    exit: Exit,
+}
+
+// Splits the code reachable from the entry point into basic blocks, ending a block at every
+// jump, call and branch, and groups the blocks into routines: the entry's, and one for each
+// CALL target. With host calls enabled, the exit host call ends a path like HALT.
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub routines: Vec<Routine>,
+    labels: HashMap<u16, String>,
+}
+
+impl ControlFlowGraph {
+    pub fn build(image: &[u8], origin: u16, entry: u16, labels: HashMap<u16, String>, host_calls: bool) -> Self {
+        let disassembler = Disassembler::with_labels(labels.clone());
+        let in_image = |address: u16| (address.wrapping_sub(origin) as usize) < image.len();
+        let read = |address: u16| image.get(address.wrapping_sub(origin) as usize).copied().unwrap_or(0);
+
+        // Follow every path from the entry point, one instruction at a time
+        let mut steps: BTreeMap<u16, Step> = BTreeMap::new();
+        let mut routine_entries: BTreeSet<u16> = BTreeSet::from([entry]);
+        let mut pending = vec![entry];
+
+        while let Some(address) = pending.pop() {
+            if steps.contains_key(&address) || !in_image(address) {
+                continue
+            }
+
+            let step = Self::step(&disassembler, &read, address, &in_image, host_calls);
+            pending.extend(step.successors.iter().map(|(target, _)| *target));
+            if let Exit::Call(target) = step.exit {
+                routine_entries.insert(target);
+                pending.push(target);
+            }
+
+            steps.insert(address, step);
+        }
+
+        // Blocks start at entries, at targets and after anything that transfers control
+        let mut leaders = routine_entries.clone();
+        for step in steps.values() {
+            if step.exit != Exit::Next {
+                leaders.insert(step.next);
+            }
+            leaders.extend(step.successors.iter().filter(|(_, edge)| *edge != Edge::Next).map(|(target, _)| *target));
+        }
+
+        let mut blocks: BTreeMap<u16, BasicBlock> = BTreeMap::new();
+        let mut current: Option<BasicBlock> = None;
+
+        for (address, step) in steps {
+            let continues = current
+                .as_ref()
+                .is_some_and(|block| block.exit == Exit::Next && block.end == address && !leaders.contains(&address));
+
+            if !continues {
+                if let Some(block) = current.take() {
+                    blocks.insert(block.start, block);
+                }
+                current = Some(BasicBlock {
+                    start: address,
+                    end: address,
+                    lines: Vec::new(),
+                    successors: Vec::new(),
+                    exit: Exit::Next,
+                });
+            }
+
+            if let Some(block) = current.as_mut() {
+                block.lines.push(step.line);
+                block.end = step.next;
+                block.successors = step.successors;
+                block.exit = step.exit;
+            }
+        }
+
+        if let Some(block) = current {
+            blocks.insert(block.start, block);
+        }
+
+        let mut graph = Self { blocks, routines: Vec::new(), labels };
+        let mut entries: Vec<u16> = routine_entries.into_iter().filter(|start| *start != entry).collect();
+        entries.insert(0, entry);
+
+        graph.routines = entries
+            .iter()
+            .filter(|start| graph.blocks.contains_key(start))
+            .map(|start| graph.routine(*start, *start == entry, &entries))
+            .collect();
+        graph
+    }
+
+    fn step(
+    disassembler: &Disassembler,
+    read: &dyn Fn(u16) -> u8,
+    address: u16,
+    in_image: &dyn Fn(u16) -> bool,
+    host_calls: bool
+    ) -> Step {
+        let line = disassembler.decode(read, address);
+        let next = address.wrapping_add(line.bytes.len() as u16);
+        let window: Vec<u8> = (0..MAXIMUM_LENGTH as u16).map(|offset| read(address.wrapping_add(offset))).collect();
+
+        let Ok((instruction, _)) = codec::decode(&window) else {
+            return Step { line, next, successors: Vec::new(), exit: Exit::Invalid }
+        };
+
+        let operation = &instruction.operation;
+        let target = match instruction.left_operand {
+            CpuOperand::JumpAddress(address) | CpuOperand::MemoryAddress(address) => Some(address),
+            _ => None,
+        };
+
+        let (successors, exit) = match operation.group {
+            Group::Subroutine(SubroutineVariant::Return) | Group::System(SystemVariant::TrapReturn) => {
+                (Vec::new(), Exit::Return)
+            },
+            Group::System(SystemVariant::Halt) => (Vec::new(), Exit::Halt),
+            Group::System(SystemVariant::Trap) if host_calls && Self::is_exit_call(&instruction.left_operand) => {
+                (Vec::new(), Exit::Halt)
+            },
+            Group::Subroutine(SubroutineVariant::Jump) => match target {
+                Some(target) => (vec![(target, Edge::Jump)], Exit::Jump),
+                None => (Vec::new(), Exit::Indirect),
+            },
+            Group::Subroutine(SubroutineVariant::Call) => match target {
+                Some(target) => (vec![(next, Edge::Next)], Exit::Call(target)),
+                None => (vec![(next, Edge::Next)], Exit::Indirect),
+            },
+            Group::Subroutine(_) => match target {
+                Some(target) => (vec![(target, Edge::Taken), (next, Edge::NotTaken)], Exit::Branch),
+                None => (vec![(next, Edge::NotTaken)], Exit::Indirect),
+            },
+            _ if operation.is_relative_branch() => match instruction.left_operand {
+                CpuOperand::Value(displacement) => {
+                    let target = next.wrapping_add(displacement as i8 as u16);
+                    (vec![(target, Edge::Taken), (next, Edge::NotTaken)], Exit::Branch)
+                },
+                _ => (vec![(next, Edge::NotTaken)], Exit::Indirect),
+            },
+            _ => (vec![(next, Edge::Next)], Exit::Next),
+        };
+
+        // Running off the end of the image is as bad as running into data
+        if exit == Exit::Next && !in_image(next) {
+            return Step { line, next, successors: Vec::new(), exit: Exit::Invalid }
+        }
+
+        Step { line, next, successors, exit }
+    }
+
+    fn is_exit_call(operand: &CpuOperand) -> bool {
+        matches!(operand, CpuOperand::Value(number) if HostCall::from_number(*number) == Some(HostCall::Exit))
+    }
+
+    fn routine(&self, entry: u16, is_main: bool, entries: &[u16]) -> Routine {
+        let name = self.name(entry, is_main);
+        let mut visited: BTreeSet<u16> = BTreeSet::new();
+        let mut problems = Vec::new();
+        let mut pending = vec![entry];
+
+        while let Some(start) = pending.pop() {
+            let Some(block) = self.blocks.get(&start).filter(|_| visited.insert(start)) else {
+                continue
+            };
+
+            if block.exit == Exit::Invalid {
+                problems.push(format!("{} can run past the end of its code at {:#06X}", name, block.end));
+            }
+
+            for (target, edge) in &block.successors {
+                if *target == entry || !entries.contains(target) {
+                    pending.push(*target);
+                } else if *edge != Edge::Jump {
+                    let other = self.name(*target, false);
+                    problems.push(match is_main {
+                        true => format!("{} can fall through into {}", name, other),
+                        false => format!("{} can fall through into {} without RTRN", name, other),
+                    });
+                }
+            }
+        }
+
+        Routine { name, entry, blocks: visited.into_iter().collect(), problems }
+    }
+
+    fn name(&self, address: u16, is_main: bool) -> String {
+        match self.labels.get(&address) {
+            Some(label) => label.to_string(),
+            None if is_main => "MAIN".to_string(),
+            None => format!("SUB_{:04X}", address),
+        }
+    }
+
+    // A Graphviz digraph of one routine. Calls and jumps into other routines point at a
+    // node named after that routine.
+    pub fn to_dot(&self, routine: &Routine) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", escape(&routine.name));
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        let mut others: BTreeSet<String> = BTreeSet::new();
+
+        for start in &routine.blocks {
+            let block = &self.blocks[start];
+
+            let mut label = String::new();
+            if let Some(name) = self.labels.get(start) {
+                label.push_str(&format!("{}:\\l", escape(name)));
+            }
+            for line in &block.lines {
+                label.push_str(&format!("{:04X}  {}\\l", line.address, escape(&line.text)));
+            }
+            dot.push_str(&format!("    b{:04X} [label=\"{}\"];\n", start, label));
+
+            if let Exit::Call(target) = block.exit {
+                let callee = self.name(target, false);
+                dot.push_str(&format!("    b{:04X} -> \"{}\" [style=dashed, label=\"call\"];\n", start, escape(&callee)));
+                others.insert(callee);
+            }
+
+            for (target, edge) in &block.successors {
+                let attributes = match edge {
+                    Edge::Next => String::new(),
+                    Edge::Jump => " [label=\"jump\"]".to_string(),
+                    Edge::Taken => " [label=\"taken\"]".to_string(),
+                    Edge::NotTaken => " [label=\"not taken\"]".to_string(),
+                };
+
+                if routine.blocks.contains(target) {
+                    dot.push_str(&format!("    b{:04X} -> b{:04X}{};\n", start, target, attributes));
+                } else {
+                    let other = self.name(*target, false);
+                    dot.push_str(&format!("    b{:04X} -> \"{}\"{};\n", start, escape(&other), attributes));
+                    others.insert(other);
+                }
+            }
+        }
+
+        for other in others {
+            dot.push_str(&format!("    \"{}\" [shape=ellipse];\n", escape(&other)));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,7 @@
+mod core;
+pub mod block;
+
+#[cfg(test)]
+mod test;
+
+pub use core::ControlFlowGraph;
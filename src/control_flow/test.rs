@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::assembler::encoder::{Encoder, symbol_table::SymbolTable};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::binary::container::{RomContainer, SymbolKind};
+use crate::control_flow::{ControlFlowGraph, block::Edge, block::Exit};
+
+fn graph(source: &str) -> ControlFlowGraph {
+    graph_with_host_calls(source, false)
+}
+
+fn graph_with_host_calls(source: &str, host_calls: bool) -> ControlFlowGraph {
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode(&parser.instructions, &mut table).unwrap();
+    let container = RomContainer::from_program(&program, &table, "test", "standard", 0x8000);
+
+    let labels: HashMap<u16, String> = container.symbols
+        .iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Label)
+        .map(|symbol| (symbol.address, symbol.name.clone()))
+        .collect();
+    let length = program.rom.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+    ControlFlowGraph::build(&program.rom[..length], 0x8000, container.entry_point, labels, host_calls)
+}
+
+#[test]
+fn splits_blocks_at_branches_and_jump_targets() {
+    let graph = graph("\
+START:
+    LOAD 5 A
+LOOP:
+    DEC A
+    JGT :LOOP B
+    HALT
+");
+
+    let blocks: Vec<(usize, Exit)> = graph.blocks.values().map(|block| (block.lines.len(), block.exit)).collect();
+    assert_eq!(blocks, vec![(1, Exit::Next), (2, Exit::Branch), (1, Exit::Halt)]);
+
+    let branch = graph.blocks.values().nth(1).unwrap();
+    assert_eq!(branch.successors, vec![(branch.start, Edge::Taken), (branch.end, Edge::NotTaken)]);
+    assert_eq!(graph.routines.len(), 1);
+    assert_eq!(graph.routines[0].problems, Vec::<String>::new());
+}
+
+#[test]
+fn call_targets_become_routines() {
+    let graph = graph("\
+START:
+    CALL :DOUBLE
+    HALT
+DOUBLE:
+    ADD A
+    RTRN
+");
+
+    let names: Vec<&str> = graph.routines.iter().map(|routine| routine.name.as_str()).collect();
+    assert_eq!(names, vec!["START", "DOUBLE"]);
+    assert_eq!(graph.routines[1].blocks.len(), 1);
+    assert!(graph.routines.iter().all(|routine| routine.problems.is_empty()));
+}
+
+#[test]
+fn reports_routines_that_fall_through_without_returning() {
+    let graph = graph("\
+START:
+    CALL :FIRST
+    CALL :SECOND
+    HALT
+FIRST:
+    INC A
+SECOND:
+    DEC A
+    RTRN
+");
+
+    assert_eq!(graph.routines[1].problems, vec!["FIRST can fall through into SECOND without RTRN"]);
+    assert!(graph.routines[2].problems.is_empty());
+}
+
+#[test]
+fn reports_code_that_runs_off_the_end() {
+    let graph = graph("START:\n    INC A\n");
+
+    assert_eq!(graph.routines[0].problems, vec!["START can run past the end of its code at 0x8003"]);
+}
+
+#[test]
+fn exports_each_routine_as_dot() {
+    let graph = graph("\
+START:
+    CALL :DOUBLE
+    HALT
+DOUBLE:
+    ADD A
+    RTRN
+");
+
+    let dot = graph.to_dot(&graph.routines[0]);
+    assert!(dot.starts_with("digraph \"START\" {\n"));
+    assert!(dot.contains("START:\\l8000  CALL :DOUBLE\\l"));
+    assert!(dot.contains("-> \"DOUBLE\" [style=dashed, label=\"call\"];"));
+    assert!(dot.contains("    \"DOUBLE\" [shape=ellipse];\n"));
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn exit_host_call_ends_a_routine_when_host_calls_are_enabled() {
+    let source = "\
+START:
+    CALL :FINISH
+    HALT
+FINISH:
+    TRAP 0xF2
+";
+
+    let finish = &graph_with_host_calls(source, true).routines[1];
+    assert!(finish.problems.is_empty());
+
+    let finish = &graph(source).routines[1];
+    assert_eq!(finish.problems.len(), 1);
+}
@@ -0,0 +1,476 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use crate::assembler::parser::Parser;
+use crate::chiiko::MachineProfile;
+use crate::chiiko::components::chip::Chip;
+use crate::dap::session::{Run, Session, Stop};
+use crate::protocol::{json::Json, transport::read_message, transport::write_message};
+use crate::testing::suite::FLAGS;
+
+const STEPS_PER_POLL: usize = 10_000; // Instructions run between checks for new requests
+const THREAD_ID: usize = 1; // The machine is the only thread
+const REGISTERS_REFERENCE: usize = 1;
+const FLAGS_REFERENCE: usize = 2;
+const REGISTERS: &[(&str, u8)] = &[("A", 0), ("B", 1), ("C", 2), ("H", 3), ("L", 4), ("I", 5), ("J", 6)];
+const PAIRS: &[(&str, u8)] = &[("BC", 9), ("HL", 10), ("IJ", 11)];
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Answers debug adapter protocol requests for one .ku program. Between requests the machine
+// runs in slices, so a program that never stops can still be paused.
+#[derive(Default)]
+pub struct Adapter {
+    session: Option<Session>,
+    running: Option<Run>,
+    stop_on_entry: bool,
+    zero_based_lines: bool, // Lines are numbered from 1 unless the editor asks otherwise
+    zero_based_columns: bool,
+    events: Vec<(&'static str, Json)>, // Sent after the response to the current request
+    sequence: usize,
+    finished: bool,
+}
+
+// Serves one editor session until it disconnects. Requests are read on a thread of their own
+// so they can arrive while the program runs.
+pub fn serve(input: impl BufRead + Send + 'static, output: &mut impl Write) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let reader = thread::spawn(move || -> io::Result<()> {
+        let mut input = input;
+        while let Some(message) = read_message(&mut input)? {
+            if sender.send(message).is_err() {
+                break
+            }
+        }
+        Ok(())
+    });
+
+    let mut adapter = Adapter::new();
+
+    loop {
+        let message = match adapter.is_running() {
+            true => match receiver.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            },
+            false => match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+
+        let replies = match message {
+            Some(message) => adapter.handle(&message),
+            None => adapter.resume(STEPS_PER_POLL),
+        };
+        for reply in replies {
+            write_message(output, &reply)?;
+        }
+
+        if adapter.finished {
+            return Ok(())
+        }
+    }
+
+    // The editor went away without disconnecting; report why if the input was broken
+    reader.join().unwrap_or(Ok(()))
+}
+
+impl Adapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    // Handles one incoming message and returns everything to send back for it
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        if message.str("type") != Some("request") {
+            return Vec::new()
+        }
+        let command = message.str("command").unwrap_or_default();
+        let arguments = message.get("arguments").cloned().unwrap_or(Json::Null);
+
+        let result = match command {
+            "initialize" => Ok(self.initialize(&arguments)),
+            "launch" => self.launch(&arguments),
+            "setBreakpoints" => self.set_breakpoints(&arguments),
+            "setExceptionBreakpoints" => Ok(Json::object([("breakpoints", Json::Array(Vec::new()))])),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(Json::object([
+                ("threads", vec![Json::object([("id", THREAD_ID.into()), ("name", "main".into())])].into()),
+            ])),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(Json::object([("scopes", vec![
+                scope("Registers", REGISTERS_REFERENCE),
+                scope("Flags", FLAGS_REFERENCE),
+            ].into())])),
+            "variables" => self.variables(&arguments),
+            "readMemory" => self.read_memory(&arguments),
+            "continue" => self.start(Run::Continue).map(|()| Json::object([("allThreadsContinued", true.into())])),
+            "next" => self.step_over(),
+            "stepIn" => self.step_in(),
+            "stepOut" => self.step_out(),
+            "pause" => {
+                if self.running.take().is_some() {
+                    self.stopped("pause");
+                }
+                Ok(Json::Null)
+            },
+            "terminate" => {
+                self.running = None;
+                self.events.push(("terminated", Json::Object(Vec::new())));
+                Ok(Json::Null)
+            },
+            "disconnect" => {
+                self.finished = true;
+                Ok(Json::Null)
+            },
+            _ => Err(format!("Unsupported request: {}", command)),
+        };
+
+        let (success, body, error) = match result {
+            Ok(body) => (true, body, Json::Null),
+            Err(error) => (false, Json::Null, error.into()),
+        };
+        let request = message.get("seq").cloned().unwrap_or(Json::Null);
+        let response = self.message("response", vec![
+            ("request_seq", request),
+            ("success", success.into()),
+            ("command", command.into()),
+            ("message", error),
+            ("body", body),
+        ]);
+
+        let mut replies = vec![response];
+        replies.extend(self.take_events());
+        replies
+    }
+
+    // Runs the machine a little further when it is running. Returns the events it caused.
+    pub fn resume(&mut self, limit: usize) -> Vec<Json> {
+        if let (Some(run), Some(session)) = (self.running, self.session.as_mut()) {
+            match session.run(run, limit) {
+                Some(stop) => self.stop(stop),
+                None => self.report_output(),
+            }
+        }
+
+        self.take_events()
+    }
+
+    fn initialize(&mut self, arguments: &Json) -> Json {
+        self.zero_based_lines = arguments.bool("linesStartAt1") == Some(false);
+        self.zero_based_columns = arguments.bool("columnsStartAt1") == Some(false);
+
+        Json::object([
+            ("supportsConfigurationDoneRequest", true.into()),
+            ("supportsReadMemoryRequest", true.into()),
+            ("supportsTerminateRequest", true.into()),
+        ])
+    }
+
+    // Assembles the program. Breakpoints can be set once the editor hears it is initialized.
+    fn launch(&mut self, arguments: &Json) -> Result<Json, String> {
+        let program = arguments.str("program").ok_or("Launching needs the path of a .ku program")?;
+        let path = fs::canonicalize(program).map_err(|error| format!("Failed to read file: {} {}", program, error))?;
+        let mut profile = match arguments.str("profile") {
+            Some(filename) => MachineProfile::from_file(filename)?,
+            None => MachineProfile::default(),
+        };
+        profile.host_calls |= arguments.bool("hostCalls").unwrap_or(false);
+        let input: Vec<&str> = arguments
+            .array("input")
+            .iter()
+            .filter_map(|line| match line {
+                Json::String(line) => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        self.session = Some(Session::launch(&path, &profile, &input)?);
+        self.stop_on_entry = arguments.bool("stopOnEntry").unwrap_or(false);
+        self.events.push(("initialized", Json::Object(Vec::new())));
+        Ok(Json::Null)
+    }
+
+    // Starts the program, unless it should stop before its first instruction. Running only
+    // checks breakpoints after each step, so one on the first instruction is checked here.
+    fn configuration_done(&mut self) -> Result<Json, String> {
+        let session = self.session.as_ref().ok_or_else(not_launched)?;
+
+        if self.stop_on_entry {
+            self.stopped("entry");
+        } else if session.at_breakpoint() {
+            self.stopped("breakpoint");
+        } else {
+            self.running = Some(Run::Continue);
+        }
+
+        Ok(Json::Null)
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        let lines: Vec<usize> = arguments
+            .array("breakpoints")
+            .iter()
+            .filter_map(|breakpoint| breakpoint.number("line"))
+            .map(|line| line as usize + self.zero_based_lines as usize)
+            .collect();
+        let session = self.session.as_mut().ok_or_else(not_launched)?;
+
+        let same_file = arguments
+            .str("source.path")
+            .and_then(|path| fs::canonicalize(path).ok())
+            .is_some_and(|path| path == session.path);
+        let placed = match same_file {
+            true => session.set_breakpoints(&lines),
+            false => vec![None; lines.len()],
+        };
+
+        let breakpoints: Vec<Json> = lines
+            .iter()
+            .zip(placed)
+            .map(|(line, placed)| match placed {
+                Some(found) => Json::object([("verified", true.into()), ("line", self.client_line(found).into())]),
+                None => Json::object([
+                    ("verified", false.into()),
+                    ("line", self.client_line(*line).into()),
+                    ("message", match same_file {
+                        true => "No code on or after this line",
+                        false => "Breakpoints can only be set in the launched program",
+                    }.into()),
+                ]),
+            })
+            .collect();
+
+        Ok(Json::object([("breakpoints", breakpoints.into())]))
+    }
+
+    fn stack_trace(&self) -> Result<Json, String> {
+        let session = self.session.as_ref().ok_or_else(not_launched)?;
+        let source = Json::object([
+            ("name", session.path.file_name().map_or(String::new(), |name| name.to_string_lossy().to_string()).into()),
+            ("path", session.path.to_string_lossy().to_string().into()),
+        ]);
+
+        let frames: Vec<Json> = session
+            .stack()
+            .iter()
+            .enumerate()
+            .map(|(id, address)| {
+                let line = session.source_map.line_of(*address);
+                object(vec![
+                    ("id", id.into()),
+                    ("name", session.name(*address).into()),
+                    ("source", line.map_or(Json::Null, |_| source.clone())),
+                    ("line", line.map_or(0, |line| self.client_line(line)).into()),
+                    ("column", (1 - self.zero_based_columns as usize).into()),
+                    ("instructionPointerReference", format!("{:#06X}", address).into()),
+                ])
+            })
+            .collect();
+
+        let total = frames.len();
+        Ok(Json::object([("stackFrames", frames.into()), ("totalFrames", total.into())]))
+    }
+
+    fn variables(&self, arguments: &Json) -> Result<Json, String> {
+        let cpu = &self.session.as_ref().ok_or_else(not_launched)?.chiiko.cpu;
+
+        let variables: Vec<Json> = match arguments.number("variablesReference").map(|reference| reference as usize) {
+            Some(REGISTERS_REFERENCE) => {
+                let bytes = REGISTERS
+                    .iter()
+                    .map(|(name, code)| byte_variable(name, cpu.read_register(*code).unwrap_or(0)))
+                    .chain([byte_variable("STATUS", cpu.status)]);
+                let words = PAIRS
+                    .iter()
+                    .map(|(name, code)| (*name, cpu.read_register_pair(*code).unwrap_or(0)))
+                    .chain([("SP", cpu.stack_pointer()), ("FP", cpu.frame_pointer()), ("PC", cpu.program_counter)])
+                    .map(|(name, value)| word_variable(name, value));
+                bytes.chain(words).collect()
+            },
+            Some(FLAGS_REFERENCE) => FLAGS
+                .iter()
+                .map(|(name, bit)| variable(name, if cpu.status & bit != 0 { "1" } else { "0" }, "flag", Json::Null))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(Json::object([("variables", variables.into())]))
+    }
+
+    // Memory references are addresses, such as the ones given with 16 bit registers
+    fn read_memory(&self, arguments: &Json) -> Result<Json, String> {
+        let session = self.session.as_ref().ok_or_else(not_launched)?;
+        let reference = arguments.str("memoryReference").unwrap_or_default();
+        let base = Parser::normalize_number(&reference.to_uppercase())
+            .ok()
+            .and_then(|address| u16::try_from(address).ok())
+            .ok_or_else(|| format!("Invalid memory reference: {}", reference))?;
+
+        let offset = arguments.number("offset").unwrap_or(0.0) as i64;
+        let count = arguments.number("count").unwrap_or(0.0).max(0.0) as i64;
+        let start = (base as i64 + offset).clamp(0, 0x10000);
+        let end = (start + count).min(0x10000);
+
+        let bytes: Vec<u8> = (start..end).map(|address| session.chiiko.cpu.read(address as u16)).collect();
+        Ok(Json::object([
+            ("address", format!("{:#06X}", start).into()),
+            ("data", base64(&bytes).into()),
+            ("unreadableBytes", ((count - bytes.len() as i64) as usize).into()),
+        ]))
+    }
+
+    fn start(&mut self, run: Run) -> Result<(), String> {
+        self.session.as_ref().ok_or_else(not_launched)?;
+        self.running = Some(run);
+        Ok(())
+    }
+
+    fn step_in(&mut self) -> Result<Json, String> {
+        let stop = self.session.as_mut().ok_or_else(not_launched)?.step();
+        self.stop(stop);
+        Ok(Json::Null)
+    }
+
+    // Steps one instruction, running through any call it makes
+    fn step_over(&mut self) -> Result<Json, String> {
+        let session = self.session.as_mut().ok_or_else(not_launched)?;
+        let depth = session.depth();
+
+        match session.step() {
+            Stop::Step if session.depth() > depth => self.running = Some(Run::Return(depth)),
+            stop => self.stop(stop),
+        }
+        Ok(Json::Null)
+    }
+
+    fn step_out(&mut self) -> Result<Json, String> {
+        let depth = self.session.as_ref().ok_or_else(not_launched)?.depth();
+
+        self.start(match depth {
+            0 => Run::Continue,
+            _ => Run::Return(depth - 1),
+        })?;
+        Ok(Json::Null)
+    }
+
+    fn stop(&mut self, stop: Stop) {
+        self.running = None;
+        self.report_output();
+
+        match stop {
+            Stop::Step => self.stopped("step"),
+            Stop::Breakpoint => self.stopped("breakpoint"),
+            Stop::Fault(fault) => {
+                let description = match fault.starts_with("No more input") {
+                    true => "The program is waiting for input: add lines to `input` in the launch configuration",
+                    false => "Machine fault",
+                };
+                self.events.push(("stopped", Json::object([
+                    ("reason", "exception".into()),
+                    ("description", description.into()),
+                    ("text", fault.into()),
+                    ("threadId", THREAD_ID.into()),
+                    ("allThreadsStopped", true.into()),
+                ])));
+            },
+            Stop::Exited(code) => {
+                self.events.push(("exited", Json::object([("exitCode", (code as usize).into())])));
+                self.events.push(("terminated", Json::Object(Vec::new())));
+            },
+        }
+    }
+
+    fn stopped(&mut self, reason: &str) {
+        self.events.push(("stopped", Json::object([
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ])));
+    }
+
+    fn report_output(&mut self) {
+        if let Some(output) = self.session.as_mut().and_then(|session| session.take_output()) {
+            self.events.push(("output", Json::object([("category", "stdout".into()), ("output", output.into())])));
+        }
+    }
+
+    fn take_events(&mut self) -> Vec<Json> {
+        std::mem::take(&mut self.events)
+            .into_iter()
+            .map(|(event, body)| self.message("event", vec![("event", event.into()), ("body", body)]))
+            .collect()
+    }
+
+    // Every message carries its own sequence number
+    fn message(&mut self, kind: &str, fields: Vec<(&str, Json)>) -> Json {
+        self.sequence += 1;
+        let mut message = vec![("seq", self.sequence.into()), ("type", kind.into())];
+        message.extend(fields);
+        object(message)
+    }
+
+    fn client_line(&self, line: usize) -> usize {
+        line - self.zero_based_lines as usize
+    }
+}
+
+// Like `Json::object`, leaving out fields that are null
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields
+        .into_iter()
+        .filter(|(_, value)| *value != Json::Null)
+        .map(|(name, value)| (name.to_string(), value))
+        .collect())
+}
+
+fn scope(name: &str, reference: usize) -> Json {
+    Json::object([("name", name.into()), ("variablesReference", reference.into()), ("expensive", false.into())])
+}
+
+fn variable(name: &str, value: &str, kind: &str, memory: Json) -> Json {
+    object(vec![
+        ("name", name.into()),
+        ("value", value.into()),
+        ("type", kind.into()),
+        ("variablesReference", 0usize.into()),
+        ("memoryReference", memory),
+    ])
+}
+
+fn byte_variable(name: &str, value: u8) -> Json {
+    variable(name, &format!("{:#04X}", value), "u8", Json::Null)
+}
+
+// Words can be opened in the editor's memory view
+fn word_variable(name: &str, value: u16) -> Json {
+    let address = format!("{:#06X}", value);
+    variable(name, &address, "u16", address.as_str().into())
+}
+
+fn not_launched() -> String {
+    "No program has been launched".to_string()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| group | (*byte as u32) << (16 - 8 * index));
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(BASE64[(group >> (18 - 6 * index) & 0x3F) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,7 @@
+pub mod adapter;
+pub mod session;
+
+#[cfg(test)]
+mod test;
+
+pub use adapter::serve;
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::assembler::assembly_error::AssemblyError;
+use crate::assembler::encoder::{Encoder, symbol_table::SymbolTable, syntax_checker::SyntaxChecker};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::assembler::source::Source;
+use crate::assembler::source_map::SourceMap;
+use crate::binary::container::{RomContainer, SymbolKind};
+use crate::chiiko::components::console::Console;
+use crate::chiiko::{Chiiko, MachineProfile};
+use crate::operation::group::{Group, SubroutineVariant};
+
+// A subroutine call the program has not returned from yet
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Frame {
+    call: u16, // The CALL instruction
+    stack_pointer: u16, // Before the return address was pushed
+}
+
+// Why the machine stopped
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stop {
+    Step,
+    Breakpoint,
+    Fault(String),
+    Exited(u8), // Halting without an exit code counts as 0
+}
+
+// How far to run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Run {
+    Continue,
+    Return(usize), // Until the call stack is no deeper than this
+}
+
+// One .ku program loaded for debugging. Calls are followed as they run so the call stack
+// can be shown without the program keeping frame pointers.
+pub struct Session {
+    pub chiiko: Chiiko,
+    pub path: PathBuf,
+    pub source_map: SourceMap,
+    labels: BTreeMap<u16, String>,
+    breakpoints: BTreeSet<u16>,
+    frames: Vec<Frame>,
+    reported: usize, // How much console output has been taken
+}
+
+impl Session {
+    // Assembles a program and loads it with `input` queued as its console input
+    pub fn launch(path: &Path, profile: &MachineProfile, input: &[&str]) -> Result<Self, String> {
+        let filename = path.to_string_lossy().to_string();
+        let failure = |error: AssemblyError| format!("{}: {}", filename, error);
+        let source = Source::from_file(&filename).map_err(failure)?;
+
+        let mut lexer = Lexer::new(&source.raw);
+        let mut parser = Parser::new(lexer.lex());
+        parser.parse();
+        SyntaxChecker::check(parser.instructions.clone()).map_err(failure)?;
+
+        let mut table = SymbolTable::from_ast(&parser.instructions);
+        let program = Encoder::encode_for(&parser.instructions, &mut table, profile).map_err(failure)?;
+        let lines: Vec<usize> = parser.node_starts.iter().map(|start| lexer.positions()[*start].0).collect();
+
+        let title = path.file_stem().map_or_else(|| filename.clone(), |stem| stem.to_string_lossy().to_string());
+        let container = RomContainer::from_program(&program, &table, &title, &profile.name, profile.rom_base);
+        let mut chiiko = Chiiko::from_container(profile, &container)?;
+        chiiko.cpu.console = Console::buffered(input);
+
+        let mut labels = BTreeMap::new();
+        for symbol in container.symbols.iter().filter(|symbol| symbol.kind == SymbolKind::Label) {
+            labels.entry(symbol.address).or_insert_with(|| symbol.name.clone());
+        }
+
+        Ok(Self {
+            chiiko,
+            path: path.to_path_buf(),
+            source_map: SourceMap::new(&parser.instructions, &lines, &program),
+            labels,
+            breakpoints: BTreeSet::new(),
+            frames: Vec::new(),
+            reported: 0,
+        })
+    }
+
+    // Replaces the breakpoints with ones on `lines`, returning the line each one landed on
+    pub fn set_breakpoints(&mut self, lines: &[usize]) -> Vec<Option<usize>> {
+        self.breakpoints.clear();
+
+        lines
+            .iter()
+            .map(|line| {
+                let (found, address) = self.source_map.address_of(*line)?;
+                self.breakpoints.insert(address);
+                Some(found)
+            })
+            .collect()
+    }
+
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.chiiko.cpu.program_counter)
+    }
+
+    // Calls the program has not returned from
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Where each frame of the call stack is, innermost first: the PC, then each call site
+    pub fn stack(&self) -> Vec<u16> {
+        let calls = self.frames.iter().rev().map(|frame| frame.call);
+        std::iter::once(self.chiiko.cpu.program_counter).chain(calls).collect()
+    }
+
+    // The label at or before `address`
+    pub fn name(&self, address: u16) -> String {
+        match self.labels.range(..=address).next_back() {
+            Some((start, label)) if *start == address => label.clone(),
+            Some((start, label)) => format!("{}+{}", label, address - start),
+            None => format!("{:#06X}", address),
+        }
+    }
+
+    // Console output printed since the last call
+    pub fn take_output(&mut self) -> Option<String> {
+        let output = &self.chiiko.cpu.console.output()[self.reported..];
+        if output.is_empty() {
+            return None
+        }
+
+        self.reported += output.len();
+        Some(output.to_string())
+    }
+
+    // Runs one instruction. A faulting instruction is left under the PC, so one waiting for
+    // input runs again once there is some.
+    pub fn step(&mut self) -> Stop {
+        if self.chiiko.is_halted() {
+            return self.exit()
+        }
+
+        let address = self.chiiko.cpu.program_counter;
+        let stack_pointer = self.chiiko.cpu.stack_pointer();
+        if let Err(fault) = self.chiiko.step() {
+            self.chiiko.cpu.set_pc(address);
+            return Stop::Fault(fault)
+        }
+
+        if self.chiiko.cpu.instruction.operation.group == Group::Subroutine(SubroutineVariant::Call) {
+            self.frames.push(Frame { call: address, stack_pointer });
+        }
+
+        // A frame ends once its return address has been popped, however that happened
+        let stack_pointer = self.chiiko.cpu.stack_pointer();
+        while self.frames.last().is_some_and(|frame| stack_pointer >= frame.stack_pointer) {
+            self.frames.pop();
+        }
+
+        match self.chiiko.is_halted() {
+            true => self.exit(),
+            false => Stop::Step,
+        }
+    }
+
+    // Runs at most `limit` instructions. Returns why the machine stopped, or nothing when it
+    // is still running.
+    pub fn run(&mut self, run: Run, limit: usize) -> Option<Stop> {
+        for _ in 0..limit {
+            match self.step() {
+                Stop::Step => (),
+                stop => return Some(stop),
+            }
+
+            if self.at_breakpoint() {
+                return Some(Stop::Breakpoint)
+            }
+            if matches!(run, Run::Return(depth) if self.frames.len() <= depth) {
+                return Some(Stop::Step)
+            }
+        }
+
+        None
+    }
+
+    fn exit(&self) -> Stop {
+        Stop::Exited(self.chiiko.cpu.exit_code.unwrap_or(0))
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::{BufReader, Cursor};
+
+use crate::dap::{adapter::Adapter, serve};
+use crate::protocol::{json::Json, transport::read_message, transport::write_message};
+
+const PROGRAM: &str = "\
+; Reads a number, triples it in a subroutine and exits with the result
+STRING $0x0020 \"Hi\"
+VAR $0x0010 NUMBER
+
+START:
+    NIN $NUMBER
+    LOAD $NUMBER B
+    CALL :TRIPLE
+    TLLY $NUMBER
+    TRAP 0xF2
+
+TRIPLE:
+    LOAD B A
+    ADD B
+    ADD B
+    RTRN
+";
+
+fn program(name: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, PROGRAM).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+fn request(seq: usize, command: &str, arguments: Json) -> Json {
+    Json::object([("seq", seq.into()), ("type", "request".into()), ("command", command.into()), ("arguments", arguments)])
+}
+
+fn launch(path: &str, input: &[&str], stop_on_entry: bool) -> (Adapter, Vec<Json>) {
+    let mut adapter = Adapter::new();
+    adapter.handle(&request(1, "initialize", Json::object([("adapterID", "chiiko".into())])));
+    let replies = adapter.handle(&request(2, "launch", Json::object([
+        ("program", path.into()),
+        ("input", input.iter().map(|line| (*line).into()).collect::<Vec<Json>>().into()),
+        ("stopOnEntry", stop_on_entry.into()),
+        ("hostCalls", true.into()),
+    ])));
+    (adapter, replies)
+}
+
+fn events<'a>(replies: &'a [Json], name: &str) -> Vec<&'a Json> {
+    replies.iter().filter(|reply| reply.str("event") == Some(name)).collect()
+}
+
+fn stop_reason(replies: &[Json]) -> Option<&str> {
+    events(replies, "stopped").first().and_then(|event| event.str("body.reason"))
+}
+
+// The name and line of each stack frame, innermost first
+fn stack(adapter: &mut Adapter) -> Vec<(String, usize)> {
+    let replies = adapter.handle(&request(90, "stackTrace", Json::object([("threadId", 1usize.into())])));
+    replies[0]
+        .array("body.stackFrames")
+        .iter()
+        .map(|frame| (frame.str("name").unwrap().to_string(), frame.number("line").unwrap() as usize))
+        .collect()
+}
+
+fn variable(adapter: &mut Adapter, reference: usize, name: &str) -> String {
+    let replies = adapter.handle(&request(91, "variables", Json::object([("variablesReference", reference.into())])));
+    let variables = replies[0].array("body.variables");
+    let variable = variables.iter().find(|variable| variable.str("name") == Some(name)).unwrap();
+    variable.str("value").unwrap().to_string()
+}
+
+#[test]
+fn launches_and_places_breakpoints_on_lines_with_code() {
+    let path = program("chiiko_dap_breakpoints.ku");
+    let (mut adapter, replies) = launch(&path, &["7"], false);
+
+    assert_eq!(replies[0].str("type"), Some("response"));
+    assert_eq!(replies[0].bool("success"), Some(true));
+    assert_eq!(replies[0].number("request_seq"), Some(2.0));
+    assert_eq!(replies[1].str("event"), Some("initialized"));
+
+    let replies = adapter.handle(&request(3, "setBreakpoints", Json::object([
+        ("source", Json::object([("path", path.as_str().into())])),
+        ("breakpoints", vec![
+            Json::object([("line", 13usize.into())]),
+            Json::object([("line", 4usize.into())]),
+            Json::object([("line", 40usize.into())]),
+        ].into()),
+    ])));
+    let breakpoints = replies[0].array("body.breakpoints");
+
+    assert_eq!(breakpoints[0].bool("verified"), Some(true));
+    assert_eq!(breakpoints[0].number("line"), Some(13.0));
+    assert_eq!(breakpoints[1].number("line"), Some(6.0)); // The blank line moves on to NIN
+    assert_eq!(breakpoints[2].bool("verified"), Some(false));
+
+    // The first instruction is already on a breakpoint
+    let replies = adapter.handle(&request(4, "configurationDone", Json::Null));
+    assert_eq!(stop_reason(&replies), Some("breakpoint"));
+    assert_eq!(stack(&mut adapter), [("START".to_string(), 6)]);
+}
+
+#[test]
+fn runs_to_breakpoints_and_shows_the_call_stack() {
+    let path = program("chiiko_dap_stack.ku");
+    let (mut adapter, _) = launch(&path, &["7"], false);
+    adapter.handle(&request(3, "setBreakpoints", Json::object([
+        ("source", Json::object([("path", path.as_str().into())])),
+        ("breakpoints", vec![Json::object([("line", 14usize.into())])].into()),
+    ])));
+    adapter.handle(&request(4, "configurationDone", Json::Null));
+
+    let replies = adapter.resume(1000);
+    assert_eq!(stop_reason(&replies), Some("breakpoint"));
+
+    let frames = stack(&mut adapter);
+    assert_eq!(frames[0], ("TRIPLE+4".to_string(), 14));
+    assert_eq!(frames[1], ("START+9".to_string(), 8));
+    assert_eq!(variable(&mut adapter, 1, "A"), "0x07");
+    assert_eq!(variable(&mut adapter, 1, "B"), "0x07");
+
+    adapter.handle(&request(5, "next", Json::Null));
+    assert_eq!(variable(&mut adapter, 1, "A"), "0x0E");
+
+    adapter.handle(&request(6, "stepOut", Json::Null));
+    let replies = adapter.resume(1000);
+    assert_eq!(stop_reason(&replies), Some("step"));
+    assert_eq!(stack(&mut adapter), [("START+12".to_string(), 9)]);
+
+    adapter.handle(&request(7, "continue", Json::Null));
+    let replies = adapter.resume(1000);
+    assert_eq!(events(&replies, "output")[0].str("body.output"), Some("7\n"));
+    assert_eq!(events(&replies, "exited")[0].number("body.exitCode"), Some(21.0));
+    assert_eq!(events(&replies, "terminated").len(), 1);
+}
+
+#[test]
+fn steps_over_and_into_calls() {
+    let path = program("chiiko_dap_steps.ku");
+    let (mut adapter, _) = launch(&path, &["2"], true);
+
+    let replies = adapter.handle(&request(3, "configurationDone", Json::Null));
+    assert_eq!(stop_reason(&replies), Some("entry"));
+
+    adapter.handle(&request(4, "next", Json::Null));
+    let replies = adapter.handle(&request(5, "next", Json::Null));
+    assert_eq!(stop_reason(&replies), Some("step"));
+    assert_eq!(stack(&mut adapter)[0].1, 8);
+
+    let replies = adapter.handle(&request(6, "stepIn", Json::Null));
+    assert_eq!(stop_reason(&replies), Some("step"));
+    assert_eq!(stack(&mut adapter), [("TRIPLE".to_string(), 13), ("START+9".to_string(), 8)]);
+
+    adapter.handle(&request(7, "stepOut", Json::Null));
+    adapter.resume(1000);
+    assert_eq!(stack(&mut adapter)[0].1, 9);
+    assert_eq!(variable(&mut adapter, 1, "A"), "0x06");
+
+    // Running over a call stops once it returns
+    let (mut adapter, _) = launch(&path, &["2"], true);
+    adapter.handle(&request(3, "configurationDone", Json::Null));
+    adapter.handle(&request(4, "next", Json::Null));
+    adapter.handle(&request(5, "next", Json::Null));
+
+    let replies = adapter.handle(&request(6, "next", Json::Null));
+    assert!(stop_reason(&replies).is_none());
+    assert!(adapter.is_running());
+
+    let replies = adapter.resume(1000);
+    assert_eq!(stop_reason(&replies), Some("step"));
+    assert_eq!(stack(&mut adapter)[0].1, 9);
+}
+
+#[test]
+fn shows_registers_flags_and_memory() {
+    let path = program("chiiko_dap_memory.ku");
+    let (mut adapter, _) = launch(&path, &["7"], true);
+    adapter.handle(&request(3, "configurationDone", Json::Null));
+
+    let replies = adapter.handle(&request(4, "scopes", Json::object([("frameId", 0usize.into())])));
+    let scopes = replies[0].array("body.scopes");
+    assert_eq!(scopes[0].str("name"), Some("Registers"));
+    assert_eq!(scopes[1].str("name"), Some("Flags"));
+
+    assert_eq!(variable(&mut adapter, 1, "PC"), "0x8000");
+    assert_eq!(variable(&mut adapter, 2, "Z"), "0");
+
+    let replies = adapter.handle(&request(5, "variables", Json::object([("variablesReference", 1usize.into())])));
+    let pc = replies[0].array("body.variables").iter().find(|variable| variable.str("name") == Some("PC")).unwrap();
+    assert_eq!(pc.str("memoryReference"), Some("0x8000"));
+
+    let replies = adapter.handle(&request(6, "readMemory", Json::object([
+        ("memoryReference", "0x001F".into()),
+        ("offset", 1usize.into()),
+        ("count", 3usize.into()),
+    ])));
+    assert_eq!(replies[0].str("body.address"), Some("0x0020"));
+    assert_eq!(replies[0].str("body.data"), Some("SGkA")); // "Hi" and its terminator
+
+    let replies = adapter.handle(&request(7, "readMemory", Json::object([
+        ("memoryReference", "0xFFFE".into()),
+        ("count", 4usize.into()),
+    ])));
+    assert_eq!(replies[0].number("body.unreadableBytes"), Some(2.0));
+}
+
+#[test]
+fn stops_when_the_program_waits_for_input() {
+    let path = program("chiiko_dap_input.ku");
+    let (mut adapter, _) = launch(&path, &[], false);
+    adapter.handle(&request(3, "configurationDone", Json::Null));
+
+    let replies = adapter.resume(1000);
+    let stopped = events(&replies, "stopped")[0];
+    assert_eq!(stopped.str("body.reason"), Some("exception"));
+    assert!(stopped.str("body.text").unwrap().starts_with("No more input"));
+    assert_eq!(stack(&mut adapter)[0].1, 6);
+}
+
+#[test]
+fn refuses_requests_it_cannot_answer() {
+    let mut adapter = Adapter::new();
+
+    let replies = adapter.handle(&request(1, "stackTrace", Json::Null));
+    assert_eq!(replies[0].bool("success"), Some(false));
+    assert_eq!(replies[0].str("message"), Some("No program has been launched"));
+
+    let replies = adapter.handle(&request(2, "evaluate", Json::Null));
+    assert_eq!(replies[0].str("message"), Some("Unsupported request: evaluate"));
+
+    let (_, replies) = launch("/nonexistent/program.ku", &[], false);
+    assert_eq!(replies[0].bool("success"), Some(false));
+    assert!(replies[0].str("message").unwrap().starts_with("Failed to read file"));
+}
+
+#[test]
+fn serves_a_session_over_streams() {
+    let path = program("chiiko_dap_serve.ku");
+    let mut input = Vec::new();
+    for message in [
+        request(1, "initialize", Json::Null),
+        request(2, "launch", Json::object([("program", path.as_str().into()), ("stopOnEntry", true.into())])),
+        request(3, "configurationDone", Json::Null),
+        request(4, "disconnect", Json::Null),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+
+    let mut output = Vec::new();
+    serve(Cursor::new(input), &mut output).unwrap();
+
+    let mut reader = BufReader::new(output.as_slice());
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+
+    let kinds: Vec<&str> = messages
+        .iter()
+        .map(|message| message.str("command").or(message.str("event")).unwrap())
+        .collect();
+    assert_eq!(kinds, ["initialize", "launch", "initialized", "configurationDone", "stopped", "disconnect"]);
+
+    let sequence: Vec<f64> = messages.iter().map(|message| message.number("seq").unwrap()).collect();
+    assert_eq!(sequence, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::assembler::parser::assembler_operand::AssemblerOperand;
+use crate::chiiko::components::cpu_operand::CpuOperand;
+use crate::codec::{self, MAXIMUM_LENGTH};
+use crate::mode::{Mode, mode_group::ModeGroup};
+
+const EXPLICIT_MODE: u8 = 0b1000_0000;
+
+// One decoded instruction. `text` is written in assembler syntax so it can be fed back in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        write!(f, "{:04X}  {:<20}{}", self.address, bytes.join(" "), self.text)
+    }
+}
+
+// Turns machine code back into assembly. Jump and branch targets are shown as absolute
+// addresses, or as label names when symbols are known.
+#[derive(Default)]
+pub struct Disassembler {
+    labels: HashMap<u16, String>,
+}
+
+impl Disassembler {
+    pub fn with_labels(labels: HashMap<u16, String>) -> Self {
+        Self { labels }
+    }
+
+    // Decodes every instruction in an image that is loaded at `origin`
+    pub fn disassemble(&self, image: &[u8], origin: u16) -> Vec<Line> {
+        let read = |address: u16| {
+            image.get(address.wrapping_sub(origin) as usize).copied().unwrap_or(0)
+        };
+        let mut lines = Vec::new();
+        let mut offset = 0;
+
+        while offset < image.len() {
+            let line = self.decode(&read, origin.wrapping_add(offset as u16));
+            offset += line.bytes.len();
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    pub fn decode(&self, read: &dyn Fn(u16) -> u8, address: u16) -> Line {
+        let window: Vec<u8> = (0..MAXIMUM_LENGTH as u16).map(|offset| read(address.wrapping_add(offset))).collect();
+        let Ok((instruction, length)) = codec::decode(&window) else {
+            return Line { address, bytes: vec![window[0]], text: format!("; data {:#04X}", window[0]) }
+        };
+
+        let bytes = window[..length].to_vec();
+        let next = address.wrapping_add(length as u16);
+        let operation = &instruction.operation;
+        let (left, right) = Mode::from_byte(instruction.mode);
+
+        let mut text = operation.mnemonics[0].to_string();
+        if bytes[0] & EXPLICIT_MODE != 0 {
+            text.push_str(&format!(" ({}, {})", left.keys[0], right.keys[0]));
+        }
+
+        let operands = [(left, instruction.left_operand), (right, instruction.right_operand)];
+        for (index, (mode, operand)) in operands.iter().enumerate() {
+            // Implicit operands take no bytes and are already named by the mode
+            if mode.group.operand_size() == 0 {
+                continue;
+            }
+
+            let relative = index == 0 && operation.is_relative_branch() && mode.group == ModeGroup::Value;
+            text.push(' ');
+            text.push_str(&self.operand_text(operand, relative.then_some(next)));
+        }
+
+        Line { address, bytes, text }
+    }
+
+    // `next` is the address after the instruction, given when the operand is a branch displacement
+    fn operand_text(&self, operand: &CpuOperand, next: Option<u16>) -> String {
+        let register = |code: u8| AssemblerOperand::register_name(code)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("{:#04X}", code));
+
+        match *operand {
+            CpuOperand::Value(value) => match next {
+                Some(next) => self.target(next.wrapping_add(value as i8 as u16)),
+                None => format!("{:#04X}", value),
+            },
+            CpuOperand::Register(code) => register(code),
+            CpuOperand::IndirectRegister(code) => format!("@{}", register(code)),
+            CpuOperand::ZeroPageAddress(address) => format!("${:#04X}", address),
+            CpuOperand::IndirectZeroPageAddress(address) => format!("@{:#04X}", address),
+            CpuOperand::MemoryAddress(address) => format!("${:#06X}", address),
+            CpuOperand::IndirectMemoryAddress(address) => format!("@{:#06X}", address),
+            CpuOperand::JumpAddress(address) => self.target(address),
+            CpuOperand::IndexedAddress(base, index) => format!("${:#06X}+{}", base, register(index)),
+            CpuOperand::IndexedZeroPage(base, index) => format!("${:#04X}+{}", base, register(index)),
+            CpuOperand::StackRelative(offset) => format!("%{}", offset as i8),
+            CpuOperand::None | CpuOperand::Error => String::new(),
+        }
+    }
+
+    fn target(&self, address: u16) -> String {
+        match self.labels.get(&address) {
+            Some(label) => format!(":{}", label),
+            None => format!(":{:#06X}", address),
+        }
+    }
+}
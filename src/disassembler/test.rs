@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use crate::assembler::encoder::{Encoder, symbol_table::SymbolTable};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::disassembler::{Disassembler, Line};
+
+fn assemble(source: &str) -> Vec<u8> {
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    Encoder::encode(&parser.instructions, &mut table).unwrap().rom
+}
+
+fn texts(lines: &[Line]) -> Vec<&str> {
+    lines.iter().map(|line| line.text.as_str()).collect()
+}
+
+#[test]
+fn backward_branch_shows_absolute_target() {
+    let rom = assemble("LOOP:\n  INC B\n  NEG :LOOP\n  HALT\n");
+
+    assert_eq!(rom[3..5], [0x23, 0xFB]);
+
+    let lines = Disassembler::default().disassemble(&rom[0..6], 0x8000);
+    assert_eq!(texts(&lines), ["INC (R, L) B", "NEG :0x8000", "HALT"]);
+    assert_eq!(lines[1].address, 0x8003);
+}
+
+#[test]
+fn labels_replace_jump_targets() {
+    let rom = assemble("START:\n  ZERO :DONE\n  CALL :START\nDONE:\n  HALT\n");
+    let labels = HashMap::from([(0x8000, "START".to_string()), (0x8005, "DONE".to_string())]);
+
+    let lines = Disassembler::with_labels(labels).disassemble(&rom[0..6], 0x8000);
+
+    assert_eq!(texts(&lines), ["ZERO :DONE", "CALL :START", "HALT"]);
+}
+
+#[test]
+fn operands_use_assembler_syntax() {
+    let rom = assemble("ADD $0x0120+I\nSAVE A %-1\nLOAD @HL B\nAND $0x10\n");
+
+    let lines = Disassembler::default().disassemble(&rom[0..16], 0x8000);
+
+    assert_eq!(texts(&lines), [
+        "ADD (X, A) $0x0120+I",
+        "SAVE (R, S) A %-1",
+        "LOAD (IR, R) @HL B",
+        "AND (Z, A) $0x10",
+    ]);
+}
+
+#[test]
+fn unknown_opcodes_are_shown_as_data() {
+    let lines = Disassembler::default().disassemble(&[0x7F], 0x8000);
+
+    assert_eq!(lines[0].text, "; data 0x7F");
+    assert_eq!(lines[0].to_string(), "8000  7F                  ; data 0x7F");
+}
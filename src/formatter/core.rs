@@ -0,0 +1,287 @@
+use crate::assembler::assembly_error::AssemblyError;
+use crate::assembler::lexer::{Lexer, token::Token};
+use crate::assembler::parser::{Parser, ast_node::ASTNode, ast_node::MacroNode};
+
+const INDENT: usize = 4;
+const MAXIMUM_WIDTH: usize = 80; // Longer ARRAY lines wrap their elements
+
+// One line of output. Code keeps its trailing comment apart until comments are aligned.
+enum Line {
+    Code { text: String, comment: Option<String> },
+    Comment(String),
+    Blank,
+}
+
+// Rewrites .ku source in the canonical style: labels and directives at the margin,
+// instructions indented with their operands in one column, upper case names, `(L, R)`
+// mode tuples, and trailing comments lined up within each block of lines.
+pub fn format(source: &str) -> Result<String, AssemblyError> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.lex();
+    let positions = lexer.positions();
+
+    // Source the lexer cannot read would not survive being rewritten
+    for (token, (line, column)) in tokens.iter().zip(positions) {
+        if let Token::Error { message, .. } = token {
+            return Err(AssemblyError::ParseError(format!("{} at line {}, column {}", message, line, column)))
+        }
+    }
+
+    let mut parser = Parser::new(tokens.clone());
+    parser.parse();
+
+    let statements: Vec<Vec<&Token>> = (0..parser.instructions.len())
+        .map(|index| {
+            let start = parser.node_starts[index];
+            let end = parser.node_starts.get(index + 1).copied().unwrap_or(tokens.len());
+            tokens[start..end]
+                .iter()
+                .filter(|token| !matches!(token, Token::Newline | Token::EndOfFile | Token::Comment(_)))
+                .collect()
+        })
+        .collect();
+
+    let mnemonic_width = parser.instructions
+        .iter()
+        .zip(&statements)
+        .filter(|(node, _)| matches!(node, ASTNode::Instruction { .. } | ASTNode::Macro(_)))
+        .filter_map(|(_, statement)| statement.first().map(|token| word(token).chars().count()))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines: Vec<Line> = Vec::new();
+    let mut depth = 0;
+
+    for (index, node) in parser.instructions.iter().enumerate() {
+        let start = parser.node_starts[index];
+        let margin = " ".repeat(depth * INDENT);
+        let indent = " ".repeat((depth + 1) * INDENT);
+
+        match node {
+            ASTNode::BlankLine => {
+                if !matches!(lines.last(), None | Some(Line::Blank)) {
+                    lines.push(Line::Blank);
+                }
+            },
+            ASTNode::Comment(text) => {
+                let trailing = start > 0 && tokens[start - 1] != Token::Newline;
+                match lines.last_mut() {
+                    Some(Line::Code { comment: comment @ None, .. }) if trailing => {
+                        *comment = Some(format!(";{}", text));
+                    },
+                    _ => {
+                        // Comments written at the margin stay there, the rest follow the code
+                        let column = positions[start].1;
+                        let indent = if column == 1 { &margin } else { &indent };
+                        lines.push(Line::Comment(format!("{};{}", indent, text)));
+                    },
+                }
+            },
+            ASTNode::Label(name) => lines.push(Line::Code { text: format!("{}{}:", margin, name), comment: None }),
+            ASTNode::Directive(name) => lines.push(Line::Code { text: format!("{}#{}", margin, name), comment: None }),
+            ASTNode::Macro(MacroNode::EndCount { .. }) => {
+                depth = depth.saturating_sub(1);
+                let indent = " ".repeat((depth + 1) * INDENT);
+                lines.push(Line::Code { text: format!("{}}}", indent), comment: None });
+            },
+            _ => {
+                let statement = &statements[index];
+                lines.push(Line::Code { text: render_statement(statement, &indent, mnemonic_width), comment: None });
+
+                if statement.iter().any(|token| **token == Token::OpenBrace) {
+                    depth += 1;
+                }
+            },
+        }
+    }
+
+    while matches!(lines.last(), Some(Line::Blank)) {
+        lines.pop();
+    }
+
+    Ok(join(&lines))
+}
+
+// Writes the lines out, aligning trailing comments one space past the longest commented
+// line of each block
+fn join(lines: &[Line]) -> String {
+    let mut output = String::new();
+
+    for block in lines.split(|line| matches!(line, Line::Blank)) {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        let column = block
+            .iter()
+            .filter_map(|line| match line {
+                Line::Code { text, comment: Some(_) } => Some(last_line_width(text)),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        for line in block {
+            match line {
+                Line::Code { text, comment: Some(comment) } => {
+                    let padding = column - last_line_width(text) + 1;
+                    output.push_str(&format!("{}{}{}\n", text, " ".repeat(padding), comment));
+                },
+                Line::Code { text, comment: None } | Line::Comment(text) => {
+                    output.push_str(text);
+                    output.push('\n');
+                },
+                Line::Blank => (),
+            }
+        }
+    }
+
+    output
+}
+
+fn last_line_width(text: &str) -> usize {
+    text.lines().last().unwrap_or("").chars().count()
+}
+
+// A mnemonic padded out to the operand column, then its operands separated by spaces
+fn render_statement(tokens: &[&Token], indent: &str, mnemonic_width: usize) -> String {
+    let Some((first, rest)) = tokens.split_first() else {
+        return String::new()
+    };
+
+    let mnemonic = word(first);
+    let mut text = format!("{}{:<width$}", indent, mnemonic, width = mnemonic_width);
+    let mut tokens = rest.iter().peekable();
+    let mut separator = " ";
+
+    while let Some(token) = tokens.next() {
+        let piece = match token {
+            Token::Comma => continue,
+            Token::OpenParen => {
+                let mut keys = Vec::new();
+                for token in tokens.by_ref() {
+                    match token {
+                        Token::ModeKey(key) => keys.push(key.trim().to_uppercase()),
+                        Token::CloseParen => break,
+                        _ => (),
+                    }
+                }
+                format!("({})", keys.join(", "))
+            },
+            Token::Quote => {
+                let mut string = String::new();
+                for token in tokens.by_ref() {
+                    match token {
+                        Token::String(value) => string.push_str(value),
+                        Token::Quote => break,
+                        _ => (),
+                    }
+                }
+                format!("\"{}\"", string)
+            },
+            Token::OpenBracket => {
+                let mut elements = Vec::new();
+                for token in tokens.by_ref() {
+                    match token {
+                        Token::Element(element) => elements.push(element_word(element)),
+                        Token::CloseBracket => break,
+                        _ => (),
+                    }
+                }
+
+                text.push_str(separator);
+                wrap_array(&mut text, &elements);
+                separator = " ";
+                continue
+            },
+            token => word(token),
+        };
+
+        text.push_str(separator);
+        text.push_str(&piece);
+        separator = " ";
+    }
+
+    text.trim_end().to_string()
+}
+
+// `[a, b, c]` on one line when it fits, otherwise continued under the first element
+fn wrap_array(text: &mut String, elements: &[String]) {
+    text.push('[');
+    let column = last_line_width(text);
+
+    for (index, element) in elements.iter().enumerate() {
+        if index > 0 {
+            text.push(',');
+            let next_width = last_line_width(text) + 1 + element.chars().count() + 1;
+            if next_width > MAXIMUM_WIDTH {
+                text.push('\n');
+                text.push_str(&" ".repeat(column));
+            } else {
+                text.push(' ');
+            }
+        }
+        text.push_str(element);
+    }
+
+    text.push(']');
+}
+
+// A single token written the canonical way
+fn word(token: &Token) -> String {
+    match token {
+        Token::Identifier(text) => text.to_uppercase(),
+        Token::Directive(text) => format!("#{}", text.to_uppercase()),
+        Token::LabelHeader(text) => format!("{}:", text.to_uppercase()),
+        Token::JumpLabel(text) => format!(":{}", text.to_uppercase()),
+        Token::DirectAddress(text) => format!("${}", name(text)),
+        Token::IndirectAddress(text) => format!("@{}", name(text)),
+        Token::StackOffset(text) => match text.strip_prefix('-') {
+            Some(digits) => format!("%-{}", name(digits)),
+            None => format!("%{}", name(text)),
+        },
+        Token::HexNumber(digits) => format!("0x{}", digits.to_uppercase()),
+        Token::OctalNumber(digits) => format!("0o{}", digits),
+        Token::BinaryNumber(digits) => format!("0b{}", digits),
+        Token::DecimalNumber(digits) | Token::String(digits) | Token::ModeKey(digits) => digits.trim().to_string(),
+        Token::Element(element) => element_word(element),
+        Token::Error { snippet, .. } => snippet.to_string(),
+        Token::OpenBrace => "{".to_string(),
+        Token::CloseBrace => "}".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn element_word(element: &str) -> String {
+    let element = element.trim();
+
+    if let Some(target) = element.strip_prefix('$') {
+        format!("${}", name(target))
+    } else if let Some(label) = element.strip_prefix(':') {
+        format!(":{}", label.trim().to_uppercase())
+    } else if let Some((key, value)) = element.split_once('=') {
+        format!("{}={}", key.trim().to_uppercase(), name(value))
+    } else {
+        name(element)
+    }
+}
+
+// Names are upper case; numbers keep a lower case radix prefix and upper case digits.
+// `BASE+I` indexed names are handled a part at a time.
+fn name(text: &str) -> String {
+    text.trim()
+        .split('+')
+        .map(|part| {
+            let part = part.trim().to_uppercase();
+            if Parser::normalize_number(&part).is_err() {
+                return part
+            }
+
+            match part.get(..2) {
+                Some(prefix @ ("0X" | "0O" | "0B")) => format!("{}{}", prefix.to_lowercase(), &part[2..]),
+                _ => part,
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("+")
+}
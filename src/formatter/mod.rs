@@ -0,0 +1,6 @@
+mod core;
+
+#[cfg(test)]
+mod test;
+
+pub use core::format;
@@ -0,0 +1,87 @@
+use crate::assembler::encoder::{Encoder, program::Program, symbol_table::SymbolTable};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::formatter::format;
+
+const MESSY: &str = "\
+; Counts down from ten
+VAR $0x10 count
+
+
+start:   ; entry point
+  load 0xa a    ; ten
+loop: dec a
+    load   a $count
+  add (r,r) b a
+zero {
+inc b
+}
+      ; done
+  jump :loop
+  halt
+";
+
+fn assemble(source: &str) -> Program {
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    Encoder::encode(&parser.instructions, &mut table).unwrap()
+}
+
+#[test]
+fn rewrites_source_in_canonical_style() {
+    assert_eq!(format(MESSY).unwrap(), "\
+; Counts down from ten
+    VAR  $0x10 COUNT
+
+START:         ; entry point
+    LOAD 0xA A ; ten
+LOOP:
+    DEC  A
+    LOAD A $COUNT
+    ADD  (R, R) B A
+    ZERO {
+        INC  B
+    }
+    ; done
+    JUMP :LOOP
+    HALT
+");
+}
+
+#[test]
+fn formatting_is_stable_and_keeps_the_program() {
+    let formatted = format(MESSY).unwrap();
+
+    assert_eq!(format(&formatted).unwrap(), formatted);
+    assert_eq!(assemble(&formatted), assemble(MESSY));
+}
+
+#[test]
+fn wraps_long_arrays_under_their_first_element() {
+    let elements: Vec<String> = (100..130).map(|value| value.to_string()).collect();
+    let source = format!("ARRAY $0x20 [{}]\nHALT\n", elements.join(","));
+    let formatted = format(&source).unwrap();
+
+    let lines: Vec<&str> = formatted.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines.iter().all(|line| line.chars().count() <= 80));
+    assert!(lines[1].starts_with(&" ".repeat(17)));
+    assert_eq!(assemble(&formatted), assemble(&source));
+}
+
+#[test]
+fn keeps_strings_and_number_spelling() {
+    assert_eq!(
+        format("string $0X40 \"Hi, there\"\nlOaD 0B101 @ptr\nload %-0X2 a\n").unwrap(),
+        "    STRING $0x40 \"Hi, there\"\n    LOAD   0b101 @PTR\n    LOAD   %-0x2 A\n"
+    );
+}
+
+#[test]
+fn refuses_source_the_lexer_cannot_read() {
+    assert!(format("  LOAD 0x A\n").is_err());
+    assert!(format("  LOAD ? A\n").is_err());
+}
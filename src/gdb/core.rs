@@ -0,0 +1,399 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+use std::net::{TcpListener, TcpStream};
+
+use crate::chiiko::Chiiko;
+use crate::chiiko::components::chip::Chip;
+use crate::gdb::packet::{Packet, read_packet, write_packet};
+
+const STEPS_PER_POLL: usize = 10_000; // Instructions run between checks for Ctrl-C
+const SIGNAL_TRAP: u8 = 5;
+const SIGNAL_SEGMENTATION_FAULT: u8 = 11;
+const MAXIMUM_PACKET_SIZE: usize = 0x1000;
+
+// Registers in the order of `g` packets and `p`/`P` numbers, with their widths in bytes.
+// Values are sent in the machine's big endian byte order.
+const REGISTERS: &[(&str, usize)] = &[
+    ("a", 1), ("b", 1), ("c", 1), ("h", 1), ("l", 1), ("i", 1), ("j", 1),
+    ("status", 1), ("sp", 2), ("fp", 2), ("pc", 2),
+];
+
+// What the server loop should do after a packet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Reply(String),
+    Continue,      // Run until a breakpoint, the end of the program or an interrupt
+    Detach(String), // Reply, then close the connection
+    Kill,
+}
+
+// Answers GDB remote serial protocol packets for one machine. Breakpoints are kept here
+// rather than patched into memory, since most of the program lives in ROM.
+pub struct Stub {
+    chiiko: Chiiko,
+    breakpoints: BTreeSet<u16>,
+    acknowledge: bool,
+}
+
+impl Stub {
+    pub fn new(chiiko: Chiiko) -> Self {
+        Self {
+            chiiko,
+            breakpoints: BTreeSet::new(),
+            acknowledge: true,
+        }
+    }
+
+    pub fn handle(&mut self, packet: &str) -> Action {
+        let (command, arguments) = packet.split_at(packet.chars().next().map_or(0, |first| first.len_utf8()));
+
+        let reply = match command {
+            "?" => self.stop_reply(SIGNAL_TRAP),
+            "g" => self.read_registers(),
+            "G" => self.write_registers(arguments),
+            "p" => self.read_register(arguments),
+            "P" => self.write_register(arguments),
+            "m" => self.read_memory(arguments),
+            "M" => self.write_memory(arguments),
+            "Z" | "z" => self.breakpoint(command == "Z", arguments),
+            "s" => {
+                self.jump(arguments);
+                self.step()
+            },
+            "c" => {
+                self.jump(arguments);
+                return Action::Continue
+            },
+            "H" | "T" => "OK".to_string(), // There is only one thread
+            "D" => return Action::Detach("OK".to_string()),
+            "k" => return Action::Kill,
+            _ => self.query(packet),
+        };
+
+        Action::Reply(reply)
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return format!("PacketSize={:x};qXfer:features:read+;QStartNoAckMode+;swbreak+", MAXIMUM_PACKET_SIZE)
+        }
+
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, length)) = range.split_once(',').and_then(|(offset, length)| {
+                Some((usize::from_str_radix(offset, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+            }) else {
+                return error(1)
+            };
+
+            let description = target_description();
+            let chunk: String = description.chars().skip(offset).take(length).collect();
+            let more = offset + length < description.len();
+            return format!("{}{}", if more { "m" } else { "l" }, chunk)
+        }
+
+        match packet {
+            "QStartNoAckMode" => {
+                self.acknowledge = false;
+                "OK".to_string()
+            },
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            _ => String::new(), // An empty reply tells GDB a packet is not supported
+        }
+    }
+
+    // `S` with a signal number while the machine can go on, `W` with the exit code once it
+    // has halted
+    fn stop_reply(&self, signal: u8) -> String {
+        match self.chiiko.is_halted() {
+            true => format!("W{:02x}", self.chiiko.cpu.exit_code.unwrap_or(0)),
+            false => format!("S{:02x}", signal),
+        }
+    }
+
+    fn step(&mut self) -> String {
+        if self.chiiko.is_halted() {
+            return self.stop_reply(SIGNAL_TRAP)
+        }
+
+        match self.chiiko.step() {
+            Ok(()) => self.stop_reply(SIGNAL_TRAP),
+            Err(fault) => {
+                eprintln!("Machine fault: {}", fault);
+                self.stop_reply(SIGNAL_SEGMENTATION_FAULT)
+            },
+        }
+    }
+
+    // Runs at most `limit` instructions. Returns the stop reply once the machine stops, or
+    // nothing when it is still running.
+    pub fn resume(&mut self, limit: usize) -> Option<String> {
+        for _ in 0..limit {
+            if self.chiiko.is_halted() {
+                return Some(self.stop_reply(SIGNAL_TRAP))
+            }
+
+            if let Err(fault) = self.chiiko.step() {
+                eprintln!("Machine fault: {}", fault);
+                return Some(self.stop_reply(SIGNAL_SEGMENTATION_FAULT))
+            }
+
+            if self.breakpoints.contains(&self.chiiko.cpu.program_counter) || self.chiiko.is_halted() {
+                return Some(self.stop_reply(SIGNAL_TRAP))
+            }
+        }
+
+        None
+    }
+
+    pub fn interrupt(&self) -> String {
+        self.stop_reply(SIGNAL_TRAP)
+    }
+
+    // `s` and `c` may name the address to resume from
+    fn jump(&mut self, address: &str) {
+        if let Ok(address) = u16::from_str_radix(address, 16) {
+            self.chiiko.cpu.set_pc(address);
+        }
+    }
+
+    fn register_value(&self, number: usize) -> u16 {
+        let cpu = &self.chiiko.cpu;
+
+        match number {
+            0..=6 => cpu.read_register(number as u8).unwrap_or(0) as u16,
+            7 => cpu.status as u16,
+            8 => cpu.stack_pointer(),
+            9 => cpu.frame_pointer(),
+            _ => cpu.program_counter,
+        }
+    }
+
+    fn set_register_value(&mut self, number: usize, value: u16) {
+        let cpu = &mut self.chiiko.cpu;
+
+        match number {
+            0..=6 => {
+                let _ = cpu.write_register(number as u8, value as u8);
+            },
+            7 => cpu.status = value as u8,
+            8 => cpu.set_stack_pointer(value),
+            9 => cpu.set_frame_pointer(value),
+            _ => cpu.set_pc(value),
+        }
+    }
+
+    fn read_registers(&self) -> String {
+        (0..REGISTERS.len()).map(|number| self.encode_register(number)).collect()
+    }
+
+    fn write_registers(&mut self, hex: &str) -> String {
+        let Some(bytes) = decode_hex(hex) else {
+            return error(1)
+        };
+        if bytes.len() != REGISTERS.iter().map(|(_, width)| width).sum() {
+            return error(1)
+        }
+
+        let mut offset = 0;
+        for (number, (_, width)) in REGISTERS.iter().enumerate() {
+            let value = bytes[offset..offset + width].iter().fold(0, |value, byte| value << 8 | *byte as u16);
+            self.set_register_value(number, value);
+            offset += width;
+        }
+
+        "OK".to_string()
+    }
+
+    fn read_register(&self, number: &str) -> String {
+        match usize::from_str_radix(number, 16) {
+            Ok(number) if number < REGISTERS.len() => self.encode_register(number),
+            _ => error(1),
+        }
+    }
+
+    fn write_register(&mut self, arguments: &str) -> String {
+        let parsed = arguments.split_once('=').and_then(|(number, value)| {
+            let number = usize::from_str_radix(number, 16).ok().filter(|number| *number < REGISTERS.len())?;
+            let bytes = decode_hex(value).filter(|bytes| bytes.len() == REGISTERS[number].1)?;
+            Some((number, bytes.iter().fold(0, |value, byte| value << 8 | *byte as u16)))
+        });
+
+        match parsed {
+            Some((number, value)) => {
+                self.set_register_value(number, value);
+                "OK".to_string()
+            },
+            None => error(1),
+        }
+    }
+
+    fn encode_register(&self, number: usize) -> String {
+        let value = self.register_value(number);
+
+        match REGISTERS[number].1 {
+            1 => format!("{:02x}", value as u8),
+            _ => format!("{:04x}", value),
+        }
+    }
+
+    fn read_memory(&self, arguments: &str) -> String {
+        let Some((address, length)) = address_and_length(arguments) else {
+            return error(1)
+        };
+
+        (0..length.min(MAXIMUM_PACKET_SIZE / 2) as u16)
+            .map(|offset| format!("{:02x}", self.chiiko.cpu.read(address.wrapping_add(offset))))
+            .collect()
+    }
+
+    fn write_memory(&mut self, arguments: &str) -> String {
+        let Some((range, data)) = arguments.split_once(':') else {
+            return error(1)
+        };
+        let (Some((address, length)), Some(bytes)) = (address_and_length(range), decode_hex(data)) else {
+            return error(1)
+        };
+        if bytes.len() != length {
+            return error(1)
+        }
+
+        for (offset, byte) in bytes.iter().enumerate() {
+            if self.chiiko.cpu.write(address.wrapping_add(offset as u16), *byte).is_err() {
+                return error(14) // EFAULT
+            }
+        }
+
+        "OK".to_string()
+    }
+
+    // Software (0) and hardware (1) breakpoints are the same thing here
+    fn breakpoint(&mut self, insert: bool, arguments: &str) -> String {
+        let mut fields = arguments.split(',');
+        let (Some(kind), Some(address)) = (fields.next(), fields.next()) else {
+            return error(1)
+        };
+        if kind != "0" && kind != "1" {
+            return String::new()
+        }
+        let Ok(address) = u16::from_str_radix(address, 16) else {
+            return error(1)
+        };
+
+        match insert {
+            true => self.breakpoints.insert(address),
+            false => self.breakpoints.remove(&address),
+        };
+        "OK".to_string()
+    }
+}
+
+// Describes the register file so GDB can show it by name
+fn target_description() -> String {
+    let registers: String = REGISTERS
+        .iter()
+        .map(|(name, width)| {
+            let kind = match *name {
+                "pc" => "code_ptr",
+                "sp" | "fp" => "data_ptr",
+                _ => "uint8",
+            };
+            format!("<reg name=\"{}\" bitsize=\"{}\" type=\"{}\"/>", name, width * 8, kind)
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+        <target version=\"1.0\"><feature name=\"org.chiiko.cpu\">{}</feature></target>",
+        registers
+    )
+}
+
+fn address_and_length(arguments: &str) -> Option<(u16, usize)> {
+    let (address, length) = arguments.split_once(',')?;
+    Some((u16::from_str_radix(address, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+fn error(number: u8) -> String {
+    format!("E{:02x}", number)
+}
+
+// Waits for one debugger on `listener` and serves it until it detaches or disconnects
+pub fn serve(listener: &TcpListener, mut stub: Stub) -> io::Result<()> {
+    let (stream, _) = listener.accept()?;
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    while let Some(packet) = read_packet(&mut reader, &mut writer, stub.acknowledge)? {
+        let action = match packet {
+            Packet::Interrupt => Action::Reply(stub.interrupt()),
+            Packet::Command(command) => stub.handle(&command),
+        };
+
+        match action {
+            Action::Reply(reply) => write_packet(&mut writer, &reply)?,
+            Action::Continue => {
+                let reply = loop {
+                    if let Some(reply) = stub.resume(STEPS_PER_POLL) {
+                        break reply
+                    }
+                    if interrupted(&mut reader)? {
+                        break stub.interrupt()
+                    }
+                };
+                write_packet(&mut writer, &reply)?;
+            },
+            Action::Detach(reply) => {
+                write_packet(&mut writer, &reply)?;
+                break
+            },
+            Action::Kill => break,
+        }
+    }
+
+    Ok(())
+}
+
+// Whether Ctrl-C arrived while the machine was running. Anything else waiting is left for
+// the packet reader. The reader may already hold the byte, so its buffer is checked before
+// the socket.
+fn interrupted(reader: &mut BufReader<TcpStream>) -> io::Result<bool> {
+    if let Some(byte) = reader.buffer().first() {
+        let interrupt = *byte == 0x03;
+        if interrupt {
+            reader.consume(1);
+        }
+        return Ok(interrupt)
+    }
+
+    let stream = reader.get_ref();
+    stream.set_nonblocking(true)?;
+    let mut byte = [0];
+    let result = stream.peek(&mut byte);
+    stream.set_nonblocking(false)?;
+
+    match result {
+        Ok(1) if byte[0] == 0x03 => {
+            reader.read_exact(&mut byte)?;
+            Ok(true)
+        },
+        Ok(0) => Ok(true), // The debugger has gone away
+        Ok(_) => Ok(false),
+        Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(false),
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,7 @@
+mod core;
+pub mod packet;
+
+#[cfg(test)]
+mod test;
+
+pub use core::{Stub, serve};
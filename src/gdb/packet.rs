@@ -0,0 +1,100 @@
+use std::io::{self, BufRead, Write};
+
+const INTERRUPT: u8 = 0x03;
+const ESCAPE: u8 = b'}';
+
+// What a debugger sent: a command packet, or the single byte it sends for Ctrl-C
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    Command(String),
+    Interrupt,
+}
+
+// Reads the next packet, skipping acknowledgements. Packets are framed as `$data#xx` where
+// `xx` is the sum of the data bytes modulo 256 in hex. Each one is acknowledged with `+`,
+// or `-` to ask for it again when the checksum is wrong, until no-ack mode is agreed.
+// Returns `None` when the connection has closed.
+pub fn read_packet(reader: &mut impl BufRead, writer: &mut impl Write, acknowledge: bool) -> io::Result<Option<Packet>> {
+    loop {
+        let Some(byte) = read_byte(reader)? else {
+            return Ok(None)
+        };
+
+        match byte {
+            INTERRUPT => return Ok(Some(Packet::Interrupt)),
+            b'$' => (),
+            _ => continue, // Acknowledgements and noise between packets
+        }
+
+        let mut data = Vec::new();
+        reader.read_until(b'#', &mut data)?;
+        if data.pop() != Some(b'#') {
+            return Ok(None)
+        }
+
+        let mut checksum = [0; 2];
+        reader.read_exact(&mut checksum)?;
+        let expected = std::str::from_utf8(&checksum).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        if expected != Some(self::checksum(&data)) {
+            if acknowledge {
+                writer.write_all(b"-")?;
+                writer.flush()?;
+            }
+            continue
+        }
+
+        if acknowledge {
+            writer.write_all(b"+")?;
+            writer.flush()?;
+        }
+        return Ok(Some(Packet::Command(String::from_utf8_lossy(&unescape(&data)).to_string())))
+    }
+}
+
+pub fn write_packet(writer: &mut impl Write, data: &str) -> io::Result<()> {
+    let escaped = escape(data.as_bytes());
+    write!(writer, "${}#{:02x}", String::from_utf8_lossy(&escaped), checksum(&escaped))?;
+    writer.flush()
+}
+
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum: u8, byte| sum.wrapping_add(*byte))
+}
+
+// `}` escapes the framing characters as the byte XOR 0x20
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+
+    for byte in data {
+        if matches!(*byte, b'$' | b'#' | b'*' | ESCAPE) {
+            escaped.extend([ESCAPE, byte ^ 0x20]);
+        } else {
+            escaped.push(*byte);
+        }
+    }
+
+    escaped
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut bytes = data.iter();
+    let mut unescaped = Vec::with_capacity(data.len());
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            &ESCAPE => unescaped.extend(bytes.next().map(|byte| byte ^ 0x20)),
+            _ => unescaped.push(*byte),
+        }
+    }
+
+    unescaped
+}
+
+fn read_byte(reader: &mut impl BufRead) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match reader.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
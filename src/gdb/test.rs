@@ -0,0 +1,152 @@
+use std::io::{BufReader, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use crate::chiiko::{Chiiko, MachineProfile};
+use crate::gdb::core::Action;
+use crate::gdb::packet::{Packet, read_packet, write_packet};
+use crate::gdb::{Stub, serve};
+
+// INC B, INC B, HALT
+const PROGRAM: &[u8] = &[0x85, 0x2A, 0x01, 0x85, 0x2A, 0x01, 0x70];
+
+fn stub() -> Stub {
+    let mut rom = vec![0; 0x8000];
+    rom[..PROGRAM.len()].copy_from_slice(PROGRAM);
+    Stub::new(Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap())
+}
+
+fn reply(stub: &mut Stub, packet: &str) -> String {
+    match stub.handle(packet) {
+        Action::Reply(reply) => reply,
+        action => panic!("Expected a reply to {}, got {:?}", packet, action),
+    }
+}
+
+#[test]
+fn frames_packets_with_checksums_and_escapes() {
+    let mut written = Vec::new();
+    write_packet(&mut written, "a}b#").unwrap();
+    assert_eq!(written, b"$a}]b}\x03#1d");
+
+    let mut acknowledgements = Vec::new();
+    let mut input = Cursor::new(b"+$a}]b}\x03#1d$g#00$g#67\x03".to_vec());
+    let mut next = || read_packet(&mut input, &mut acknowledgements, true).unwrap();
+
+    assert_eq!(next(), Some(Packet::Command("a}b#".to_string())));
+    assert_eq!(next(), Some(Packet::Command("g".to_string())));
+    assert_eq!(next(), Some(Packet::Interrupt));
+    assert_eq!(next(), None);
+    assert_eq!(acknowledgements, b"+-+");
+}
+
+#[test]
+fn reads_and_writes_registers() {
+    let mut stub = stub();
+
+    assert_eq!(reply(&mut stub, "g"), "00000000000000001fff1fff8000");
+    assert_eq!(reply(&mut stub, "P1=2a"), "OK");
+    assert_eq!(reply(&mut stub, "Pa=8003"), "OK");
+    assert_eq!(reply(&mut stub, "p1"), "2a");
+    assert_eq!(reply(&mut stub, "pa"), "8003");
+    assert_eq!(reply(&mut stub, "p1f"), "E01");
+
+    assert_eq!(reply(&mut stub, "G0102030405060780123412348001"), "OK");
+    assert_eq!(reply(&mut stub, "p6"), "07");
+    assert_eq!(reply(&mut stub, "p7"), "80");
+    assert_eq!(reply(&mut stub, "pa"), "8001");
+}
+
+#[test]
+fn reads_and_writes_memory() {
+    let mut stub = stub();
+
+    assert_eq!(reply(&mut stub, "m8000,3"), "852a01");
+    assert_eq!(reply(&mut stub, "M0010,2:beef"), "OK");
+    assert_eq!(reply(&mut stub, "m0010,2"), "beef");
+    assert_eq!(reply(&mut stub, "M0010,2:be"), "E01");
+}
+
+#[test]
+fn steps_and_continues_to_breakpoints() {
+    let mut stub = stub();
+
+    assert_eq!(reply(&mut stub, "s"), "S05");
+    assert_eq!(reply(&mut stub, "p1"), "01");
+
+    assert_eq!(reply(&mut stub, "Z0,8006,1"), "OK");
+    assert_eq!(stub.handle("c"), Action::Continue);
+    assert_eq!(stub.resume(100), Some("S05".to_string()));
+    assert_eq!(reply(&mut stub, "pa"), "8006");
+
+    assert_eq!(reply(&mut stub, "z0,8006,1"), "OK");
+    stub.handle("c");
+    assert_eq!(stub.resume(100), Some("W00".to_string()));
+    assert_eq!(reply(&mut stub, "?"), "W00");
+}
+
+#[test]
+fn describes_the_target() {
+    let mut stub = stub();
+
+    assert!(reply(&mut stub, "qSupported:multiprocess+").contains("qXfer:features:read+"));
+    let description = reply(&mut stub, "qXfer:features:read:target.xml:0,1000");
+    assert!(description.starts_with("l<?xml"));
+    assert!(description.contains("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>"));
+    assert_eq!(reply(&mut stub, "qXfer:features:read:target.xml:0,5"), "m<?xml");
+    assert_eq!(reply(&mut stub, "vMustReplyEmpty"), "");
+}
+
+#[test]
+fn serves_a_debugger_over_loopback() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || serve(&listener, stub()));
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+    let mut exchange = |packet: &str| {
+        write_packet(&mut client, packet).unwrap();
+        let mut acknowledgement = [0];
+        reader.read_exact(&mut acknowledgement).unwrap();
+        assert_eq!(&acknowledgement, b"+");
+        let Some(Packet::Command(reply)) = read_packet(&mut reader, &mut Vec::new(), false).unwrap() else {
+            panic!("No reply to {}", packet)
+        };
+        client.write_all(b"+").unwrap();
+        reply
+    };
+
+    assert_eq!(exchange("?"), "S05");
+    assert_eq!(exchange("Z0,8003,1"), "OK");
+    assert_eq!(exchange("c"), "S05");
+    assert_eq!(exchange("p1"), "01");
+    assert_eq!(exchange("c"), "W00");
+    assert_eq!(exchange("D"), "OK");
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn interrupt_sent_with_continue_stops_the_machine() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    // LOOP: JUMP :LOOP
+    let mut rom = vec![0; 0x8000];
+    rom[..3].copy_from_slice(&[0x32, 0x80, 0x00]);
+    let stub = Stub::new(Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap());
+    let server = thread::spawn(move || serve(&listener, stub));
+
+    // Both arrive together, so the server's reader buffers the Ctrl-C with the packet
+    let mut client = TcpStream::connect(address).unwrap();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+    client.write_all(b"$c#63\x03").unwrap();
+
+    let mut acknowledgement = [0];
+    reader.read_exact(&mut acknowledgement).unwrap();
+    let reply = read_packet(&mut reader, &mut Vec::new(), false).unwrap();
+    assert_eq!(reply, Some(Packet::Command("S05".to_string())));
+
+    client.write_all(b"+").unwrap();
+    write_packet(&mut client, "k").unwrap();
+    server.join().unwrap().unwrap();
+}
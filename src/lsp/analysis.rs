@@ -0,0 +1,276 @@
+use crate::assembler::assembly_error::AssemblyError;
+use crate::assembler::encoder::{Encoder, symbol_table::Symbol, symbol_table::SymbolTable,
+    syntax_checker::SyntaxChecker,
+};
+use crate::assembler::lexer::{Lexer, token::Token};
+use crate::assembler::linter::{self, warning::Lint};
+use crate::assembler::parser::{Parser, assembler_operand::AssemblerOperand, ast_node::ASTNode,
+    ast_node::MacroNode,
+};
+use crate::chiiko::MachineProfile;
+use crate::operation::Operation;
+
+// A stretch of one line. Lines and columns count from 0, as editors expect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && self.start <= column && column <= self.end
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+    pub lint: Option<Lint>, // Set for warnings, which do not stop the program assembling
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DefinitionKind {
+    Label,
+    Variable,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub span: Span,
+    pub address: Option<u16>, // Known once the program lays out without errors
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WordKind {
+    Mnemonic,
+    Symbol,
+    Register,
+    ModeKey,
+}
+
+// A token an editor can ask about. `text` is upper case, without its sigil.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    pub span: Span,
+    pub text: String,
+    pub kind: WordKind,
+}
+
+// Everything the language server knows about one version of a document
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub definitions: Vec<Definition>,
+    pub words: Vec<Word>,
+}
+
+impl Analysis {
+    pub fn of(text: &str) -> Self {
+        let mut analysis = Self::default();
+
+        let mut lexer = Lexer::new(text);
+        let tokens = lexer.lex();
+        let spans: Vec<Span> = tokens
+            .iter()
+            .zip(lexer.positions())
+            .map(|(token, (line, column))| {
+                Span { line: line - 1, start: column - 1, end: column - 1 + token.width() }
+            })
+            .collect();
+
+        let mut parser = Parser::new(tokens.clone());
+        parser.parse();
+
+        analysis.collect_words(&tokens, &spans, &parser.node_starts);
+
+        for (token, span) in tokens.iter().zip(&spans) {
+            if let Token::Error { message, .. } = token {
+                analysis.diagnostics.push(Diagnostic { span: *span, message: message.to_string(), lint: None });
+            }
+        }
+
+        for (index, node) in parser.instructions.iter().enumerate() {
+            let start = parser.node_starts[index];
+            analysis.collect_definitions(node, &tokens[start..], &spans[start..]);
+
+            if let Err(error) = SyntaxChecker::check(vec![node.clone()]) {
+                let span = Self::line_span(&tokens[start..], &spans[start..]);
+                analysis.diagnostics.push(Diagnostic { span, message: error.to_string(), lint: None });
+            }
+        }
+
+        if analysis.diagnostics.is_empty() {
+            analysis.resolve(&parser.instructions);
+        }
+
+        for warning in linter::lint(text, &MachineProfile::default()) {
+            let line = warning.line - 1;
+            analysis.diagnostics.push(Diagnostic {
+                span: Self::code_span(line, &tokens, &spans),
+                message: warning.message,
+                lint: Some(warning.lint),
+            });
+        }
+
+        analysis
+    }
+
+    pub fn word_at(&self, line: usize, column: usize) -> Option<&Word> {
+        self.words.iter().find(|word| word.span.contains(line, column))
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|definition| definition.name == name)
+    }
+
+    // Lays the program out to find symbol addresses and the errors only the encoder sees
+    fn resolve(&mut self, ast: &[ASTNode]) {
+        let mut table = SymbolTable::from_ast(&ast.to_vec());
+
+        if let Err(error) = Encoder::encode(ast, &mut table) {
+            let span = self.span_of_error(&error);
+            self.diagnostics.push(Diagnostic { span, message: error.to_string(), lint: None });
+        }
+
+        for definition in &mut self.definitions {
+            definition.address = match table.table.get(&definition.name) {
+                Some(Symbol::Address(address)) | Some(Symbol::Variable { address, .. }) => Some(*address),
+                _ => None,
+            };
+        }
+    }
+
+    fn span_of_error(&self, error: &AssemblyError) -> Span {
+        let name = match error {
+            AssemblyError::UndefinedSymbol(name) | AssemblyError::InvalidRegister(name) |
+            AssemblyError::InvalidOperand(name) | AssemblyError::ValueOutOfRange(name) => name,
+            _ => return Span { line: 0, start: 0, end: 0 },
+        };
+
+        self.words
+            .iter()
+            .find(|word| word.text == *name && word.kind != WordKind::Mnemonic)
+            .map_or(Span { line: 0, start: 0, end: 0 }, |word| word.span)
+    }
+
+    fn collect_words(&mut self, tokens: &[Token], spans: &[Span], node_starts: &[usize]) {
+        for (index, (token, span)) in tokens.iter().zip(spans).enumerate() {
+            let span = *span;
+            let (text, kind) = match token {
+                Token::Identifier(text) if node_starts.contains(&index) => {
+                    (text.to_uppercase(), WordKind::Mnemonic)
+                },
+                Token::Identifier(text) if text.len() < 3 => (text.to_uppercase(), WordKind::Register),
+                Token::Identifier(text) => (text.to_uppercase(), WordKind::Symbol),
+                Token::LabelHeader(text) | Token::JumpLabel(text) => {
+                    (text.to_uppercase(), WordKind::Symbol)
+                },
+                Token::DirectAddress(text) | Token::IndirectAddress(text) => {
+                    let base = text.split('+').next().unwrap_or(text).to_uppercase();
+                    if Parser::normalize_number(&base).is_ok() {
+                        continue
+                    }
+                    let kind = if base.len() < 3 { WordKind::Register } else { WordKind::Symbol };
+                    (base, kind)
+                },
+                Token::Element(text) => {
+                    let name = text.trim_start_matches(['$', ':']).split('=').next().unwrap_or(text);
+                    let name = name.trim().to_uppercase();
+                    if name.is_empty() || Parser::normalize_number(&name).is_ok() {
+                        continue
+                    }
+                    (name, WordKind::Symbol)
+                },
+                Token::ModeKey(text) => (text.trim().to_uppercase(), WordKind::ModeKey),
+                _ => continue,
+            };
+
+            self.words.push(Word { span, text, kind });
+        }
+    }
+
+    fn collect_definitions(&mut self, node: &ASTNode, tokens: &[Token], spans: &[Span]) {
+        let names: Vec<(String, DefinitionKind)> = match node {
+            ASTNode::Label(name) => vec![(name.to_string(), DefinitionKind::Label)],
+            ASTNode::Macro(MacroNode::VariableData { label, .. }) => label
+                .string()
+                .map(|name| vec![(name, DefinitionKind::Variable)])
+                .unwrap_or_default(),
+            ASTNode::Macro(MacroNode::ArrayData { elements, .. }) => elements
+                .iter()
+                .filter_map(|element| match element {
+                    AssemblerOperand::NamedElement { name, .. } | AssemblerOperand::Identifier(name) => {
+                        Some((name.to_string(), DefinitionKind::Variable))
+                    },
+                    _ => None,
+                })
+                .collect(),
+            _ => return,
+        };
+
+        // Arrays never contain newline tokens, so this covers every line of the node
+        let node_tokens: Vec<(&Token, &Span)> = tokens
+            .iter()
+            .zip(spans)
+            .take_while(|(token, _)| !matches!(token, Token::Newline | Token::EndOfFile))
+            .collect();
+
+        for (name, kind) in names {
+            let span = node_tokens
+                .iter()
+                .find(|(token, _)| match token {
+                    Token::LabelHeader(text) | Token::Identifier(text) => text.to_uppercase() == name,
+                    Token::Element(text) => text.split('=').next().unwrap_or(text).trim().to_uppercase() == name,
+                    _ => false,
+                })
+                .map_or(spans[0], |(_, span)| **span);
+
+            self.definitions.push(Definition { name, kind, span, address: None });
+        }
+    }
+
+    // The code on one line, leaving out any comment
+    fn code_span(line: usize, tokens: &[Token], spans: &[Span]) -> Span {
+        let code: Vec<&Span> = tokens
+            .iter()
+            .zip(spans)
+            .filter(|(token, span)| span.line == line && !matches!(token, Token::Comment(_) | Token::Newline))
+            .map(|(_, span)| span)
+            .collect();
+
+        Span {
+            line,
+            start: code.iter().map(|span| span.start).min().unwrap_or(0),
+            end: code.iter().map(|span| span.end).max().unwrap_or(0),
+        }
+    }
+
+    // From the first token of a node to the end of its line
+    fn line_span(tokens: &[Token], spans: &[Span]) -> Span {
+        let end = tokens
+            .iter()
+            .zip(spans)
+            .take_while(|(token, span)| span.line == spans[0].line && !matches!(token, Token::Newline))
+            .filter(|(token, _)| !matches!(token, Token::Comment(_)))
+            .map(|(_, span)| span.end)
+            .max()
+            .unwrap_or(spans[0].end);
+
+        Span { line: spans[0].line, start: spans[0].start, end }
+    }
+}
+
+// The names the first word of a line can take
+pub fn mnemonics() -> Vec<(&'static str, Option<Operation>)> {
+    let mut names: Vec<(&'static str, Option<Operation>)> = Operation::all()
+        .iter()
+        .flat_map(|operation| operation.mnemonics.iter().map(|mnemonic| (*mnemonic, Some(*operation))))
+        .collect();
+    names.extend(Operation::macro_mnemonics().iter().map(|mnemonic| (*mnemonic, None)));
+    names
+}
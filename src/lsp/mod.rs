@@ -0,0 +1,7 @@
+pub mod analysis;
+pub mod server;
+
+#[cfg(test)]
+mod test;
+
+pub use server::serve;
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::lsp::analysis::{self, Analysis, DefinitionKind, Span, WordKind};
+use crate::mode::Mode;
+use crate::operation::Operation;
+use crate::protocol::{json::Json, transport::read_message, transport::write_message};
+
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const FULL_SYNC: usize = 1;
+const ERROR_SEVERITY: usize = 1;
+const WARNING_SEVERITY: usize = 2;
+
+// Completion and symbol kinds from the protocol
+const KEYWORD_KIND: usize = 14;
+const ENUM_MEMBER_KIND: usize = 20;
+const FUNCTION_COMPLETION: usize = 3;
+const VARIABLE_COMPLETION: usize = 6;
+const FUNCTION_SYMBOL: usize = 12;
+const VARIABLE_SYMBOL: usize = 13;
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+// Answers language server requests for .ku files. Documents are synced in full on every
+// change, and are small enough to analyse again from scratch each time.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool,
+    exit_code: Option<u8>,
+}
+
+// Serves one editor session and returns the exit code the protocol asks for
+pub fn serve(mut input: impl BufRead, output: &mut impl Write) -> io::Result<u8> {
+    let mut server = Server::new();
+
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            write_message(output, &reply)?;
+        }
+
+        if let Some(code) = server.exit_code {
+            return Ok(code)
+        }
+    }
+
+    Ok(if server.shutdown_requested { 0 } else { 1 })
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Handles one incoming message and returns everything to send back for it
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let Some(method) = message.str("method") else {
+            return Vec::new() // Responses to requests we never make
+        };
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, &params)
+        };
+
+        let result = match method {
+            "initialize" => Self::capabilities(),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Json::Null
+            },
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/completion" => self.completion(&params),
+            "textDocument/documentSymbol" => self.document_symbols(&params),
+            _ => return vec![Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id),
+                ("error", Json::object([
+                    ("code", Json::Number(METHOD_NOT_FOUND)),
+                    ("message", format!("Unsupported method: {}", method).into()),
+                ])),
+            ])],
+        };
+
+        vec![Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)])]
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.str("textDocument.uri").unwrap_or_default().to_string();
+
+        let text = match method {
+            "textDocument/didOpen" => params.str("textDocument.text"),
+            "textDocument/didChange" => params.array("contentChanges").last().and_then(|change| change.str("text")),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![Self::diagnostics(&uri, &Analysis::default())]
+            },
+            "exit" => {
+                self.exit_code = Some(if self.shutdown_requested { 0 } else { 1 });
+                return Vec::new()
+            },
+            _ => None,
+        };
+
+        let Some(text) = text else {
+            return Vec::new()
+        };
+
+        let document = Document { text: text.to_string(), analysis: Analysis::of(text) };
+        let published = Self::diagnostics(&uri, &document.analysis);
+        self.documents.insert(uri, document);
+        vec![published]
+    }
+
+    fn capabilities() -> Json {
+        Json::object([
+            ("capabilities", Json::object([
+                ("textDocumentSync", FULL_SYNC.into()),
+                ("definitionProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                ("completionProvider", Json::object([
+                    ("triggerCharacters", vec![":".into(), "$".into(), "@".into(), "(".into()].into()),
+                ])),
+            ])),
+            ("serverInfo", Json::object([("name", "chiiko".into())])),
+        ])
+    }
+
+    fn diagnostics(uri: &str, analysis: &Analysis) -> Json {
+        let diagnostics = analysis.diagnostics
+            .iter()
+            .map(|diagnostic| match diagnostic.lint {
+                Some(lint) => Json::object([
+                    ("range", range(&diagnostic.span)),
+                    ("severity", WARNING_SEVERITY.into()),
+                    ("code", lint.code().into()),
+                    ("source", "chiiko".into()),
+                    ("message", diagnostic.message.as_str().into()),
+                ]),
+                None => Json::object([
+                    ("range", range(&diagnostic.span)),
+                    ("severity", ERROR_SEVERITY.into()),
+                    ("source", "chiiko".into()),
+                    ("message", diagnostic.message.as_str().into()),
+                ]),
+            })
+            .collect::<Vec<Json>>();
+
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())])),
+        ])
+    }
+
+    // The document and cursor position a request refers to
+    fn target<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document, usize, usize)> {
+        let uri = params.str("textDocument.uri")?;
+        let document = self.documents.get(uri)?;
+        let line = params.number("position.line")? as usize;
+        let character = params.number("position.character")? as usize;
+        Some((uri, document, line, character))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let Some((uri, document, line, character)) = self.target(params) else {
+            return Json::Null
+        };
+
+        document.analysis
+            .word_at(line, character)
+            .filter(|word| word.kind == WordKind::Symbol)
+            .and_then(|word| document.analysis.definition(&word.text))
+            .map_or(Json::Null, |definition| Json::object([
+                ("uri", uri.into()),
+                ("range", range(&definition.span)),
+            ]))
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let Some((_, document, line, character)) = self.target(params) else {
+            return Json::Null
+        };
+        let Some(word) = document.analysis.word_at(line, character) else {
+            return Json::Null
+        };
+
+        let text = match word.kind {
+            WordKind::Mnemonic => match Operation::lookup_mnemonic(&word.text) {
+                Some(operation) => {
+                    let mut text = format!(
+                        "**{}** opcode `{:#04X}`\n\nDefault mode `{}` (`{:#04X}`)",
+                        word.text, operation.opcode, mode_keys(operation.default_mode), operation.default_mode
+                    );
+                    if operation.mnemonics.len() > 1 {
+                        text.push_str(&format!("\n\nAlso written {}", operation.mnemonics.join(", ")));
+                    }
+                    text
+                },
+                None if Operation::is_macro(&word.text) => format!("**{}** assembler macro", word.text),
+                None => return Json::Null,
+            },
+            WordKind::Symbol => match document.analysis.definition(&word.text) {
+                Some(definition) => {
+                    let kind = match definition.kind {
+                        DefinitionKind::Label => "label",
+                        DefinitionKind::Variable => "variable",
+                    };
+                    match definition.address {
+                        Some(address) => format!("**{}** {} at `{:#06X}`", definition.name, kind, address),
+                        None => format!("**{}** {}, placed once the program assembles", definition.name, kind),
+                    }
+                },
+                None => return Json::Null,
+            },
+            WordKind::ModeKey => match Mode::lookup_key(&word.text) {
+                Some(mode) => format!("Mode key `{}`: {:?} operand, nibble `{:X}`", word.text, mode.group, mode.nibble),
+                None => return Json::Null,
+            },
+            WordKind::Register => return Json::Null,
+        };
+
+        Json::object([
+            ("contents", Json::object([("kind", "markdown".into()), ("value", text.into())])),
+            ("range", range(&word.span)),
+        ])
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let Some((_, document, line, character)) = self.target(params) else {
+            return Json::Array(Vec::new())
+        };
+
+        let prefix: String = document.text.lines().nth(line).unwrap_or("").chars().take(character).collect();
+        let word_start = prefix.rfind(|c: char| c.is_whitespace() || c == ',').map_or(0, |index| index + 1);
+        let (before, word) = prefix.split_at(word_start);
+
+        // Inside `(L, R)` only mode keys make sense
+        if prefix.rfind('(') > prefix.rfind(')') {
+            return Mode::all()
+                .iter()
+                .flat_map(|mode| mode.keys.iter().map(move |key| {
+                    completion_item(key, ENUM_MEMBER_KIND, &format!("{:?}", mode.group))
+                }))
+                .collect::<Vec<Json>>()
+                .into()
+        }
+
+        let symbols = |kind: Option<DefinitionKind>| -> Json {
+            document.analysis.definitions
+                .iter()
+                .filter(|definition| kind.is_none_or(|kind| definition.kind == kind))
+                .map(|definition| {
+                    let item_kind = match definition.kind {
+                        DefinitionKind::Label => FUNCTION_COMPLETION,
+                        DefinitionKind::Variable => VARIABLE_COMPLETION,
+                    };
+                    let detail = definition.address.map_or(String::new(), |address| format!("{:#06X}", address));
+                    completion_item(&definition.name, item_kind, &detail)
+                })
+                .collect::<Vec<Json>>()
+                .into()
+        };
+
+        if word.starts_with(':') {
+            symbols(Some(DefinitionKind::Label))
+        } else if word.starts_with('$') || word.starts_with('@') {
+            symbols(Some(DefinitionKind::Variable))
+        } else if before.trim().is_empty() || before.trim_end().ends_with(':') {
+            // The first word of a line, possibly after a label header
+            analysis::mnemonics()
+                .iter()
+                .map(|(mnemonic, operation)| {
+                    let detail = operation.map_or("macro".to_string(), |operation| {
+                        format!("default mode {}", mode_keys(operation.default_mode))
+                    });
+                    completion_item(mnemonic, KEYWORD_KIND, &detail)
+                })
+                .collect::<Vec<Json>>()
+                .into()
+        } else {
+            symbols(None)
+        }
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let Some(document) = params.str("textDocument.uri").and_then(|uri| self.documents.get(uri)) else {
+            return Json::Array(Vec::new())
+        };
+
+        document.analysis.definitions
+            .iter()
+            .map(|definition| {
+                let kind = match definition.kind {
+                    DefinitionKind::Label => FUNCTION_SYMBOL,
+                    DefinitionKind::Variable => VARIABLE_SYMBOL,
+                };
+                let detail = definition.address.map_or(String::new(), |address| format!("{:#06X}", address));
+
+                Json::object([
+                    ("name", definition.name.as_str().into()),
+                    ("detail", detail.into()),
+                    ("kind", kind.into()),
+                    ("range", range(&definition.span)),
+                    ("selectionRange", range(&definition.span)),
+                ])
+            })
+            .collect::<Vec<Json>>()
+            .into()
+    }
+}
+
+fn range(span: &Span) -> Json {
+    Json::object([
+        ("start", Json::object([("line", span.line.into()), ("character", span.start.into())])),
+        ("end", Json::object([("line", span.line.into()), ("character", span.end.into())])),
+    ])
+}
+
+fn completion_item(label: &str, kind: usize, detail: &str) -> Json {
+    Json::object([("label", label.into()), ("kind", kind.into()), ("detail", detail.into())])
+}
+
+// Mode bytes as they are written in source, e.g. `(R, A)`
+fn mode_keys(byte: u8) -> String {
+    let (left, right) = Mode::from_byte(byte);
+    format!("({}, {})", left.keys[0], right.keys[0])
+}
@@ -0,0 +1,143 @@
+use std::io::BufReader;
+
+use crate::lsp::{serve, server::Server};
+use crate::lsp::analysis::{Analysis, DefinitionKind, Span};
+use crate::protocol::{json::Json, transport::read_message, transport::write_message};
+
+const SOURCE: &str = "VAR $0x10 SCORE\nSTART:\n  LOAD $SCORE A\n  JUMP :START\n";
+const URI: &str = "file:///game.ku";
+
+fn request(id: usize, method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("id", id.into()), ("method", method.into()), ("params", params)])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)])
+}
+
+fn at(line: usize, character: usize) -> Json {
+    Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+        ("position", Json::object([("line", line.into()), ("character", character.into())])),
+    ])
+}
+
+fn open(server: &mut Server, text: &str) -> Vec<Json> {
+    server.handle(&notification("textDocument/didOpen", Json::object([
+        ("textDocument", Json::object([("uri", URI.into()), ("text", text.into())])),
+    ])))
+}
+
+fn labels(response: &Json) -> Vec<&str> {
+    response.array("result").iter().filter_map(|item| item.str("label")).collect()
+}
+
+#[test]
+fn resolves_definitions_and_addresses() {
+    let analysis = Analysis::of(SOURCE);
+
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+
+    let score = analysis.definition("SCORE").unwrap();
+    assert_eq!(score.kind, DefinitionKind::Variable);
+    assert_eq!(score.address, Some(0x10));
+    assert_eq!(score.span, Span { line: 0, start: 10, end: 15 });
+
+    let start = analysis.definition("START").unwrap();
+    assert_eq!(start.kind, DefinitionKind::Label);
+    assert_eq!(start.address, Some(0x8000));
+    assert_eq!(start.span.line, 1);
+}
+
+#[test]
+fn reports_errors_on_their_lines() {
+    let analysis = Analysis::of("START:\n  FLY A\n  LOAD A (Q, R)\n  JUMP :START\n");
+    let lines: Vec<usize> = analysis.diagnostics.iter().map(|diagnostic| diagnostic.span.line).collect();
+    assert_eq!(lines, vec![1, 2]);
+    assert_eq!(analysis.diagnostics[0].message, "Unknown mnemonic: FLY");
+
+    let analysis = Analysis::of("JUMP :NOWHERE\n");
+    assert_eq!(analysis.diagnostics.len(), 1);
+    assert_eq!(analysis.diagnostics[0].span, Span { line: 0, start: 5, end: 13 });
+}
+
+#[test]
+fn publishes_diagnostics_as_documents_change() {
+    let mut server = Server::new();
+
+    let published = open(&mut server, "JUMP :NOWHERE\n");
+    assert_eq!(published[0].str("method"), Some("textDocument/publishDiagnostics"));
+    assert_eq!(published[0].array("params.diagnostics").len(), 1);
+    assert_eq!(published[0].number("params.diagnostics.0.range.start.line"), Some(0.0));
+
+    let published = server.handle(&notification("textDocument/didChange", Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+        ("contentChanges", vec![Json::object([("text", SOURCE.into())])].into()),
+    ])));
+    assert!(published[0].array("params.diagnostics").is_empty());
+
+    let published = open(&mut server, "START:\n  HALT\n  INC B\n");
+    assert_eq!(published[0].number("params.diagnostics.0.severity"), Some(2.0));
+    assert_eq!(published[0].str("params.diagnostics.0.code"), Some("unreachable"));
+    assert_eq!(published[0].number("params.diagnostics.0.range.start.line"), Some(2.0));
+}
+
+#[test]
+fn answers_definition_hover_and_symbol_requests() {
+    let mut server = Server::new();
+    open(&mut server, SOURCE);
+
+    let definition = &server.handle(&request(1, "textDocument/definition", at(3, 9)))[0];
+    assert_eq!(definition.str("result.uri"), Some(URI));
+    assert_eq!(definition.number("result.range.start.line"), Some(1.0));
+
+    let hover = &server.handle(&request(2, "textDocument/hover", at(3, 3)))[0];
+    let text = hover.str("result.contents.value").unwrap();
+    assert!(text.contains("Default mode `(J, _)`"), "{}", text);
+
+    let hover = &server.handle(&request(3, "textDocument/hover", at(2, 10)))[0];
+    assert_eq!(hover.str("result.contents.value"), Some("**SCORE** variable at `0x0010`"));
+
+    let symbols = &server.handle(&request(4, "textDocument/documentSymbol", Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+    ])))[0];
+    let names: Vec<&str> = symbols.array("result").iter().filter_map(|symbol| symbol.str("name")).collect();
+    assert_eq!(names, vec!["SCORE", "START"]);
+}
+
+#[test]
+fn completes_mnemonics_mode_keys_and_symbols() {
+    let mut server = Server::new();
+    open(&mut server, "VAR $0x10 SCORE\nSTART:\n  LO\n  LOAD A (R, \n  JUMP :\n");
+
+    let mnemonics = &server.handle(&request(1, "textDocument/completion", at(2, 4)))[0];
+    let names = labels(mnemonics);
+    assert!(names.contains(&"LOAD") && names.contains(&"VAR"));
+
+    let keys = &server.handle(&request(2, "textDocument/completion", at(3, 12)))[0];
+    let names = labels(keys);
+    assert!(names.contains(&"R") && names.contains(&"IM"));
+
+    let symbols = &server.handle(&request(3, "textDocument/completion", at(4, 8)))[0];
+    assert_eq!(labels(symbols), vec!["START"]);
+}
+
+#[test]
+fn serves_a_framed_session() {
+    let mut input = Vec::new();
+    write_message(&mut input, &request(1, "initialize", Json::object([]))).unwrap();
+    write_message(&mut input, &request(2, "workspace/symbol", Json::object([]))).unwrap();
+    write_message(&mut input, &request(3, "shutdown", Json::Null)).unwrap();
+    write_message(&mut input, &notification("exit", Json::Null)).unwrap();
+
+    let mut output = Vec::new();
+    assert_eq!(serve(input.as_slice(), &mut output).unwrap(), 0);
+
+    let mut reader = BufReader::new(output.as_slice());
+    let initialized = read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(initialized.bool("result.capabilities.hoverProvider"), Some(true));
+    let unsupported = read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(unsupported.number("error.code"), Some(-32601.0));
+    let shutdown = read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(shutdown.get("result"), Some(&Json::Null));
+}
@@ -1,6 +1,7 @@
 use crate::mode::mode_group::ModeGroup;
 use crate::mode::mode_group::ModeGroup::{NoOperand, Value, Register, IndirectRegister, ZeroPage,
-    IndirectZeroPage, DirectAddress, IndirectAddress, JumpAddress, Accumulator,
+    IndirectZeroPage, DirectAddress, IndirectAddress, JumpAddress, IndexedAddress, IndexedZeroPage,
+    StackRelative, Accumulator,
     Low, High, Error,
 };
 
@@ -42,6 +43,28 @@ impl Mode {
             .clone()
     }
 
+    // Like `from_key`, but for keys that may have been mistyped
+    pub fn lookup_key(key: &str) -> Option<Self> {
+        MODES.iter().find(|mode| mode.keys.contains(&key)).cloned()
+    }
+
+    // Like `from_group`, but for groups that may have no mode of their own
+    pub fn lookup_group(group: &ModeGroup) -> Option<Self> {
+        MODES.iter().find(|mode| mode.group == *group).cloned()
+    }
+
+    pub fn all() -> &'static [Mode] {
+        MODES
+    }
+
+    pub fn from_group(group: &ModeGroup) -> Self {
+        MODES
+            .iter()
+            .find(|mode| mode.group == *group)
+            .unwrap_or_else(|| panic!("No Mode for Group: {:?}", group))
+            .clone()
+    }
+
     pub fn into_nibble(&self) -> u8 {
         MODES
             .iter()
@@ -86,5 +109,8 @@ static MODES: &[Mode] = &[
     Mode { keys: &["A"], group: Accumulator, nibble: 0x9 },
     Mode { keys: &["L", "1"], group: Low, nibble: 0xA },
     Mode { keys: &["H", "255", "FF"], group: High, nibble: 0xB },
+    Mode { keys: &["X", "M+"], group: IndexedAddress, nibble: 0xC },
+    Mode { keys: &["XZ", "Z+"], group: IndexedZeroPage, nibble: 0xD },
+    Mode { keys: &["S", "%"], group: StackRelative, nibble: 0xE },
     Mode { keys: &["E"], group: Error, nibble: 0xF },
 ];
@@ -9,9 +9,26 @@ pub enum ModeGroup {
     DirectAddress,
     IndirectAddress,
     JumpAddress,
+    IndexedAddress,
+    IndexedZeroPage,
+    StackRelative,
     Accumulator,
     Low,
     High,
     Error,
     Default,
 }
+
+impl ModeGroup {
+    // Bytes the operand takes after the opcode and mode byte
+    pub fn operand_size(&self) -> u16 {
+        match self {
+            ModeGroup::Value | ModeGroup::Register | ModeGroup::IndirectRegister |
+            ModeGroup::ZeroPage | ModeGroup::IndirectZeroPage | ModeGroup::StackRelative => 1,
+            ModeGroup::DirectAddress | ModeGroup::IndirectAddress | ModeGroup::JumpAddress |
+            ModeGroup::IndexedZeroPage => 2,
+            ModeGroup::IndexedAddress => 3,
+            _ => 0,
+        }
+    }
+}
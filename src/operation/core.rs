@@ -22,6 +22,19 @@ impl Operation {
             .clone()
     }
 
+    // Like `from_mnemonic`, but for names that may not be instructions at all
+    pub fn lookup_mnemonic(mnemonic: &str) -> Option<Self> {
+        OPERATIONS.iter().find(|inst| inst.mnemonics.contains(&mnemonic)).cloned()
+    }
+
+    pub fn all() -> &'static [Operation] {
+        OPERATIONS
+    }
+
+    pub fn macro_mnemonics() -> &'static [&'static str] {
+        MACRO_MNEMONICS
+    }
+
     pub fn from_byte(byte: u8) -> Self {
         let mut operation = OPERATIONS
             .iter()
@@ -36,6 +49,23 @@ impl Operation {
         operation
     }
 
+    // Like `from_byte`, but for bytes that may not be instructions at all
+    pub fn lookup_byte(byte: u8) -> Option<Self> {
+        OPERATIONS
+            .iter()
+            .find(|inst| inst.opcode == (byte & 0x7F))
+            .map(|_| Self::from_byte(byte))
+    }
+
+    // Branches whose operand is a signed displacement from the next instruction
+    pub fn is_relative_branch(&self) -> bool {
+        matches!(
+        self.group, 
+        Group::Branch(BranchVariant::Positive | BranchVariant::Zero | BranchVariant::Negative |
+        BranchVariant::Overflow | BranchVariant::Carry | BranchVariant::NoCarry)
+        )
+    }
+
     pub fn has_default_mode(&self) -> bool {
         self.opcode >> 7 == 0
     }
@@ -68,7 +98,7 @@ impl Operation {
 }
 
 static MACRO_MNEMONICS: &[&str] = &[
-    "STRING", "ARRAY", "VAR", "NAME", "LINK"
+    "STRING", "ARRAY", "VAR", "NAME", "LINK", "BANK"
 ];
 
 static DIRECTIVES: &[&str] = &[
@@ -148,6 +178,30 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x0B,
         default_mode: 0x29,
     },
+    Operation { 
+        mnemonics: &["SMUL", "IMUL"], 
+        group: Group::Arithmetic(ArithmeticVariant::SignedMultiply), 
+        opcode: 0x0C,
+        default_mode: 0x29,
+    },
+    Operation { 
+        mnemonics: &["SDIV", "IDIV"], 
+        group: Group::Arithmetic(ArithmeticVariant::SignedDivide), 
+        opcode: 0x0D,
+        default_mode: 0x29,
+    },
+    Operation { 
+        mnemonics: &["ADC", "ADDC"], 
+        group: Group::Arithmetic(ArithmeticVariant::AddCarry), 
+        opcode: 0x0E,
+        default_mode: 0x29,
+    },
+    Operation { 
+        mnemonics: &["SBC", "SUBB"], 
+        group: Group::Arithmetic(ArithmeticVariant::SubtractBorrow), 
+        opcode: 0x0F,
+        default_mode: 0x29,
+    },
 
     Operation { 
         mnemonics: &["AND"], 
@@ -197,6 +251,24 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x17,
         default_mode: 0x9A,
     },
+    Operation { 
+        mnemonics: &["BTST", "BIT"], 
+        group: Group::Logic(LogicVariant::BitTest), 
+        opcode: 0x18,
+        default_mode: 0x19,
+    },
+    Operation { 
+        mnemonics: &["BSET", "SETB"], 
+        group: Group::Logic(LogicVariant::BitSet), 
+        opcode: 0x19,
+        default_mode: 0x19,
+    },
+    Operation { 
+        mnemonics: &["BCLR", "CLRB"], 
+        group: Group::Logic(LogicVariant::BitClear), 
+        opcode: 0x1A,
+        default_mode: 0x19,
+    },
 
     Operation { 
         mnemonics: &["COMP", "CMPR"], 
@@ -222,6 +294,30 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x23, 
         default_mode: 0x10,
     },
+    Operation { 
+        mnemonics: &["SCMP", "SCMPR"], 
+        group: Group::Branch(BranchVariant::SignedCompare),  
+        opcode: 0x24, 
+        default_mode: 0x22,
+    },
+    Operation { 
+        mnemonics: &["OVFL", "OVER"], 
+        group: Group::Branch(BranchVariant::Overflow),   
+        opcode: 0x25, 
+        default_mode: 0x10,
+    },
+    Operation { 
+        mnemonics: &["CARY", "CRRY"], 
+        group: Group::Branch(BranchVariant::Carry),   
+        opcode: 0x26, 
+        default_mode: 0x10,
+    },
+    Operation { 
+        mnemonics: &["NCRY", "NCAR"], 
+        group: Group::Branch(BranchVariant::NoCarry),   
+        opcode: 0x27, 
+        default_mode: 0x10,
+    },
 
     Operation { 
         mnemonics: &["CALL"], 
@@ -277,6 +373,30 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x38, 
         default_mode: 0x82,
     },
+    Operation { 
+        mnemonics: &["SJGT", "JGTS"],  
+        group: Group::Subroutine(SubroutineVariant::JumpGreaterSigned), 
+        opcode: 0x39, 
+        default_mode: 0x82,
+    },
+    Operation { 
+        mnemonics: &["SJGE", "JGES"],  
+        group: Group::Subroutine(SubroutineVariant::JumpGreaterEqualSigned), 
+        opcode: 0x3A, 
+        default_mode: 0x82,
+    },
+    Operation { 
+        mnemonics: &["SJLE", "JLES"],  
+        group: Group::Subroutine(SubroutineVariant::JumpLessEqualSigned), 
+        opcode: 0x3B, 
+        default_mode: 0x82,
+    },
+    Operation { 
+        mnemonics: &["SJLT", "JLTS"],  
+        group: Group::Subroutine(SubroutineVariant::JumpLessSigned), 
+        opcode: 0x3C, 
+        default_mode: 0x82,
+    },
 
     Operation { 
         mnemonics: &["PUSH"], 
@@ -302,6 +422,30 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x43,
         default_mode: 0x00,
     },
+    Operation { 
+        mnemonics: &["GTSP", "GETSP"], 
+        group: Group::Stack(StackVariant::GetStackPointer),  
+        opcode: 0x44,
+        default_mode: 0x20,
+    },
+    Operation { 
+        mnemonics: &["STSP", "SETSP"], 
+        group: Group::Stack(StackVariant::SetStackPointer),  
+        opcode: 0x45,
+        default_mode: 0x20,
+    },
+    Operation { 
+        mnemonics: &["ENTR", "ENTER"], 
+        group: Group::Stack(StackVariant::EnterFrame),  
+        opcode: 0x46,
+        default_mode: 0x10,
+    },
+    Operation { 
+        mnemonics: &["LEAV", "LEAVE"], 
+        group: Group::Stack(StackVariant::LeaveFrame),  
+        opcode: 0x47,
+        default_mode: 0x00,
+    },
 
     Operation { 
         mnemonics: &["MOVE", "MOV"], 
@@ -327,6 +471,18 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x53,
         default_mode: 0x22,
     },
+    Operation { 
+        mnemonics: &["COPY", "BCPY"], 
+        group: Group::Memory(MemoryVariant::BlockCopy),   
+        opcode: 0x54,
+        default_mode: 0x00,
+    },
+    Operation { 
+        mnemonics: &["FILL", "BFIL"], 
+        group: Group::Memory(MemoryVariant::BlockFill),   
+        opcode: 0x55,
+        default_mode: 0x90,
+    },
 
     Operation { 
         mnemonics: &["IN", "GET"],   
@@ -365,4 +521,28 @@ static OPERATIONS: &[Operation] = &[
         opcode: 0x71,
         default_mode: 0x00,
     },
+    Operation { 
+        mnemonics: &["SETC", "SEC"], 
+        group: Group::System(SystemVariant::SetCarry),   
+        opcode: 0x72,
+        default_mode: 0x00,
+    },
+    Operation { 
+        mnemonics: &["CLRC", "CLC"], 
+        group: Group::System(SystemVariant::ClearCarry),   
+        opcode: 0x73,
+        default_mode: 0x00,
+    },
+    Operation { 
+        mnemonics: &["TRAP", "SYS"], 
+        group: Group::System(SystemVariant::Trap),   
+        opcode: 0x74,
+        default_mode: 0x10,
+    },
+    Operation { 
+        mnemonics: &["RTRP", "IRET"], 
+        group: Group::System(SystemVariant::TrapReturn),   
+        opcode: 0x75,
+        default_mode: 0x00,
+    },
 ];
@@ -26,6 +26,10 @@ pub enum ArithmeticVariant {
     Difference,
     Product,
     Quotient,
+    SignedMultiply,
+    SignedDivide,
+    AddCarry,
+    SubtractBorrow,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -38,6 +42,9 @@ pub enum LogicVariant {
     RightShift,
     LeftRotate,
     RightRotate,
+    BitTest,
+    BitSet,
+    BitClear,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -46,6 +53,10 @@ pub enum BranchVariant {
     Positive,
     Negative,
     Zero,
+    SignedCompare,
+    Overflow,
+    Carry,
+    NoCarry,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -59,6 +70,10 @@ pub enum SubroutineVariant {
     JumpLessEqual,
     JumpLess,
     JumpNotEqual,
+    JumpGreaterSigned,
+    JumpGreaterEqualSigned,
+    JumpLessEqualSigned,
+    JumpLessSigned,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -67,6 +82,10 @@ pub enum StackVariant {
     Pop,
     Dump,
     Restore,
+    GetStackPointer,
+    SetStackPointer,
+    EnterFrame,
+    LeaveFrame,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -75,6 +94,8 @@ pub enum MemoryVariant {
     Load,
     Save,
     Swap,
+    BlockCopy,
+    BlockFill,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -89,4 +110,8 @@ pub enum InputOutputVariant {
 pub enum SystemVariant {
     Halt,
     Wait, // No-op instruction
+    SetCarry,
+    ClearCarry,
+    Trap,
+    TrapReturn,
 }
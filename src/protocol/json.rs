@@ -0,0 +1,295 @@
+use std::fmt;
+
+// Just enough JSON for the editor protocols: values are parsed into this tree and
+// written back out with `Display`. Objects keep their keys in insertion order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut reader = Reader { text, position: 0 };
+        let value = reader.value()?;
+
+        reader.skip_whitespace();
+        if reader.position < text.len() {
+            return Err(format!("Unexpected text after JSON value at {}", reader.position))
+        }
+
+        Ok(value)
+    }
+
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    // Looks up a dotted path such as `params.textDocument.uri`
+    pub fn get(&self, path: &str) -> Option<&Json> {
+        path.split('.').try_fold(self, |value, key| match value {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            Json::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None,
+        })
+    }
+
+    pub fn str(&self, path: &str) -> Option<&str> {
+        match self.get(path) {
+            Some(Json::String(text)) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, path: &str) -> Option<f64> {
+        match self.get(path) {
+            Some(Json::Number(number)) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn array(&self, path: &str) -> &[Json] {
+        match self.get(path) {
+            Some(Json::Array(items)) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn bool(&self, path: &str) -> Option<bool> {
+        match self.get(path) {
+            Some(Json::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(text: &str) -> Self {
+        Json::String(text.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(text: String) -> Self {
+        Json::String(text)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<u16> for Json {
+    fn from(value: u16) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(items: Vec<Json>) -> Self {
+        Json::Array(items)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            },
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(text) => write_string(f, text),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 { write!(f, ",")?; }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 { write!(f, ",")?; }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for character in text.chars() {
+        match character {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.position += character.len_utf8();
+        Some(character)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        if self.text[self.position..].starts_with(word) {
+            self.position += word.len();
+            Ok(())
+        } else {
+            Err(format!("Expected {} at {}", word, self.position))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('n') => self.expect("null").map(|_| Json::Null),
+            Some('t') => self.expect("true").map(|_| Json::Bool(true)),
+            Some('f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.next();
+                let mut items = Vec::new();
+
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.next();
+                    return Ok(Json::Array(items))
+                }
+
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+
+                    match self.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(items)),
+                        _ => return Err(format!("Expected , or ] at {}", self.position)),
+                    }
+                }
+            },
+            Some('{') => {
+                self.next();
+                let mut fields = Vec::new();
+
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.next();
+                    return Ok(Json::Object(fields))
+                }
+
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    fields.push((key, self.value()?));
+                    self.skip_whitespace();
+
+                    match self.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(fields)),
+                        _ => return Err(format!("Expected , or }} at {}", self.position)),
+                    }
+                }
+            },
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.position;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+                    self.next();
+                }
+
+                self.text[start..self.position]
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| format!("Invalid number at {}", start))
+            },
+            _ => Err(format!("Unexpected character at {}", self.position)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut text = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => match self.next() {
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('b') => text.push('\u{8}'),
+                    Some('f') => text.push('\u{c}'),
+                    Some('u') => text.push(self.escaped_character()?),
+                    Some(c) => text.push(c),
+                    None => return Err("Unterminated string".to_string()),
+                },
+                Some(c) => text.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    // `\uXXXX`, including surrogate pairs for characters outside the basic plane
+    fn escaped_character(&mut self) -> Result<char, String> {
+        let high = self.hex_digits()?;
+
+        if (0xD800..0xDC00).contains(&high) {
+            self.expect("\\u")?;
+            let low = self.hex_digits()?;
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+            return char::from_u32(code).ok_or_else(|| "Invalid surrogate pair".to_string())
+        }
+
+        char::from_u32(high).ok_or_else(|| format!("Invalid escape at {}", self.position))
+    }
+
+    fn hex_digits(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.position..self.position + 4).ok_or("Truncated escape")?;
+        self.position += 4;
+        u32::from_str_radix(digits, 16).map_err(|_| format!("Invalid escape: {}", digits))
+    }
+}
@@ -0,0 +1,5 @@
+pub mod json;
+pub mod transport;
+
+#[cfg(test)]
+mod test;
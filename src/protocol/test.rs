@@ -0,0 +1,51 @@
+use std::io::BufReader;
+
+use crate::protocol::json::Json;
+use crate::protocol::transport::{read_message, write_message};
+
+#[test]
+fn parses_nested_values() {
+    let value = Json::parse(r#"{"id": 3, "params": {"items": [true, null, -1.5e1, "a\"bé"]}}"#).unwrap();
+
+    assert_eq!(value.number("id"), Some(3.0));
+    assert_eq!(value.bool("params.items.0"), Some(true));
+    assert_eq!(value.get("params.items.1"), Some(&Json::Null));
+    assert_eq!(value.number("params.items.2"), Some(-15.0));
+    assert_eq!(value.str("params.items.3"), Some("a\"bé"));
+    assert_eq!(value.get("params.missing"), None);
+}
+
+#[test]
+fn writes_compact_text_that_parses_back() {
+    let value = Json::object([
+        ("name", "line\none".into()),
+        ("count", 12_usize.into()),
+        ("items", vec![Json::Bool(false), Json::Null].into()),
+    ]);
+
+    let text = value.to_string();
+    assert_eq!(text, r#"{"name":"line\none","count":12,"items":[false,null]}"#);
+    assert_eq!(Json::parse(&text), Ok(value));
+}
+
+#[test]
+fn rejects_malformed_json() {
+    assert!(Json::parse("{\"a\": }").is_err());
+    assert!(Json::parse("[1, 2").is_err());
+    assert!(Json::parse("\"open").is_err());
+    assert!(Json::parse("1 2").is_err());
+}
+
+#[test]
+fn frames_messages_with_content_length() {
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, &Json::object([("id", 1_usize.into())])).unwrap();
+    write_message(&mut buffer, &Json::object([("id", 2_usize.into())])).unwrap();
+
+    assert!(buffer.starts_with(b"Content-Length: 8\r\n\r\n{\"id\":1}"));
+
+    let mut reader = BufReader::new(buffer.as_slice());
+    assert_eq!(read_message(&mut reader).unwrap().unwrap().number("id"), Some(1.0));
+    assert_eq!(read_message(&mut reader).unwrap().unwrap().number("id"), Some(2.0));
+    assert_eq!(read_message(&mut reader).unwrap(), None);
+}
@@ -0,0 +1,46 @@
+use std::io::{self, BufRead, Write};
+
+use crate::protocol::json::Json;
+
+// Both the language server and debug adapter protocols frame each JSON message with
+// a `Content-Length` header and a blank line.
+
+// Returns `None` when the input has ended
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None)
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid("Message without a Content-Length header"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let text = String::from_utf8(body).map_err(|_| invalid("Message is not valid UTF-8"))?;
+    Json::parse(&text).map(Some).map_err(|error| invalid(&error))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::assembler::parser::{Parser, assembler_operand::AssemblerOperand};
+use crate::binary::container::{RomContainer, SymbolKind};
+use crate::chiiko::components::{chip::Chip, console::Console, cpu::HALT_ADDRESS};
+use crate::chiiko::{Chiiko, MachineProfile};
+use crate::testing::suite::{Assignment, Case, Place, Value};
+
+pub const EXTENSION: &str = "kutest";
+
+// Every test file under `path`, in a stable order. A file given directly is used even when
+// its extension is different.
+pub fn discover(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()])
+    }
+
+    let entries = fs::read_dir(path)
+        .map_err(|error| format!("Failed to read directory: {} {}", path.display(), error))?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(discover(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+// Runs one case on a fresh machine: the setup is applied, the label is called with a return
+// address of HALT_ADDRESS so its RTRN stops the machine like a HALT would, and the
+// expectations are checked. Returns what went wrong, so an empty list is a pass.
+pub fn run_case(case: &Case, container: &RomContainer, profile: &MachineProfile) -> Vec<String> {
+    match prepare(case, container, profile).and_then(|chiiko| execute(case, chiiko)) {
+        Ok(chiiko) => case.expectations
+            .iter()
+            .filter_map(|expectation| check(expectation, &chiiko, container).err())
+            .collect(),
+        Err(error) => vec![error],
+    }
+}
+
+fn prepare(case: &Case, container: &RomContainer, profile: &MachineProfile) -> Result<Chiiko, String> {
+    let mut chiiko = Chiiko::from_container(profile, container)?;
+    let inputs: Vec<&str> = case.input.iter().map(|line| line.as_str()).collect();
+    chiiko.cpu.console = Console::buffered(&inputs);
+    if !case.random.is_empty() {
+        chiiko.script_random(&case.random);
+    }
+
+    let entry = container.symbols
+        .iter()
+        .find(|symbol| symbol.kind == SymbolKind::Label && symbol.name == case.call)
+        .ok_or_else(|| format!("No label named {}", case.call))?
+        .address;
+
+    for assignment in &case.setup {
+        apply(assignment, &mut chiiko, container)?;
+    }
+
+    let [high, low] = HALT_ADDRESS.to_be_bytes();
+    chiiko.cpu.push(high)?;
+    chiiko.cpu.push(low)?;
+    chiiko.cpu.set_pc(entry);
+    Ok(chiiko)
+}
+
+fn execute(case: &Case, mut chiiko: Chiiko) -> Result<Chiiko, String> {
+    for _ in 0..case.step_limit {
+        if chiiko.is_halted() {
+            return Ok(chiiko)
+        }
+
+        chiiko.step().map_err(|error| format!("Machine fault: {}", error))?;
+    }
+
+    match chiiko.is_halted() {
+        true => Ok(chiiko),
+        false => Err(format!("{} did not return within {} steps", case.call, case.step_limit)),
+    }
+}
+
+fn apply(assignment: &Assignment, chiiko: &mut Chiiko, container: &RomContainer) -> Result<(), String> {
+    let Value::Numbers(numbers) = &assignment.value else {
+        return Err(format!("Line {}: output cannot be set", assignment.line))
+    };
+    let cpu = &mut chiiko.cpu;
+    let value = numbers[0];
+
+    match &assignment.place {
+        Place::Register(code) => cpu.write_register(*code, value as u8)?,
+        Place::Pair(code) => cpu.write_register_pair(*code, value)?,
+        Place::Flag(_, bit) if value == 0 => cpu.status &= !bit,
+        Place::Flag(_, bit) => cpu.status |= bit,
+        Place::Memory(name) => {
+            let address = resolve(name, container, assignment.line)?;
+            for (offset, byte) in numbers.iter().enumerate() {
+                cpu.write(address.wrapping_add(offset as u16), *byte as u8)?;
+            }
+        },
+        Place::Output => (),
+    }
+
+    Ok(())
+}
+
+fn check(expectation: &Assignment, chiiko: &Chiiko, container: &RomContainer) -> Result<(), String> {
+    let cpu = &chiiko.cpu;
+
+    let (name, expected, actual) = match (&expectation.place, &expectation.value) {
+        (Place::Output, Value::Text(expected)) => {
+            return match cpu.console.output() == expected {
+                true => Ok(()),
+                false => Err(format!("output differs:\n{}", diff(expected, cpu.console.output()))),
+            }
+        },
+        (Place::Register(code), Value::Numbers(numbers)) => (
+            register(*code),
+            format!("{:#04X}", numbers[0]),
+            format!("{:#04X}", cpu.read_register(*code)?),
+        ),
+        (Place::Pair(code), Value::Numbers(numbers)) => (
+            register(*code),
+            format!("{:#06X}", numbers[0]),
+            format!("{:#06X}", cpu.read_register_pair(*code)?),
+        ),
+        (Place::Flag(flag, bit), Value::Numbers(numbers)) => (
+            format!("{}F", flag),
+            numbers[0].to_string(),
+            ((cpu.status & bit != 0) as u8).to_string(),
+        ),
+        (Place::Memory(name), Value::Numbers(numbers)) => {
+            let address = resolve(name, container, expectation.line)?;
+            let actual: Vec<u16> = (0..numbers.len())
+                .map(|offset| cpu.read(address.wrapping_add(offset as u16)) as u16)
+                .collect();
+            let name = match Parser::normalize_number(name) {
+                Ok(_) => format!("${:#06X}", address),
+                Err(_) => format!("${}", name),
+            };
+            (name, bytes(numbers), bytes(&actual))
+        },
+        _ => return Err(format!("Line {}: cannot compare {:?}", expectation.line, expectation.place)),
+    };
+
+    match expected == actual {
+        true => Ok(()),
+        false => Err(format!("{}: expected {}, got {} (line {})", name, expected, actual, expectation.line)),
+    }
+}
+
+// Addresses are numbers or the names of VAR and ARRAY symbols
+fn resolve(name: &str, container: &RomContainer, line: usize) -> Result<u16, String> {
+    if let Ok(address) = Parser::normalize_number(name) {
+        return u16::try_from(address).map_err(|_| format!("Line {}: address out of range ${}", line, name))
+    }
+
+    container.symbols
+        .iter()
+        .find(|symbol| symbol.name == name)
+        .map(|symbol| symbol.address)
+        .ok_or_else(|| format!("Line {}: no symbol named {}", line, name))
+}
+
+fn register(code: u8) -> String {
+    AssemblerOperand::register_name(code).unwrap_or("?").to_string()
+}
+
+fn bytes(values: &[u16]) -> String {
+    values.iter().map(|value| format!("{:02X}", value)).collect::<Vec<String>>().join(" ")
+}
+
+// Line by line, with `-` for what was expected and `+` for what the program printed
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.split('\n').collect();
+    let actual: Vec<&str> = actual.split('\n').collect();
+    let mut text = String::new();
+
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(wanted), Some(got)) if wanted == got => text.push_str(&format!("    {:?}\n", wanted)),
+            (wanted, got) => {
+                if let Some(wanted) = wanted {
+                    text.push_str(&format!("  - {:?}\n", wanted));
+                }
+                if let Some(got) = got {
+                    text.push_str(&format!("  + {:?}\n", got));
+                }
+            },
+        }
+    }
+
+    text
+}
@@ -0,0 +1,8 @@
+mod core;
+pub mod regression;
+pub mod suite;
+
+#[cfg(test)]
+mod test;
+
+pub use core::{diff, discover, run_case};
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::binary::container::RomContainer;
+use crate::chiiko::components::console::Console;
+use crate::chiiko::{Chiiko, MachineProfile, Stop};
+use crate::testing::{diff, suite::FLAGS};
+
+pub const DEFAULT_CYCLE_LIMIT: u64 = 1_000_000;
+pub const EXPECTED_EXTENSION: &str = "expected";
+pub const INPUT_EXTENSION: &str = "stdin";
+pub const PROFILE_FILENAME: &str = "regress.profile";
+
+// How a program compared with its `.expected` file
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Passed,
+    Failed(String), // A diff of the expected and actual results
+    Missing,
+    Blessed, // The expected file was written or replaced
+}
+
+// The `.ku` programs directly inside `directory`, sorted by name
+pub fn programs(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(directory)
+        .map_err(|error| format!("Failed to read directory: {} {}", directory.display(), error))?;
+
+    let mut programs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ku")))
+        .collect();
+    programs.sort();
+    Ok(programs)
+}
+
+// The machine a corpus asks to be run on, from a `regress.profile` beside its programs
+pub fn profile(directory: &Path) -> Option<PathBuf> {
+    Some(directory.join(PROFILE_FILENAME)).filter(|path| path.is_file())
+}
+
+// Console input comes from a sibling `.stdin` file, one line per read; without one the
+// program gets no input at all
+pub fn input(program: &Path) -> Vec<String> {
+    fs::read_to_string(program.with_extension(INPUT_EXTENSION))
+        .map(|text| text.lines().map(|line| line.to_string()).collect())
+        .unwrap_or_default()
+}
+
+// Runs a program to the end and describes how it finished: why it stopped, the registers and
+// flags, then everything it printed. RAND is seeded so runs repeat unless the profile picks
+// its own seed.
+//
+//   stop: exit 3
+//   registers: A=03 B=00 C=00 H=00 L=00 I=00 J=00
+//   flags: -----
+//   output:
+//   Hello
+pub fn record(
+container: &RomContainer,
+profile: &MachineProfile,
+input: &[String],
+cycle_limit: u64
+) -> Result<String, String> {
+    let mut chiiko = Chiiko::from_container(profile, container)?;
+    let input: Vec<&str> = input.iter().map(|line| line.as_str()).collect();
+    chiiko.cpu.console = Console::buffered(&input);
+    if profile.seed.is_none() {
+        chiiko.seed_random(0);
+    }
+
+    let stop = match chiiko.run(Some(cycle_limit)) {
+        Ok(Stop::Halted) => "halted".to_string(),
+        Ok(Stop::Exited(code)) => format!("exit {}", code),
+        Ok(Stop::CycleLimit) => format!("cycle limit {}", cycle_limit),
+        Err(error) => format!("fault: {}", error),
+    };
+
+    let cpu = &chiiko.cpu;
+    let flags: String = FLAGS
+        .iter()
+        .map(|(name, bit)| if cpu.status & bit != 0 { name } else { "-" })
+        .collect();
+
+    Ok(format!(
+        "stop: {}\nregisters: A={:02X} B={:02X} C={:02X} H={:02X} L={:02X} I={:02X} J={:02X}\nflags: {}\noutput:\n{}",
+        stop, cpu.accumulator, cpu.b_register, cpu.c_register, cpu.h_register, cpu.l_register,
+        cpu.i_register, cpu.j_register, flags, cpu.console.output(),
+    ))
+}
+
+// Compares a recorded run with the program's `.expected` file. Blessing writes the recording
+// as the new expectation instead of failing.
+pub fn compare(program: &Path, actual: &str, bless: bool) -> Result<Verdict, String> {
+    let path = program.with_extension(EXPECTED_EXTENSION);
+    let expected = fs::read_to_string(&path).ok();
+
+    if expected.as_deref() == Some(actual) {
+        return Ok(Verdict::Passed)
+    }
+
+    if bless {
+        fs::write(&path, actual)
+            .map_err(|error| format!("Failed to write file: {} {}", path.display(), error))?;
+        return Ok(Verdict::Blessed)
+    }
+
+    Ok(match expected {
+        Some(expected) => Verdict::Failed(diff(&expected, actual)),
+        None => Verdict::Missing,
+    })
+}
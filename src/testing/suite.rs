@@ -0,0 +1,232 @@
+use std::fs;
+
+use crate::assembler::parser::{Parser, assembler_operand::AssemblerOperand};
+
+pub const DEFAULT_STEP_LIMIT: u64 = 10_000;
+
+// Status bits that tests can set and check, as the debugger shows them
+pub const FLAGS: &[(&str, u8)] = &[("Z", 0x01), ("N", 0x02), ("C", 0x04), ("V", 0x08), ("I", 0x80)];
+
+// What a setting or expectation refers to
+#[derive(Clone, Debug, PartialEq)]
+pub enum Place {
+    Register(u8),
+    Pair(u8),
+    Flag(&'static str, u8),
+    Memory(String), // A number or a VAR name, resolved against the program's symbols
+    Output,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Numbers(Vec<u16>),
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pub line: usize,
+    pub place: Place,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub name: String,
+    pub line: usize,
+    pub call: String,
+    pub step_limit: u64,
+    pub setup: Vec<Assignment>,
+    pub input: Vec<String>,
+    pub random: Vec<u8>, // Values RAND returns in turn; the profile's source when empty
+    pub expectations: Vec<Assignment>,
+}
+
+// A `.kutest` file: the program under test followed by named cases.
+//
+//   program = math.ku          # Relative to the test file
+//   profile = compact.profile  # Optional
+//
+//   [doubles_small_numbers]
+//   call = DOUBLE              # The label to call; the case ends at its RTRN or a HALT
+//   steps = 100                # Instructions allowed before the case fails
+//   A = 4                      # Registers, pairs, flags and memory: HL = 0x1234, CF = 1,
+//   $0x0010 = 1, 2, 3          #   $COUNT = 7
+//   input = "12"               # One console input line each
+//   random = 3, 12             # What RAND returns, in turn and then repeating
+//   expect A = 8
+//   expect output = "8\n"
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suite {
+    pub program: String,
+    pub profile: Option<String>,
+    pub cases: Vec<Case>,
+}
+
+impl Suite {
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let raw = fs::read_to_string(filename)
+            .map_err(|error| format!("Failed to read file: {} {}", filename, error))?;
+        Self::parse(&raw).map_err(|error| format!("{}: {}", filename, error))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut program: Option<String> = None;
+        let mut profile: Option<String> = None;
+        let mut cases: Vec<Case> = Vec::new();
+
+        for (index, line) in raw.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() { continue; }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                cases.push(Case {
+                    name: name.trim().to_string(),
+                    line: number,
+                    call: String::new(),
+                    step_limit: DEFAULT_STEP_LIMIT,
+                    setup: Vec::new(),
+                    input: Vec::new(),
+                    random: Vec::new(),
+                    expectations: Vec::new(),
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected `key = value`", number))?;
+
+            let Some(case) = cases.last_mut() else {
+                match key {
+                    "program" => program = Some(value.to_string()),
+                    "profile" => profile = Some(value.to_string()),
+                    _ => return Err(format!("Line {}: `{}` belongs inside a [test] section", number, key)),
+                }
+                continue;
+            };
+
+            match key {
+                "call" => case.call = value.to_uppercase(),
+                "steps" => {
+                    case.step_limit = parse_number(value)
+                        .ok_or_else(|| format!("Line {}: invalid number `{}`", number, value))? as u64;
+                },
+                "input" => case.input.push(parse_text(value, number)?),
+                "random" => {
+                    case.random = value
+                        .split(',')
+                        .map(|number| parse_number(number.trim()).and_then(|number| u8::try_from(number).ok()))
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or_else(|| format!("Line {}: invalid RAND value in `{}`", number, value))?;
+                },
+                _ => match key.strip_prefix("expect ") {
+                    Some(key) => case.expectations.push(Self::assignment(key.trim(), value, number)?),
+                    None => case.setup.push(Self::assignment(key, value, number)?),
+                },
+            }
+        }
+
+        if let Some(case) = cases.iter().find(|case| case.call.is_empty()) {
+            return Err(format!("Line {}: test {} has no `call`", case.line, case.name))
+        }
+
+        let program = program.ok_or("Missing `program = <file>`")?;
+        Ok(Self { program, profile, cases })
+    }
+
+    fn assignment(key: &str, value: &str, line: usize) -> Result<Assignment, String> {
+        let key = key.to_uppercase();
+        let place = if key == "OUTPUT" {
+            Place::Output
+        } else if let Some(address) = key.strip_prefix('$') {
+            Place::Memory(address.to_string())
+        } else if let Some((name, bit)) = FLAGS.iter().find(|(name, _)| key.strip_suffix('F') == Some(name)) {
+            // C is both a register and the carry flag, so flags are written ZF, CF and so on
+            Place::Flag(name, *bit)
+        } else {
+            match AssemblerOperand::register_code(&key) {
+                Some(code) if code < 9 => Place::Register(code),
+                Some(code) => Place::Pair(code),
+                None => return Err(format!("Line {}: unknown register, flag or address `{}`", line, key)),
+            }
+        };
+
+        let value = match place {
+            Place::Output => Value::Text(parse_text(value, line)?),
+            _ => {
+                let numbers = value
+                    .split(',')
+                    .map(|number| parse_number(number.trim()))
+                    .collect::<Option<Vec<usize>>>()
+                    .ok_or_else(|| format!("Line {}: invalid number in `{}`", line, value))?;
+
+                let limit = match place {
+                    Place::Pair(_) => 0xFFFF,
+                    Place::Flag(..) => 1,
+                    _ => 0xFF,
+                };
+                if numbers.iter().any(|number| *number > limit) {
+                    return Err(format!("Line {}: `{}` does not fit {}", line, value, key))
+                }
+                if numbers.len() > 1 && !matches!(place, Place::Memory(_)) {
+                    return Err(format!("Line {}: {} takes one value", line, key))
+                }
+
+                Value::Numbers(numbers.into_iter().map(|number| number as u16).collect())
+            },
+        };
+
+        Ok(Assignment { line, place, value })
+    }
+}
+
+fn parse_number(value: &str) -> Option<usize> {
+    Parser::normalize_number(&value.to_uppercase().replace('_', "")).ok()
+}
+
+// Text is quoted, with `\n`, `\t`, `\"` and `\\` escapes
+fn parse_text(value: &str, line: usize) -> Result<String, String> {
+    let quoted = value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("Line {}: text must be quoted", line))?;
+
+    let mut text = String::new();
+    let mut characters = quoted.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            text.push(character);
+            continue;
+        }
+
+        match characters.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(escaped @ ('"' | '\\')) => text.push(escaped),
+            _ => return Err(format!("Line {}: unknown escape in {}", line, value)),
+        }
+    }
+
+    Ok(text)
+}
+
+// `#` starts a comment, except inside quoted text
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (index, character) in line.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => (),
+        }
+    }
+
+    line
+}
@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use crate::assembler::encoder::{Encoder, symbol_table::SymbolTable};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::binary::container::RomContainer;
+use crate::chiiko::MachineProfile;
+use crate::testing::regression::{self, Verdict};
+use crate::testing::run_case;
+use crate::testing::suite::{Place, Suite, Value};
+
+const PROGRAM: &str = "\
+VAR $0x0010 TOTAL
+START:
+    HALT
+DOUBLE:
+    ADD A
+    RTRN
+SUM:
+    LOAD $0x0020 A
+    ADD $0x0021
+    LOAD A $TOTAL
+    PRNT $0x0030
+    RTRN
+FOREVER:
+    JUMP :FOREVER
+ROLL:
+    RAND A 6
+    RTRN
+";
+
+fn container(source: &str) -> RomContainer {
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode(&parser.instructions, &mut table).unwrap();
+    RomContainer::from_program(&program, &table, "test", "standard", 0x8000)
+}
+
+fn failures(suite: &str) -> Vec<Vec<String>> {
+    let suite = Suite::parse(suite).unwrap();
+    let container = container(PROGRAM);
+
+    suite.cases.iter().map(|case| run_case(case, &container, &MachineProfile::default())).collect()
+}
+
+#[test]
+fn parses_setup_and_expectations() {
+    let suite = Suite::parse("\
+# Doubling
+program = math.ku
+
+[doubles]
+call = double
+steps = 0x20
+A = 4
+cf = 1
+$total = 1, 2   # two bytes
+input = \"a # b\"
+expect HL = 0x1234
+expect output = \"8\\n\"
+").unwrap();
+
+    assert_eq!(suite.program, "math.ku");
+    let case = &suite.cases[0];
+    assert_eq!((case.call.as_str(), case.step_limit), ("DOUBLE", 32));
+    assert_eq!(case.input, vec!["a # b"]);
+    assert_eq!(case.setup.iter().map(|setting| &setting.place).collect::<Vec<&Place>>(), vec![
+        &Place::Register(0),
+        &Place::Flag("C", 0x04),
+        &Place::Memory("TOTAL".to_string()),
+    ]);
+    assert_eq!(case.setup[2].value, Value::Numbers(vec![1, 2]));
+    assert_eq!(case.expectations[0].place, Place::Pair(10));
+    assert_eq!(case.expectations[1].value, Value::Text("8\n".to_string()));
+}
+
+#[test]
+fn rejects_malformed_suites() {
+    assert!(Suite::parse("[orphan]\ncall = DOUBLE\n").is_err());
+    assert!(Suite::parse("program = a.ku\n[no_call]\nA = 1\n").is_err());
+    assert!(Suite::parse("program = a.ku\n[big]\ncall = X\nA = 0x100\n").is_err());
+    assert!(Suite::parse("program = a.ku\n[unknown]\ncall = X\nexpect Q = 1\n").is_err());
+}
+
+#[test]
+fn passing_cases_report_nothing() {
+    let results = failures("\
+program = math.ku
+
+[doubles]
+call = DOUBLE
+A = 21
+expect A = 42
+expect CF = 0
+
+[sums]
+call = SUM
+$0x0020 = 3, 4
+$0x0030 = 0x48, 0x69, 0
+expect $TOTAL = 7
+expect output = \"Hi\"
+");
+
+    assert_eq!(results, vec![Vec::<String>::new(), Vec::new()]);
+}
+
+#[test]
+fn cases_can_script_rand() {
+    let results = failures("\
+program = math.ku
+
+[rolls]
+call = ROLL
+random = 3, 12
+expect A = 3
+");
+
+    assert_eq!(results, vec![Vec::<String>::new()]);
+    assert!(Suite::parse("program = a.ku\n[bad]\ncall = X\nrandom = 0x100\n").is_err());
+}
+
+#[test]
+fn failing_cases_explain_the_difference() {
+    let results = failures("\
+program = math.ku
+
+[wrong]
+call = DOUBLE
+A = 2
+expect A = 5
+expect $0x0010 = 1, 2
+expect output = \"Hello\\nthere\"
+");
+
+    assert_eq!(results[0], vec![
+        "A: expected 0x05, got 0x04 (line 6)",
+        "$0x0010: expected 01 02, got 00 00 (line 7)",
+        "output differs:\n  - \"Hello\"\n  + \"\"\n  - \"there\"\n",
+    ]);
+}
+
+#[test]
+fn runaway_and_unknown_labels_fail() {
+    let results = failures("\
+program = math.ku
+
+[loops]
+call = FOREVER
+steps = 50
+
+[missing]
+call = NOWHERE
+");
+
+    assert_eq!(results, vec![
+        vec!["FOREVER did not return within 50 steps".to_string()],
+        vec!["No label named NOWHERE".to_string()],
+    ]);
+}
+
+#[test]
+fn records_how_a_program_ends() {
+    let container = container("IN $0x0020\nPRNT $0x0020\nLOAD 3 A\nTRAP 0xF2\n");
+    let profile = MachineProfile { host_calls: true, ..MachineProfile::default() };
+    let recorded = regression::record(&container, &profile, &["Ada".to_string()], 1000).unwrap();
+
+    assert_eq!(recorded, "\
+stop: exit 3
+registers: A=03 B=00 C=00 H=00 L=00 I=00 J=00
+flags: -----
+output:
+Ada
+");
+
+    let stalled = regression::record(&container, &profile, &[], 1000).unwrap();
+    assert!(stalled.starts_with("stop: fault: No more input at 0x8000\n"));
+}
+
+#[test]
+fn bless_writes_the_expected_file() {
+    let program = std::env::temp_dir().join("chiiko_regression_bless.ku");
+    let _ = std::fs::remove_file(program.with_extension("expected"));
+
+    assert_eq!(regression::compare(&program, "stop: halted\n", false), Ok(Verdict::Missing));
+    assert_eq!(regression::compare(&program, "stop: halted\n", true), Ok(Verdict::Blessed));
+    assert_eq!(regression::compare(&program, "stop: halted\n", false), Ok(Verdict::Passed));
+    assert_eq!(
+        regression::compare(&program, "stop: exit 1\n", false),
+        Ok(Verdict::Failed("  - \"stop: halted\"\n  + \"stop: exit 1\"\n    \"\"\n".to_string()))
+    );
+}
+
+#[test]
+fn corpus_names_its_own_profile() {
+    let corpus = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test_binaries"));
+    let profile = regression::profile(corpus).unwrap();
+
+    assert!(MachineProfile::from_file(&profile.to_string_lossy()).unwrap().host_calls);
+    assert_eq!(regression::profile(&corpus.join("missing")), None);
+}
@@ -0,0 +1,416 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Read, Write};
+
+use crate::chiiko::Chiiko;
+use crate::chiiko::components::{chip::Chip, console::Console};
+use crate::disassembler::{Disassembler, Line};
+use crate::testing::suite::FLAGS;
+use crate::tui::terminal::{self, BOLD, DEFAULT_SIZE, ENTER_SCREEN, HOME, INVERSE, Key, LEAVE_SCREEN, RESET, RawMode};
+
+const STEPS_PER_FRAME: usize = 2000;
+const MINIMUM_SIZE: (usize, usize) = (60, 20);
+const BOTTOM_HEIGHT: usize = 10;
+const REGISTER_LINES: usize = 4;
+
+const HELP: &str = "s step  r run  p pause  b breakpoint  j/k move  PgUp/PgDn memory  i input  q quit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Paused,
+    Running,
+    Typing, // Reading a line of console input for the program
+}
+
+// A full-screen front end: disassembly around the PC, registers and flags, the stack,
+// a memory view and the program's console output, redrawn as the machine runs.
+pub struct Tui {
+    chiiko: Chiiko,
+    disassembler: Disassembler,
+    listing: Vec<Line>,         // The program disassembled once, for moving around the code
+    breakpoints: BTreeSet<u16>,
+    cursor: u16,                // Where breakpoints are toggled; follows the PC when it moves
+    memory_start: u16,
+    state: State,
+    typed: String,
+    message: String,
+    size: (usize, usize),
+}
+
+impl Tui {
+    pub fn new(
+    mut chiiko: Chiiko,
+    image: &[u8],
+    origin: u16,
+    labels: HashMap<u16, String>
+    ) -> Self {
+        // Input is typed into the UI, and output is drawn in its own pane
+        chiiko.cpu.console = Console::buffered(&[]);
+
+        let disassembler = Disassembler::with_labels(labels);
+        let listing = disassembler.disassemble(image, origin);
+        let cursor = chiiko.cpu.program_counter;
+
+        Self {
+            chiiko,
+            disassembler,
+            listing,
+            breakpoints: BTreeSet::new(),
+            cursor,
+            memory_start: 0,
+            state: State::Paused,
+            typed: String::new(),
+            message: HELP.to_string(),
+            size: DEFAULT_SIZE,
+        }
+    }
+
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        self.size = (columns.max(MINIMUM_SIZE.0), rows.max(MINIMUM_SIZE.1));
+    }
+
+    // Handles one key press. Returns false when the user quits.
+    pub fn key(&mut self, key: Key) -> bool {
+        if self.state == State::Typing {
+            self.type_key(key);
+            return true
+        }
+
+        match key {
+            Key::Character('q') => return false,
+            Key::Character('s' | ' ') => {
+                self.state = State::Paused;
+                self.step();
+            },
+            Key::Character('r') if !self.chiiko.is_halted() => {
+                self.state = State::Running;
+                self.message = "Running".to_string();
+            },
+            Key::Character('p') => {
+                self.state = State::Paused;
+                self.message = "Paused".to_string();
+                self.cursor = self.chiiko.cpu.program_counter;
+            },
+            Key::Character('b') => {
+                if self.breakpoints.contains(&self.cursor) {
+                    self.breakpoints.remove(&self.cursor);
+                } else {
+                    self.breakpoints.insert(self.cursor);
+                }
+            },
+            Key::Character('j') | Key::Down => self.move_cursor(1),
+            Key::Character('k') | Key::Up => self.move_cursor(-1),
+            Key::PageDown => self.memory_start = self.memory_start.wrapping_add(self.memory_page()),
+            Key::PageUp => self.memory_start = self.memory_start.wrapping_sub(self.memory_page()),
+            Key::Character('i') => {
+                self.state = State::Typing;
+                self.typed.clear();
+            },
+            _ => (),
+        }
+
+        true
+    }
+
+    // Runs a batch of instructions while running, so the screen keeps up with the program
+    pub fn tick(&mut self) {
+        if self.state != State::Running {
+            return
+        }
+
+        for _ in 0..STEPS_PER_FRAME {
+            if !self.step() {
+                self.state = State::Paused;
+                return
+            }
+
+            if self.breakpoints.contains(&self.chiiko.cpu.program_counter) {
+                self.state = State::Paused;
+                self.message = format!("Breakpoint at {:#06X}", self.chiiko.cpu.program_counter);
+                return
+            }
+        }
+    }
+
+    // Returns false when the machine cannot go on. A faulting instruction is left under the
+    // PC, so one waiting for input runs again once a line has been typed.
+    fn step(&mut self) -> bool {
+        let address = self.chiiko.cpu.program_counter;
+        let result = match self.chiiko.is_halted() {
+            true => Err("Halted".to_string()),
+            false => self.chiiko.step(),
+        };
+        if result.is_err() {
+            self.chiiko.cpu.set_pc(address);
+        }
+        // A halted machine keeps the cursor on the instruction that stopped it
+        if !self.chiiko.is_halted() {
+            self.cursor = self.chiiko.cpu.program_counter;
+        }
+
+        match result {
+            Ok(()) if self.chiiko.is_halted() => {
+                self.message = match self.chiiko.cpu.exit_code {
+                    Some(code) => format!("Exited with code {}", code),
+                    None => "Halted".to_string(),
+                };
+                false
+            },
+            Ok(()) => true,
+            Err(error) => {
+                self.message = match error.starts_with("No more input") {
+                    true => "The program is waiting for input: press i to type a line".to_string(),
+                    false => error,
+                };
+                false
+            },
+        }
+    }
+
+    fn type_key(&mut self, key: Key) {
+        match key {
+            Key::Enter => {
+                if let Console::Buffered { input, .. } = &mut self.chiiko.cpu.console {
+                    input.push_back(self.typed.clone());
+                }
+                self.state = State::Paused;
+                self.message = format!("Queued input: {}", self.typed);
+            },
+            Key::Escape => {
+                self.state = State::Paused;
+                self.message = HELP.to_string();
+            },
+            Key::Backspace => {
+                self.typed.pop();
+            },
+            Key::Character(character) if !character.is_control() => self.typed.push(character),
+            _ => (),
+        }
+    }
+
+    fn move_cursor(&mut self, offset: isize) {
+        let Some(index) = self.listing.iter().position(|line| line.address == self.cursor) else {
+            return
+        };
+
+        let index = index.saturating_add_signed(offset).min(self.listing.len() - 1);
+        self.cursor = self.listing[index].address;
+    }
+
+    fn memory_page(&self) -> u16 {
+        (self.memory_row_width() * (BOTTOM_HEIGHT - 1)) as u16
+    }
+
+    fn memory_row_width(&self) -> usize {
+        match self.left_width() >= 6 + 16 * 3 {
+            true => 16,
+            false => 8,
+        }
+    }
+
+    fn left_width(&self) -> usize {
+        self.size.0 / 2
+    }
+
+    // The whole screen, drawn from the top left corner over whatever was there
+    pub fn render(&self) -> String {
+        let (columns, rows) = self.size;
+        let left = self.left_width();
+        let right = columns - left - 1;
+        let top = rows - 2 - BOTTOM_HEIGHT;
+
+        let stack_height = top - REGISTER_LINES - 1;
+        let mut upper_right = pane("Registers", self.registers(), right, REGISTER_LINES + 1);
+        upper_right.extend(pane("Stack", self.stack(stack_height - 1), right, stack_height));
+
+        let columns_of = |first: Vec<String>, second: Vec<String>| -> Vec<String> {
+            first.into_iter().zip(second).map(|(first, second)| format!("{} {}", first, second)).collect()
+        };
+
+        let mut screen = vec![self.title(columns)];
+        screen.extend(columns_of(pane("Disassembly", self.disassembly(top - 1), left, top), upper_right));
+        screen.extend(columns_of(
+            pane("Memory", self.memory(BOTTOM_HEIGHT - 1), left, BOTTOM_HEIGHT),
+            pane("Console", self.console(BOTTOM_HEIGHT - 1, right), right, BOTTOM_HEIGHT),
+        ));
+        screen.push(match self.state {
+            State::Typing => fit(&format!("Input: {}_", self.typed), columns),
+            _ => fit(&self.message, columns),
+        });
+
+        format!("{}{}", HOME, screen.join("\r\n"))
+    }
+
+    fn title(&self, columns: usize) -> String {
+        let state = match (self.state, self.chiiko.is_halted()) {
+            (_, true) => "halted",
+            (State::Running, _) => "running",
+            (State::Typing, _) => "input",
+            (State::Paused, _) => "paused",
+        };
+        let text = format!(" chiiko  {}  cycles {}", state, self.chiiko.cpu.cycle_count);
+        format!("{}{}{}", INVERSE, fit(&text, columns), RESET)
+    }
+
+    // Lines around the cursor, from the program listing when the cursor is in it and decoded
+    // from live memory otherwise
+    fn disassembly(&self, height: usize) -> Vec<(String, Style)> {
+        let lines: Vec<Line> = match self.listing.iter().position(|line| line.address == self.cursor) {
+            Some(index) => {
+                let start = index.saturating_sub(height / 3);
+                self.listing[start..].iter().take(height).cloned().collect()
+            },
+            None => {
+                let mut address = self.cursor;
+                let read = |address: u16| self.chiiko.cpu.read(address);
+                (0..height)
+                    .map(|_| {
+                        let line = self.disassembler.decode(&read, address);
+                        address = address.wrapping_add(line.bytes.len().max(1) as u16);
+                        line
+                    })
+                    .collect()
+            },
+        };
+
+        lines
+            .iter()
+            .map(|line| {
+                let current = line.address == self.cursor && self.chiiko.is_halted()
+                    || line.address == self.chiiko.cpu.program_counter;
+                let marker = match (current, self.breakpoints.contains(&line.address)) {
+                    (true, true) => "*>",
+                    (true, false) => " >",
+                    (false, true) => "* ",
+                    (false, false) => "  ",
+                };
+                let style = match (current, line.address == self.cursor) {
+                    (true, _) => Style::Highlight,
+                    (false, true) => Style::Bold,
+                    _ => Style::Plain,
+                };
+                (format!("{} {}", marker, line), style)
+            })
+            .collect()
+    }
+
+    fn registers(&self) -> Vec<(String, Style)> {
+        let cpu = &self.chiiko.cpu;
+        let flags: String = FLAGS
+            .iter()
+            .map(|(name, bit)| if cpu.status & bit != 0 { name } else { "-" })
+            .collect();
+
+        vec![
+            format!("A={:02X} B={:02X} C={:02X} H={:02X}", cpu.accumulator, cpu.b_register, cpu.c_register, cpu.h_register),
+            format!("L={:02X} I={:02X} J={:02X}", cpu.l_register, cpu.i_register, cpu.j_register),
+            format!("PC={:04X} SP={:04X} FP={:04X}", cpu.program_counter, cpu.stack_pointer(), cpu.frame_pointer()),
+            format!("F={} ({:08b})", flags, cpu.status),
+        ]
+        .into_iter()
+        .map(|line| (line, Style::Plain))
+        .collect()
+    }
+
+    // From the top of the stack, just above the stack pointer, down to the stack's base
+    fn stack(&self, height: usize) -> Vec<(String, Style)> {
+        let top = self.chiiko.cpu.stack_pointer().wrapping_add(1);
+        let base = self.chiiko.cpu.stack_address();
+        if top > base {
+            return vec![("(empty)".to_string(), Style::Plain)]
+        }
+
+        (top..=base)
+            .take(height)
+            .map(|address| (format!("{:04X}  {:02X}", address, self.chiiko.cpu.read(address)), Style::Plain))
+            .collect()
+    }
+
+    fn memory(&self, height: usize) -> Vec<(String, Style)> {
+        let width = self.memory_row_width();
+
+        (0..height)
+            .map(|row| {
+                let start = self.memory_start.wrapping_add((row * width) as u16);
+                let bytes: Vec<String> = (0..width as u16)
+                    .map(|offset| format!("{:02X}", self.chiiko.cpu.read(start.wrapping_add(offset))))
+                    .collect();
+                (format!("{:04X}  {}", start, bytes.join(" ")), Style::Plain)
+            })
+            .collect()
+    }
+
+    // The last lines printed, with long lines wrapped to the pane
+    fn console(&self, height: usize, width: usize) -> Vec<(String, Style)> {
+        let mut lines: Vec<String> = Vec::new();
+        for line in self.chiiko.cpu.console.output().split('\n') {
+            let characters: Vec<char> = line.chars().collect();
+            if characters.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(characters.chunks(width.max(1)).map(|chunk| chunk.iter().collect()));
+        }
+
+        let start = lines.len().saturating_sub(height);
+        lines[start..].iter().map(|line| (line.to_string(), Style::Plain)).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Style {
+    Plain,
+    Bold,
+    Highlight,
+}
+
+// A titled box of exactly `height` rows, each `width` characters wide once styles are removed
+fn pane(title: &str, lines: Vec<(String, Style)>, width: usize, height: usize) -> Vec<String> {
+    let mut rows = vec![format!("{}{}{}", BOLD, fit(&format!("── {} {}", title, "─".repeat(width)), width), RESET)];
+
+    for index in 0..height - 1 {
+        let (text, style) = lines.get(index).cloned().unwrap_or((String::new(), Style::Plain));
+        rows.push(match style {
+            Style::Plain => fit(&text, width),
+            Style::Bold => format!("{}{}{}", BOLD, fit(&text, width), RESET),
+            Style::Highlight => format!("{}{}{}", INVERSE, fit(&text, width), RESET),
+        });
+    }
+
+    rows
+}
+
+// Pads or cuts text to exactly `width` characters
+fn fit(text: &str, width: usize) -> String {
+    let mut text: String = text.chars().take(width).collect();
+    let length = text.chars().count();
+    text.push_str(&" ".repeat(width - length));
+    text
+}
+
+// Takes over the terminal until the user quits
+pub fn run(mut tui: Tui) -> io::Result<()> {
+    let raw_mode = RawMode::enter()?;
+    let mut output = io::stdout().lock();
+    let mut input = io::stdin().lock();
+    write!(output, "{}", ENTER_SCREEN)?;
+
+    let mut buffer = [0; 32];
+    'frames: loop {
+        let (columns, rows) = terminal::size();
+        tui.resize(columns, rows);
+        write!(output, "{}", tui.render())?;
+        output.flush()?;
+
+        let count = input.read(&mut buffer)?;
+        for key in Key::parse(&buffer[..count]) {
+            if !tui.key(key) {
+                break 'frames
+            }
+        }
+        tui.tick();
+    }
+
+    write!(output, "{}", LEAVE_SCREEN)?;
+    output.flush()?;
+    drop(raw_mode);
+    Ok(())
+}
@@ -0,0 +1,7 @@
+mod core;
+pub mod terminal;
+
+#[cfg(test)]
+mod test;
+
+pub use core::{Tui, run};
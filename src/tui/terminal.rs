@@ -0,0 +1,98 @@
+use std::io;
+use std::process::{Command, Stdio};
+
+pub const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l"; // Alternate screen, hidden cursor
+pub const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+pub const HOME: &str = "\x1b[H";
+pub const INVERSE: &str = "\x1b[7m";
+pub const BOLD: &str = "\x1b[1m";
+pub const RESET: &str = "\x1b[0m";
+
+pub const DEFAULT_SIZE: (usize, usize) = (80, 24); // Columns and rows
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Character(char),
+    Enter,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
+impl Key {
+    // Splits what one read returned into keys. Escape sequences are assumed to arrive whole,
+    // which holds for terminals writing to a local pty.
+    pub fn parse(bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        let mut index = 0;
+
+        while index < bytes.len() {
+            let rest = &bytes[index..];
+            let (key, length) = match rest {
+                [0x1b, b'[', b'A', ..] => (Key::Up, 3),
+                [0x1b, b'[', b'B', ..] => (Key::Down, 3),
+                [0x1b, b'[', b'5', b'~', ..] => (Key::PageUp, 4),
+                [0x1b, b'[', b'6', b'~', ..] => (Key::PageDown, 4),
+                [0x1b, b'[', ..] => (Key::Escape, rest.len()), // Anything else is ignored whole
+                [0x1b, ..] => (Key::Escape, 1),
+                [b'\r' | b'\n', ..] => (Key::Enter, 1),
+                [0x7f | 0x08, ..] => (Key::Backspace, 1),
+                [byte, ..] if byte.is_ascii() => (Key::Character(*byte as char), 1),
+                _ => {
+                    index += 1;
+                    continue
+                },
+            };
+
+            keys.push(key);
+            index += length;
+        }
+
+        keys
+    }
+}
+
+// Puts the terminal in raw mode for as long as it lives. Reads then return each key as it
+// is pressed, or nothing after a tenth of a second, so the screen can keep updating while
+// a program runs.
+pub struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    pub fn enter() -> io::Result<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo", "min", "0", "time", "1"])?;
+        Ok(Self { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+// Columns and rows
+pub fn size() -> (usize, usize) {
+    let Ok(size) = stty(&["size"]) else {
+        return DEFAULT_SIZE
+    };
+
+    match size.split_whitespace().map(|number| number.parse()).collect::<Vec<_>>()[..] {
+        [Ok(rows), Ok(columns)] if rows > 0 && columns > 0 => (columns, rows),
+        _ => DEFAULT_SIZE,
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed: standard input is not a terminal"))
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::assembler::encoder::{Encoder, symbol_table::SymbolTable};
+use crate::assembler::lexer::Lexer;
+use crate::assembler::parser::Parser;
+use crate::chiiko::{Chiiko, MachineProfile};
+use crate::tui::{Tui, terminal::Key};
+
+// INC B, INC B, HALT
+const PROGRAM: &[u8] = &[0x85, 0x2A, 0x01, 0x85, 0x2A, 0x01, 0x70];
+
+fn tui(program: &[u8]) -> Tui {
+    let mut rom = vec![0; 0x8000];
+    rom[..program.len()].copy_from_slice(program);
+    let chiiko = Chiiko::from_profile(&MachineProfile::default(), &rom).unwrap();
+
+    Tui::new(chiiko, program, 0x8000, HashMap::from([(0x8003, "AGAIN".to_string())]))
+}
+
+fn plain(screen: &str) -> String {
+    let mut text = String::new();
+    let mut characters = screen.chars();
+
+    while let Some(character) = characters.next() {
+        if character == '\x1b' {
+            characters.by_ref().find(|character| character.is_ascii_alphabetic());
+        } else if character != '\r' {
+            text.push(character);
+        }
+    }
+
+    text
+}
+
+#[test]
+fn parses_keys_and_escape_sequences() {
+    assert_eq!(
+        Key::parse(b"s\x1b[A\x1b[6~\r\x7f\x1b"),
+        vec![Key::Character('s'), Key::Up, Key::PageDown, Key::Enter, Key::Backspace, Key::Escape]
+    );
+}
+
+#[test]
+fn renders_every_pane_at_the_terminal_size() {
+    let mut tui = tui(PROGRAM);
+    tui.resize(100, 30);
+    let screen = plain(&tui.render());
+    let lines: Vec<&str> = screen.lines().collect();
+
+    assert_eq!(lines.len(), 30);
+    assert!(lines.iter().all(|line| line.chars().count() == 100));
+    for title in ["Disassembly", "Registers", "Stack", "Memory", "Console"] {
+        assert!(screen.contains(&format!("── {} ─", title)), "{} pane is missing", title);
+    }
+    assert!(screen.contains(" > 8000  85 2A 01"));
+    assert!(screen.contains("PC=8000 SP=1FFF"));
+    assert!(screen.contains("(empty)"));
+}
+
+#[test]
+fn steps_and_highlights_the_new_line() {
+    let mut tui = tui(PROGRAM);
+
+    assert!(tui.key(Key::Character('s')));
+    assert!(plain(&tui.render()).contains("A=00 B=01"));
+    assert!(tui.render().contains("\x1b[7m > 8003"));
+    assert!(!tui.key(Key::Character('q')));
+}
+
+#[test]
+fn runs_until_a_toggled_breakpoint() {
+    let mut tui = tui(PROGRAM);
+
+    tui.key(Key::Down);
+    tui.key(Key::Character('b'));
+    tui.key(Key::Character('r'));
+    tui.tick();
+    assert!(plain(&tui.render()).contains("PC=8003"));
+    assert!(plain(&tui.render()).contains("Breakpoint at 0x8003"));
+
+    tui.key(Key::Character('b'));
+    tui.key(Key::Character('r'));
+    tui.tick();
+    assert!(plain(&tui.render()).contains("PC=FFFF"));
+    assert!(plain(&tui.render()).contains("chiiko  halted"));
+}
+
+#[test]
+fn typed_lines_feed_a_program_waiting_for_input() {
+    let mut lexer = Lexer::new("IN $0x0020\nPRNT $0x0020\nHALT\n");
+    let mut parser = Parser::new(lexer.lex());
+    parser.parse();
+    let mut table = SymbolTable::from_ast(&parser.instructions);
+    let program = Encoder::encode(&parser.instructions, &mut table).unwrap();
+    let length = program.rom.iter().rposition(|byte| *byte != 0).unwrap() + 1;
+    let mut tui = tui(&program.rom[..length]);
+
+    tui.key(Key::Character('r'));
+    tui.tick();
+    assert!(plain(&tui.render()).contains("PC=8000"));
+
+    for key in Key::parse(b"iHi\r") {
+        tui.key(key);
+    }
+    tui.key(Key::Character('r'));
+    tui.tick();
+    assert!(plain(&tui.render()).contains("PC=FFFF"));
+    let screen = plain(&tui.render());
+    assert!(screen.contains(" Hi  "));
+    assert!(screen.contains(" > 8004  70"));
+}
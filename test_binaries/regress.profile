@@ -0,0 +1,3 @@
+# The machine `chiiko regress test_binaries` runs these programs on: the standard machine,
+# with host calls so programs can end with TRAP 0xF2 and report an exit code.
+host_calls = 1